@@ -0,0 +1,151 @@
+//! Compares the indexed pattern matcher against the previous all-relations
+//! backtracking scan. Run with `cargo bench --bench pattern_matching`.
+//!
+//! Both matchers are timed on graphs of up to 10^5 relations. The scan is O(R^k) for
+//! a k-relation pattern, so a scan run is abandoned after `SCAN_DEADLINE`; its time
+//! and the speedup are then reported as lower bounds, marked `>=`, along with the
+//! share of candidates for the first pattern relation it got through.
+
+use std::time::{Duration, Instant};
+
+use wolfram_sim_rust::matching::find_pattern_matches;
+use wolfram_sim_rust::rules::pattern::{Binding, Pattern, PatternElement};
+use wolfram_sim_rust::{AtomId, Hypergraph, RelationId};
+
+/// Longest a single scan run may take before it is abandoned.
+const SCAN_DEADLINE: Duration = Duration::from_secs(10);
+
+/// Builds a random-looking but reproducible graph of binary relations in which
+/// every atom has out-degree 2, so `{{x,y},{y,z}}` has about 4 matches per atom.
+fn build_graph(relation_count: usize) -> Hypergraph {
+    let atom_count = relation_count / 2;
+    let mut hypergraph = Hypergraph::with_capacity(atom_count, relation_count);
+    let atoms: Vec<AtomId> = (0..atom_count).map(|_| hypergraph.create_atom()).collect();
+    let mut state: u64 = 0x2545F4914F6CDD1D;
+    for i in 0..relation_count {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let from = atoms[i / 2];
+        let to = atoms[(state % atom_count as u64) as usize];
+        hypergraph.create_relation(vec![from, to]);
+    }
+    hypergraph
+}
+
+/// The previous matcher: tries every relation for every pattern relation, cloning
+/// the binding at each level. If it is still running at `deadline`, returns the
+/// share of candidates for the first pattern relation it got through as the error.
+fn scan_matches(pattern: &Pattern, hypergraph: &Hypergraph, deadline: Instant) -> Result<usize, f64> {
+    fn extend(
+        pattern: &Pattern,
+        hypergraph: &Hypergraph,
+        index: usize,
+        binding: &Binding,
+        matched: &mut Vec<RelationId>,
+        all_relations: &[RelationId],
+        deadline: Instant,
+    ) -> Result<usize, f64> {
+        if index == pattern.len() {
+            return Ok(1);
+        }
+        let pattern_relation = &pattern.relations()[index];
+        let mut count = 0;
+        for (position, &relation_id) in all_relations.iter().enumerate() {
+            // Checked for the first pattern relation only, where each candidate is
+            // followed by a scan of every relation, so the check costs nothing
+            if index == 0 && Instant::now() >= deadline {
+                return Err(position as f64 / all_relations.len() as f64);
+            }
+            if matched.contains(&relation_id) {
+                continue;
+            }
+            let relation = hypergraph.get_relation(relation_id).unwrap();
+            if relation.arity() != pattern_relation.arity() {
+                continue;
+            }
+            let mut next = binding.clone();
+            let consistent = pattern_relation.elements().iter().zip(relation.atoms()).all(|(element, &atom)| {
+                match element {
+                    PatternElement::Atom(pattern_atom) => *pattern_atom == atom,
+                    PatternElement::Variable(var) => next.bind(var.clone(), atom),
+                }
+            });
+            if consistent {
+                matched.push(relation_id);
+                count += extend(pattern, hypergraph, index + 1, &next, matched, all_relations, deadline)?;
+                matched.pop();
+            }
+        }
+        Ok(count)
+    }
+
+    let all_relations: Vec<RelationId> = hypergraph.relation_ids().copied().collect();
+    extend(pattern, hypergraph, 0, &Binding::new(), &mut Vec::new(), &all_relations, deadline)
+}
+
+/// Times the scan like `time`, unless one run exceeds `SCAN_DEADLINE`. Returns the
+/// result of the scan, and the mean time per run or, for an abandoned run, the time
+/// it ran for.
+fn time_scan(budget: Duration, pattern: &Pattern, hypergraph: &Hypergraph) -> (Result<usize, f64>, Duration) {
+    let start = Instant::now();
+    match scan_matches(pattern, hypergraph, start + SCAN_DEADLINE) {
+        Ok(matches) if start.elapsed() < budget => {
+            let (result, mean) = time(budget, || scan_matches(pattern, hypergraph, Instant::now() + SCAN_DEADLINE));
+            (result.map(|_| matches), mean)
+        }
+        result => (result, start.elapsed()),
+    }
+}
+
+/// Runs `f` repeatedly for at least `budget`, returning its result and the mean time per run.
+fn time<T>(budget: Duration, mut f: impl FnMut() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let mut runs = 0u32;
+    loop {
+        let result = f();
+        runs += 1;
+        if start.elapsed() >= budget {
+            return (result, start.elapsed() / runs);
+        }
+    }
+}
+
+fn main() {
+    let patterns = ["{{x,y}}", "{{x,y},{y,z}}", "{{x,y},{x,z}}", "{{x,y},{y,z},{z,w}}"];
+    let budget = Duration::from_millis(500);
+
+    println!(
+        "{:<22} {:>9} {:>9} {:>14} {:>14} {:>10} {:>11}",
+        "pattern", "relations", "matches", "indexed", "scan", "speedup", "scan done"
+    );
+    for relation_count in [1_000, 10_000, 100_000] {
+        let hypergraph = build_graph(relation_count);
+        for text in patterns {
+            let pattern: Pattern = text.parse().unwrap();
+            let (matches, indexed) = time(budget, || find_pattern_matches(&pattern, &hypergraph).len());
+
+            let (scan_result, scan) = time_scan(budget, &pattern, &hypergraph);
+            let speedup = scan.as_secs_f64() / indexed.as_secs_f64();
+            let (scan, speedup, done) = match scan_result {
+                Ok(scan_matches) => {
+                    assert_eq!(scan_matches, matches, "matchers disagree on {}", text);
+                    (format!("{:.3?}", scan), format!("{:.1}x", speedup), 1.0)
+                }
+                Err(done) => (format!(">= {:.3?}", scan), format!(">= {:.0}x", speedup), done),
+            };
+
+            println!(
+                "{:<22} {:>9} {:>9} {:>14} {:>14} {:>10} {:>10.1}%",
+                text,
+                relation_count,
+                matches,
+                format!("{:.3?}", indexed),
+                scan,
+                speedup,
+                done * 100.0
+            );
+        }
+    }
+}
@@ -1,7 +1,6 @@
-use std::path::PathBuf;
 use wolfram_sim_rust::{
     simulation::{SimulationManager, ContinuousSimulationConfig},
-    serialization::{PersistenceManager, PredefinedExamples},
+    serialization::{PersistenceManager, PredefinedExamples, SaveConfig},
 };
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -112,27 +111,27 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // F1.7: Demonstrate persistence - saving and loading
     println!("💾 F1.7: Hypergraph Persistence");
-    let persistence_manager = PersistenceManager::new();
+    let mut persistence_manager = PersistenceManager::new();
     
     // Save the final state
     println!("Saving final simulation state...");
-    let save_path = persistence_manager.save_hypergraph_state(
+    let saved = persistence_manager.save_hypergraph_state(
         &result.final_state,
-        None, // Use default path
+        None, // Use a generated key
         None  // Use default config
     )?;
-    println!("  Saved to: {}", save_path.display());
+    println!("  Saved to: {}", persistence_manager.location(&saved.key));
     
-    // List saved files
-    let saved_files = persistence_manager.list_saved_hypergraphs()?;
-    println!("  Found {} saved hypergraph files:", saved_files.len());
-    for (i, file) in saved_files.iter().take(3).enumerate() {
-        println!("    {}: {}", i + 1, file.file_name().unwrap().to_str().unwrap());
+    // List saved states
+    let saved_states = persistence_manager.list_saved_hypergraphs()?;
+    println!("  Found {} saved hypergraphs:", saved_states.len());
+    for (i, info) in saved_states.iter().take(3).enumerate() {
+        println!("    {}: {} (step {}, {} atoms)", i + 1, info.key, info.step_number, info.atom_count);
     }
     
     // Load it back to verify persistence works
     println!("Loading saved state back...");
-    let loaded_state = persistence_manager.load_hypergraph_state(&save_path)?;
+    let loaded_state = persistence_manager.load_hypergraph_state(&saved.key)?;
     
     // Verify the loaded state matches what we saved
     let matches = loaded_state.atoms().len() == result.final_state.atoms().len() &&
@@ -149,17 +148,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Demonstrate loading from predefined examples through persistence
     println!("\nTesting persistence with predefined examples:");
     let example_state = PredefinedExamples::get_example("small_cycle").unwrap();
-    let temp_path = PathBuf::from("temp_example.json");
+    let temp_key = "temp_example";
     
     // Save and reload an example
-    persistence_manager.save_hypergraph_state(&example_state, Some(&temp_path), None)?;
-    let reloaded_example = persistence_manager.load_hypergraph_state(&temp_path)?;
+    let config = SaveConfig { overwrite_existing: true, ..SaveConfig::default() };
+    persistence_manager.save_hypergraph_state(&example_state, Some(temp_key), Some(config))?;
+    let reloaded_example = persistence_manager.load_hypergraph_state(temp_key)?;
     
     let example_matches = example_state == reloaded_example;
     println!("  Example save/load verification: {}", example_matches);
     
-    // Clean up temp file
-    std::fs::remove_file(&temp_path).ok();
+    // Clean up the temporary snapshot
+    persistence_manager.delete_hypergraph(temp_key).ok();
     
     println!("\n✅ Sprint 3 Demo Complete!");
     println!("   F1.5: Simulation Loop & Event Management - ✅ Working");
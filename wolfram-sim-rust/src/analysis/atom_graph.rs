@@ -0,0 +1,152 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::hypergraph::{AtomId, Hypergraph};
+use crate::simulation::SimulationRng;
+
+/// Undirected graph on the atoms of a hypergraph, in which two atoms are adjacent
+/// if some relation contains both (the 2-graph expansion). Atoms are indexed
+/// `0..atom_count()` in ID order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AtomGraph {
+    /// Atom at each index, sorted by ID
+    atoms: Vec<AtomId>,
+
+    /// Index of each atom
+    index: HashMap<AtomId, usize>,
+
+    /// Sorted, distinct neighbours of each atom, without self loops
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl AtomGraph {
+    /// Builds the 2-graph expansion of a hypergraph.
+    pub fn from_hypergraph(hypergraph: &Hypergraph) -> Self {
+        let mut atoms: Vec<AtomId> = hypergraph.atom_ids().copied().collect();
+        atoms.sort();
+        let index: HashMap<AtomId, usize> = atoms.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+        // Neighbours come from the hypergraph's atom-to-relations index
+        let neighbors = atoms
+            .iter()
+            .enumerate()
+            .map(|(a, &atom)| {
+                let mut list: Vec<usize> = hypergraph
+                    .find_relations_with_atom(atom)
+                    .into_iter()
+                    .flat_map(|relation| relation.atoms().iter().filter_map(|id| index.get(id).copied()))
+                    .filter(|&b| b != a)
+                    .collect();
+                list.sort_unstable();
+                list.dedup();
+                list
+            })
+            .collect();
+
+        AtomGraph { atoms, index, neighbors }
+    }
+
+    /// Returns the number of atoms.
+    pub fn atom_count(&self) -> usize {
+        self.atoms.len()
+    }
+
+    /// Returns the number of undirected edges.
+    pub fn edge_count(&self) -> usize {
+        self.neighbors.iter().map(Vec::len).sum::<usize>() / 2
+    }
+
+    /// Returns every undirected edge once, as `(a, b)` with `a < b`, in index order.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbors
+            .iter()
+            .enumerate()
+            .flat_map(|(a, list)| list.iter().filter(move |&&b| a < b).map(move |&b| (a, b)))
+    }
+
+    /// Returns the atom at `index`.
+    pub fn atom(&self, index: usize) -> AtomId {
+        self.atoms[index]
+    }
+
+    /// Returns the index of an atom, if it is in the graph.
+    pub fn index_of(&self, atom: AtomId) -> Option<usize> {
+        self.index.get(&atom).copied()
+    }
+
+    /// Returns the neighbours of the atom at `index`.
+    pub fn neighbors(&self, index: usize) -> &[usize] {
+        &self.neighbors[index]
+    }
+
+    /// Returns the degree of the atom at `index`.
+    pub fn degree(&self, index: usize) -> usize {
+        self.neighbors[index].len()
+    }
+
+    /// Returns the distance from `source` to every atom within `max_radius`,
+    /// or `None` for atoms further away or unreachable.
+    pub fn distances_from(&self, source: usize, max_radius: usize) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.atoms.len()];
+        distances[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[current].unwrap_or_default();
+            if distance == max_radius {
+                continue;
+            }
+            for &next in &self.neighbors[current] {
+                if distances[next].is_none() {
+                    distances[next] = Some(distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Returns the number of atoms within distance `r` of `source`, for `r` in `0..=max_radius`.
+    pub fn ball_volumes(&self, source: usize, max_radius: usize) -> Vec<usize> {
+        let mut volumes = vec![0; max_radius + 1];
+        for distance in self.distances_from(source, max_radius).into_iter().flatten() {
+            volumes[distance] += 1;
+        }
+        for r in 1..volumes.len() {
+            volumes[r] += volumes[r - 1];
+        }
+        volumes
+    }
+
+    /// Picks up to `count` distinct atom indices uniformly at random.
+    pub fn sample_atoms(&self, count: usize, rng: &mut SimulationRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.atoms.len()).collect();
+        rng.sample(&mut indices, count);
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_graph_expansion() {
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<AtomId> = (0..4).map(|_| hypergraph.create_atom()).collect();
+        hypergraph.create_relation(vec![atoms[0], atoms[1], atoms[2]]);
+        hypergraph.create_relation(vec![atoms[2], atoms[3]]);
+        hypergraph.create_relation(vec![atoms[3], atoms[3]]);
+
+        let graph = AtomGraph::from_hypergraph(&hypergraph);
+        assert_eq!(graph.atom_count(), 4);
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(graph.neighbors(2), &[0, 1, 3]);
+        assert_eq!(graph.edges().collect::<Vec<_>>(), vec![(0, 1), (0, 2), (1, 2), (2, 3)]);
+        assert_eq!(graph.ball_volumes(0, 3), vec![1, 3, 4, 4]);
+        assert_eq!(graph.distances_from(0, 1)[3], None);
+
+        let mut rng = SimulationRng::new(7);
+        let mut sample = graph.sample_atoms(10, &mut rng);
+        sample.sort();
+        assert_eq!(sample, vec![0, 1, 2, 3]);
+    }
+}
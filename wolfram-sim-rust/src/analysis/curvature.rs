@@ -0,0 +1,381 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::hypergraph::{AtomId, Hypergraph};
+use crate::rules::rule::RuleSet;
+use crate::serialization::{SimulationSession, SnapshotStore};
+use crate::simulation::{SimulationManager, SimulationRng, Timeline, CHECKPOINT_KEY_PREFIX};
+use super::atom_graph::AtomGraph;
+
+/// Settings for measuring discrete curvature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurvatureConfig {
+    /// Largest number of edges to measure; larger graphs are sampled
+    pub max_edges: usize,
+
+    /// Probability that the random walk defining Ollivier-Ricci curvature stays put (0 to 1)
+    pub idleness: f64,
+
+    /// Seed for choosing the sampled edges
+    pub seed: u64,
+}
+
+impl Default for CurvatureConfig {
+    fn default() -> Self {
+        CurvatureConfig {
+            max_edges: 256,
+            idleness: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Curvature of one edge of the 2-graph expansion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeCurvature {
+    /// One end of the edge
+    pub from: AtomId,
+
+    /// The other end of the edge
+    pub to: AtomId,
+
+    /// Ollivier-Ricci curvature, `1 - W1(m_from, m_to)`
+    pub ollivier_ricci: f64,
+
+    /// Augmented Forman curvature, `4 - deg(from) - deg(to) + 3 * triangles`
+    pub forman: f64,
+}
+
+/// Curvature measured on all or a sample of the edges of a hypergraph's 2-graph expansion.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CurvatureSummary {
+    /// Curvature of each measured edge
+    pub edges: Vec<EdgeCurvature>,
+
+    /// Number of edges in the whole 2-graph expansion
+    pub total_edges: usize,
+}
+
+impl CurvatureSummary {
+    /// Returns the mean Ollivier-Ricci curvature of the measured edges.
+    pub fn mean_ollivier_ricci(&self) -> Option<f64> {
+        self.mean(|edge| edge.ollivier_ricci)
+    }
+
+    /// Returns the mean Forman curvature of the measured edges.
+    pub fn mean_forman(&self) -> Option<f64> {
+        self.mean(|edge| edge.forman)
+    }
+
+    /// Returns true if only some of the edges were measured.
+    pub fn is_sampled(&self) -> bool {
+        self.edges.len() < self.total_edges
+    }
+
+    fn mean(&self, value: impl Fn(&EdgeCurvature) -> f64) -> Option<f64> {
+        if self.edges.is_empty() {
+            return None;
+        }
+        Some(self.edges.iter().map(value).sum::<f64>() / self.edges.len() as f64)
+    }
+}
+
+/// Measures Ollivier-Ricci and Forman curvature on the edges of the 2-graph expansion
+/// of a hypergraph. If it has more than `max_edges` edges, a sample chosen with the
+/// configured seed is measured, so the same hypergraph and seed give the same result.
+///
+/// Ollivier-Ricci curvature compares the random walk measures `m_x`, which stay at `x`
+/// with probability `idleness` and otherwise move to a uniformly chosen neighbour; the
+/// Wasserstein distance between them is solved exactly as a transport problem.
+pub fn measure_curvature(hypergraph: &Hypergraph, config: &CurvatureConfig) -> CurvatureSummary {
+    let graph = AtomGraph::from_hypergraph(hypergraph);
+    let mut edges: Vec<(usize, usize)> = graph.edges().collect();
+    let total_edges = edges.len();
+
+    if edges.len() > config.max_edges {
+        SimulationRng::new(config.seed).sample(&mut edges, config.max_edges);
+        edges.sort_unstable();
+    }
+
+    let edges = edges
+        .into_iter()
+        .map(|(a, b)| EdgeCurvature {
+            from: graph.atom(a),
+            to: graph.atom(b),
+            ollivier_ricci: ollivier_ricci(&graph, a, b, config.idleness),
+            forman: forman(&graph, a, b),
+        })
+        .collect();
+    CurvatureSummary { edges, total_edges }
+}
+
+/// Measures curvature at every checkpoint of a recorded run, in step order.
+pub fn curvature_at_checkpoints(
+    timeline: &Timeline,
+    rule_set: &RuleSet,
+    config: &CurvatureConfig,
+) -> Result<Vec<(u64, CurvatureSummary)>, String> {
+    timeline
+        .checkpoint_steps()
+        .map(|step| {
+            let hypergraph = timeline.state_at(step, rule_set)?.to_hypergraph();
+            Ok((step, measure_curvature(&hypergraph, config)))
+        })
+        .collect()
+}
+
+/// Measures curvature at every checkpoint a timeline saved in `store`, in step order,
+/// without replaying any events. For checkpoints written to a directory, pass a
+/// `FilesystemStore` for that directory.
+pub fn curvature_at_stored_checkpoints(
+    store: &dyn SnapshotStore,
+    config: &CurvatureConfig,
+) -> Result<Vec<(u64, CurvatureSummary)>, String> {
+    let mut checkpoints: Vec<_> = store
+        .list()
+        .map_err(|e| format!("Failed to list checkpoints: {}", e))?
+        .into_iter()
+        .filter(|info| info.key.starts_with(CHECKPOINT_KEY_PREFIX))
+        .collect();
+    checkpoints.sort_by_key(|info| info.step_number);
+    checkpoints
+        .into_iter()
+        .map(|info| {
+            let state = store
+                .get(&info.key)
+                .map_err(|e| format!("Failed to load checkpoint {}: {}", info.key, e))?;
+            Ok((info.step_number, measure_curvature(&state.to_hypergraph(), config)))
+        })
+        .collect()
+}
+
+/// Measures curvature along a saved session, replaying its events from the initial
+/// state. Measures the initial state, every step that is a multiple of `interval` and
+/// the current state, in step order.
+pub fn curvature_over_session(
+    session: &SimulationSession,
+    interval: u64,
+    config: &CurvatureConfig,
+) -> Result<Vec<(u64, CurvatureSummary)>, String> {
+    let interval = interval.max(1);
+    let mut manager = SimulationManager::from_state(&session.initial_state, session.rule_set.clone())?;
+    let mut results = vec![(manager.step_number(), measure_curvature(manager.hypergraph(), config))];
+    for (i, event) in session.events.iter().enumerate() {
+        manager.replay_event(event)?;
+        if manager.step_number().is_multiple_of(interval) || i + 1 == session.events.len() {
+            results.push((manager.step_number(), measure_curvature(manager.hypergraph(), config)));
+        }
+    }
+    Ok(results)
+}
+
+/// Augmented Forman curvature of the edge `a`-`b`, counting the triangles it lies on.
+fn forman(graph: &AtomGraph, a: usize, b: usize) -> f64 {
+    let triangles = graph.neighbors(a).iter().filter(|n| graph.neighbors(b).binary_search(n).is_ok()).count();
+    4.0 - graph.degree(a) as f64 - graph.degree(b) as f64 + 3.0 * triangles as f64
+}
+
+/// Ollivier-Ricci curvature of the edge `a`-`b`.
+fn ollivier_ricci(graph: &AtomGraph, a: usize, b: usize, idleness: f64) -> f64 {
+    let measure = |x: usize| {
+        let step = (1.0 - idleness) / graph.degree(x) as f64;
+        let mut mass: Vec<(usize, f64)> = graph.neighbors(x).iter().map(|&n| (n, step)).collect();
+        if idleness > 0.0 {
+            mass.push((x, idleness));
+        }
+        mass
+    };
+    let supply = measure(a);
+    let demand = measure(b);
+
+    // Supports of the two measures are at most 3 apart
+    let distances: Vec<HashMap<usize, usize>> = supply.iter().map(|&(x, _)| bounded_distances(graph, x, 3)).collect();
+    let cost = |i: usize, j: usize| distances[i].get(&demand[j].0).copied().unwrap_or(3) as f64;
+    1.0 - transport_cost(&supply, &demand, cost)
+}
+
+/// Breadth-first distances from `source` to the atoms within `max_radius`.
+fn bounded_distances(graph: &AtomGraph, source: usize, max_radius: usize) -> HashMap<usize, usize> {
+    let mut distances = HashMap::from([(source, 0)]);
+    let mut queue = VecDeque::from([source]);
+    while let Some(current) = queue.pop_front() {
+        let distance = distances[&current];
+        if distance == max_radius {
+            continue;
+        }
+        for &next in graph.neighbors(current) {
+            distances.entry(next).or_insert_with(|| {
+                queue.push_back(next);
+                distance + 1
+            });
+        }
+    }
+    distances
+}
+
+/// Masses below this are treated as zero.
+const EPSILON: f64 = 1e-12;
+
+/// Minimum cost of moving the `supply` masses onto the `demand` masses, where moving a
+/// unit from supply `i` to demand `j` costs `cost(i, j)`. Solved by successive shortest
+/// augmenting paths on the residual network.
+fn transport_cost(supply: &[(usize, f64)], demand: &[(usize, f64)], cost: impl Fn(usize, usize) -> f64) -> f64 {
+    struct Arc {
+        to: usize,
+        capacity: f64,
+        cost: f64,
+    }
+
+    // Nodes: source, supplies, demands, sink. Arc `k ^ 1` is the reverse of arc `k`.
+    let source = 0;
+    let sink = supply.len() + demand.len() + 1;
+    let mut arcs: Vec<Arc> = Vec::new();
+    let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); sink + 1];
+    let mut add_arc = |from: usize, to: usize, capacity: f64, cost: f64| {
+        outgoing[from].push(arcs.len());
+        arcs.push(Arc { to, capacity, cost });
+        outgoing[to].push(arcs.len());
+        arcs.push(Arc { to: from, capacity: 0.0, cost: -cost });
+    };
+    for (i, &(_, mass)) in supply.iter().enumerate() {
+        add_arc(source, 1 + i, mass, 0.0);
+        for j in 0..demand.len() {
+            add_arc(1 + i, 1 + supply.len() + j, f64::INFINITY, cost(i, j));
+        }
+    }
+    for (j, &(_, mass)) in demand.iter().enumerate() {
+        add_arc(1 + supply.len() + j, sink, mass, 0.0);
+    }
+
+    let mut total = 0.0;
+    loop {
+        // Bellman-Ford, as reverse arcs have negative costs
+        let mut distance = vec![f64::INFINITY; sink + 1];
+        let mut via: Vec<Option<usize>> = vec![None; sink + 1];
+        distance[source] = 0.0;
+        for _ in 0..=sink {
+            let mut changed = false;
+            for node in 0..=sink {
+                if distance[node].is_infinite() {
+                    continue;
+                }
+                for &k in &outgoing[node] {
+                    let arc = &arcs[k];
+                    if arc.capacity > EPSILON && distance[node] + arc.cost < distance[arc.to] - EPSILON {
+                        distance[arc.to] = distance[node] + arc.cost;
+                        via[arc.to] = Some(k);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        if via[sink].is_none() {
+            return total;
+        }
+
+        let mut path = Vec::new();
+        let mut node = sink;
+        while let Some(k) = via[node] {
+            path.push(k);
+            node = arcs[k ^ 1].to;
+        }
+        let amount = path.iter().map(|&k| arcs[k].capacity).fold(f64::INFINITY, f64::min);
+        for &k in &path {
+            arcs[k].capacity -= amount;
+            arcs[k ^ 1].capacity += amount;
+        }
+        total += amount * distance[sink];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::{FilesystemStore, PersistenceManager, PredefinedExamples};
+    use tempfile::TempDir;
+
+    fn from_edges(atom_count: u64, edges: &[(u64, u64)]) -> Hypergraph {
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<AtomId> = (0..atom_count).map(|_| hypergraph.create_atom()).collect();
+        for &(a, b) in edges {
+            hypergraph.create_relation(vec![atoms[a as usize], atoms[b as usize]]);
+        }
+        hypergraph
+    }
+
+    #[test]
+    fn test_known_curvatures() {
+        let config = CurvatureConfig::default();
+
+        // Complete graph K4: W1 = 1/3
+        let complete = from_edges(4, &[(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]);
+        let summary = measure_curvature(&complete, &config);
+        assert_eq!(summary.edges.len(), 6);
+        assert!((summary.mean_ollivier_ricci().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(summary.mean_forman(), Some(4.0));
+
+        // Long cycles are flat
+        let cycle: Vec<(u64, u64)> = (0..10).map(|i| (i, (i + 1) % 10)).collect();
+        let summary = measure_curvature(&from_edges(10, &cycle), &config);
+        assert!(summary.mean_ollivier_ricci().unwrap().abs() < 1e-9);
+        assert_eq!(summary.mean_forman(), Some(0.0));
+
+        // Trees are negatively curved: the middle edge of two joined stars
+        let stars = from_edges(6, &[(0, 1), (0, 2), (0, 3), (3, 4), (3, 5)]);
+        let middle = measure_curvature(&stars, &config).edges.into_iter().find(|e| e.from.value() == 0 && e.to.value() == 3).unwrap();
+        assert!((middle.ollivier_ricci - (-2.0 / 3.0)).abs() < 1e-9);
+        assert_eq!(middle.forman, -2.0);
+
+        // A lazier walk sees less of the tree around the middle edge
+        let lazy = measure_curvature(&stars, &CurvatureConfig { idleness: 0.5, ..config });
+        let middle = lazy.edges.into_iter().find(|e| e.from.value() == 0 && e.to.value() == 3).unwrap();
+        assert!((middle.ollivier_ricci - (-1.0 / 3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sampling_is_deterministic() {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.step_multiple(50);
+        let config = CurvatureConfig { max_edges: 10, seed: 3, ..Default::default() };
+
+        let first = measure_curvature(manager.hypergraph(), &config);
+        assert_eq!(first.edges.len(), 10);
+        assert!(first.is_sampled());
+        assert_eq!(measure_curvature(manager.hypergraph(), &config), first);
+        assert!(measure_curvature(&Hypergraph::new(), &config).mean_forman().is_none());
+    }
+
+    #[test]
+    fn test_curvature_at_checkpoints() {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_timeline(Timeline::new(10));
+        manager.step_multiple(25);
+
+        let results = curvature_at_checkpoints(manager.timeline(), manager.rule_set(), &CurvatureConfig::default()).unwrap();
+        assert_eq!(results.iter().map(|(step, _)| *step).collect::<Vec<_>>(), vec![0, 10, 20]);
+        assert_eq!(results[2].1.total_edges, 23);
+
+        // The same run, from its saved session
+        let session = SimulationSession::capture(&manager, Default::default()).unwrap();
+        let replayed = curvature_over_session(&session, 10, &CurvatureConfig::default()).unwrap();
+        assert_eq!(replayed.iter().map(|(step, _)| *step).collect::<Vec<_>>(), vec![0, 10, 20, 25]);
+        assert_eq!(replayed[..3], results[..]);
+    }
+
+    #[test]
+    fn test_curvature_at_stored_checkpoints() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_timeline(Timeline::with_persistence(10, PersistenceManager::with_save_directory(temp_dir.path())));
+        manager.step_multiple(25);
+        let expected = curvature_at_checkpoints(manager.timeline(), manager.rule_set(), &CurvatureConfig::default()).unwrap();
+        drop(manager);
+
+        // Other snapshots in the directory are not checkpoints
+        let mut store = FilesystemStore::new(temp_dir.path());
+        store.put("final", &PredefinedExamples::triangle(), &Default::default()).unwrap();
+        assert_eq!(curvature_at_stored_checkpoints(&store, &CurvatureConfig::default()).unwrap(), expected);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::hypergraph::Hypergraph;
+use crate::simulation::{SimulationManager, SimulationRng};
+use super::atom_graph::AtomGraph;
+
+/// Settings for estimating dimension from ball growth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DimensionConfig {
+    /// Number of atoms to grow balls from
+    pub sample_size: usize,
+
+    /// Largest ball radius to measure
+    pub max_radius: usize,
+
+    /// Seed for choosing the sampled atoms
+    pub seed: u64,
+}
+
+impl Default for DimensionConfig {
+    fn default() -> Self {
+        DimensionConfig {
+            sample_size: 32,
+            max_radius: 8,
+            seed: 0,
+        }
+    }
+}
+
+/// Effective dimension of a hypergraph estimated from how ball volumes grow with radius.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionEstimate {
+    /// Fitted growth exponent `d` in `V(r) ~ r^d`
+    pub dimension: f64,
+
+    /// Mean number of atoms within distance `r` of a sampled atom, for `r` in `0..=max_radius`
+    pub mean_ball_volumes: Vec<f64>,
+
+    /// Growth exponent between consecutive radii, starting with radii 1 and 2
+    pub local_dimensions: Vec<f64>,
+
+    /// Largest radius used in the fit
+    pub fitted_radius: usize,
+
+    /// Number of atoms balls were grown from
+    pub sampled_atoms: usize,
+}
+
+/// Estimates the effective dimension of a hypergraph from the growth of graph-distance
+/// balls in its 2-graph expansion.
+///
+/// Balls are grown from randomly sampled atoms and their mean volumes fitted to
+/// `V(r) ~ r^d` by least squares on a log-log scale. Radii are shifted by one half, which
+/// makes ball volumes in lattices almost exact powers (`2r + 1 = 2(r + 1/2)` on a line).
+/// Radii from the point where balls stop growing are left out. Returns `None` when fewer
+/// than two radii show growth, as in tiny or disconnected hypergraphs.
+pub fn estimate_dimension(hypergraph: &Hypergraph, config: &DimensionConfig) -> Option<DimensionEstimate> {
+    let graph = AtomGraph::from_hypergraph(hypergraph);
+    let mut rng = SimulationRng::new(config.seed);
+    let samples = graph.sample_atoms(config.sample_size, &mut rng);
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut mean_ball_volumes = vec![0.0; config.max_radius + 1];
+    for &source in &samples {
+        for (total, volume) in mean_ball_volumes.iter_mut().zip(graph.ball_volumes(source, config.max_radius)) {
+            *total += volume as f64;
+        }
+    }
+    for volume in &mut mean_ball_volumes {
+        *volume /= samples.len() as f64;
+    }
+
+    // Fit over radii 1..=fitted_radius, where the balls are still growing
+    let fitted_radius = (1..=config.max_radius)
+        .take_while(|&r| mean_ball_volumes[r] > mean_ball_volumes[r - 1])
+        .last()?;
+    if fitted_radius < 2 {
+        return None;
+    }
+
+    let point = |r: usize| ((r as f64 + 0.5).ln(), mean_ball_volumes[r].ln());
+    let points: Vec<(f64, f64)> = (1..=fitted_radius).map(point).collect();
+    let local_dimensions = points.windows(2).map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0)).collect();
+
+    Some(DimensionEstimate {
+        dimension: fit_slope(&points),
+        mean_ball_volumes,
+        local_dimensions,
+        fitted_radius,
+        sampled_atoms: samples.len(),
+    })
+}
+
+/// Estimates the dimension of the simulation state at each of `steps`, reconstructing
+/// past states from the manager's timeline.
+pub fn dimension_by_step<I>(
+    manager: &SimulationManager,
+    steps: I,
+    config: &DimensionConfig,
+) -> Result<Vec<(u64, Option<DimensionEstimate>)>, String>
+where
+    I: IntoIterator<Item = u64>,
+{
+    steps
+        .into_iter()
+        .map(|step| {
+            let hypergraph = manager.state_at_step(step)?.to_hypergraph();
+            Ok((step, estimate_dimension(&hypergraph, config)))
+        })
+        .collect()
+}
+
+/// Returns the least-squares slope of `y` against `x`.
+fn fit_slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    covariance / variance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypergraph::AtomId;
+    use crate::rules::rule::RuleSet;
+    use crate::serialization::PredefinedExamples;
+
+    /// Builds a `width` x `width` grid of binary relations wrapped into a torus.
+    fn torus(width: u64) -> Hypergraph {
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<AtomId> = (0..width * width).map(|_| hypergraph.create_atom()).collect();
+        for x in 0..width {
+            for y in 0..width {
+                let at = |x: u64, y: u64| atoms[((x % width) * width + y % width) as usize];
+                hypergraph.create_relation(vec![at(x, y), at(x + 1, y)]);
+                hypergraph.create_relation(vec![at(x, y), at(x, y + 1)]);
+            }
+        }
+        hypergraph
+    }
+
+    #[test]
+    fn test_lattice_dimensions() {
+        let config = DimensionConfig::default();
+
+        let mut cycle = Hypergraph::new();
+        let atoms: Vec<AtomId> = (0..100).map(|_| cycle.create_atom()).collect();
+        for i in 0..atoms.len() {
+            cycle.create_relation(vec![atoms[i], atoms[(i + 1) % atoms.len()]]);
+        }
+        let line = estimate_dimension(&cycle, &config).unwrap();
+        assert!((line.dimension - 1.0).abs() < 1e-9, "{}", line.dimension);
+        assert_eq!(line.mean_ball_volumes[3], 7.0);
+
+        let plane = estimate_dimension(&torus(40), &config).unwrap();
+        assert!((plane.dimension - 2.0).abs() < 0.1, "{}", plane.dimension);
+        assert_eq!(plane.local_dimensions.len(), config.max_radius - 1);
+        assert_eq!(plane.sampled_atoms, config.sample_size);
+    }
+
+    #[test]
+    fn test_saturated_and_tiny_graphs() {
+        // A 4x4 torus is covered by balls of radius 4
+        let small = estimate_dimension(&torus(4), &DimensionConfig::default()).unwrap();
+        assert_eq!(small.fitted_radius, 4);
+        assert_eq!(small.mean_ball_volumes[8], 16.0);
+
+        assert!(estimate_dimension(&Hypergraph::new(), &DimensionConfig::default()).is_none());
+        assert!(estimate_dimension(&PredefinedExamples::triangle().to_hypergraph(), &DimensionConfig::default()).is_none());
+    }
+
+    #[test]
+    fn test_dimension_by_step() {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.step_multiple(30);
+        let estimates = dimension_by_step(&manager, [0, 30], &DimensionConfig::default()).unwrap();
+        assert_eq!(estimates[0].0, 0);
+        assert!(estimates[0].1.is_none());
+        assert!(estimates[1].1.is_some());
+        assert!(dimension_by_step(&manager, [31], &DimensionConfig::default()).is_err());
+    }
+}
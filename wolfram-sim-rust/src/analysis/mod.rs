@@ -0,0 +1,10 @@
+pub mod atom_graph;
+pub mod dimension;
+pub mod curvature;
+
+pub use atom_graph::AtomGraph;
+pub use dimension::{DimensionConfig, DimensionEstimate, estimate_dimension, dimension_by_step};
+pub use curvature::{
+    CurvatureConfig, CurvatureSummary, EdgeCurvature, measure_curvature, curvature_at_checkpoints,
+    curvature_at_stored_checkpoints, curvature_over_session,
+};
@@ -0,0 +1,394 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+use crate::hypergraph::RelationId;
+use crate::simulation::history::events_to_forget;
+use crate::simulation::SimulationEvent;
+
+/// Identifies an event node in a causal graph, in the order events were added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CausalEventId(pub usize);
+
+impl CausalEventId {
+    /// Returns the inner value of the CausalEventId.
+    pub fn value(&self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for CausalEventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Event({})", self.0)
+    }
+}
+
+/// A causal dependency: `to` consumed a relation that `from` created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CausalEdge {
+    /// The event that created the relation
+    pub from: CausalEventId,
+
+    /// The event that consumed the relation
+    pub to: CausalEventId,
+
+    /// The relation passed from one event to the other
+    pub relation: RelationId,
+}
+
+/// The causal graph of an evolution: a DAG whose nodes are events and whose edges
+/// connect each event to the later events that consumed relations it created.
+/// Relations present in the initial state have no creator and produce no edges.
+///
+/// With a `max_events` limit, the oldest events are forgotten along with their edges,
+/// and relations they created are treated like initial ones. IDs of the remaining
+/// events do not change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CausalGraph {
+    /// ID of the oldest event kept
+    #[serde(default)]
+    first_id: usize,
+
+    /// Largest number of events kept, or `None` to keep every event
+    #[serde(default)]
+    max_events: Option<usize>,
+
+    /// Events in the order they were added, from `first_id` on
+    events: Vec<SimulationEvent>,
+
+    /// All causal edges, in the order they were discovered
+    edges: Vec<CausalEdge>,
+
+    /// For each event, the kept events it directly depends on
+    parents: Vec<Vec<CausalEventId>>,
+
+    /// For each event, the events that directly depend on it
+    children: Vec<Vec<CausalEventId>>,
+
+    /// Creator of every relation that has not been consumed yet
+    relation_creators: HashMap<RelationId, CausalEventId>,
+}
+
+impl CausalGraph {
+    /// Creates a new, empty causal graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a causal graph from a sequence of events in the order they occurred.
+    pub fn from_events<'a, I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = &'a SimulationEvent>,
+    {
+        let mut graph = Self::new();
+        for event in events {
+            graph.add_event(event.clone());
+        }
+        graph
+    }
+
+    /// Adds the next event of the evolution, connecting it to the events that
+    /// created the relations it removed. Returns the ID of the new node.
+    pub fn add_event(&mut self, event: SimulationEvent) -> CausalEventId {
+        let id = CausalEventId(self.first_id + self.events.len());
+        let mut parents = Vec::new();
+
+        for relation in event.relations_removed() {
+            if let Some(creator) = self.relation_creators.remove(relation) {
+                self.edges.push(CausalEdge { from: creator, to: id, relation: *relation });
+                if !parents.contains(&creator) {
+                    parents.push(creator);
+                    self.children[creator.0 - self.first_id].push(id);
+                }
+            }
+        }
+
+        for relation in event.relations_created() {
+            self.relation_creators.insert(*relation, id);
+        }
+
+        self.events.push(event);
+        self.parents.push(parents);
+        self.children.push(Vec::new());
+        self.forget_oldest();
+        id
+    }
+
+    /// Returns the largest number of events kept, if limited.
+    pub fn max_events(&self) -> Option<usize> {
+        self.max_events
+    }
+
+    /// Limits the number of events kept, forgetting the oldest events if needed.
+    pub fn set_max_events(&mut self, max_events: Option<usize>) {
+        self.max_events = max_events;
+        self.forget_oldest();
+    }
+
+    /// Forgets the oldest events if there are more than `max_events`.
+    fn forget_oldest(&mut self) {
+        let forget = events_to_forget(self.events.len(), self.max_events);
+        if forget == 0 {
+            return;
+        }
+        self.events.drain(..forget);
+        self.parents.drain(..forget);
+        self.children.drain(..forget);
+        self.first_id += forget;
+
+        let first = CausalEventId(self.first_id);
+        self.edges.retain(|edge| edge.from >= first);
+        for parents in &mut self.parents {
+            parents.retain(|parent| *parent >= first);
+        }
+        self.relation_creators.retain(|_, creator| *creator >= first);
+    }
+
+    /// Removes the most recently added event and its edges, restoring the graph to
+    /// its state before that event was added. Returns the removed event.
+    pub fn pop_event(&mut self) -> Option<SimulationEvent> {
+        let event = self.events.pop()?;
+        let id = CausalEventId(self.first_id + self.events.len());
+        
+        for relation in event.relations_created() {
+            self.relation_creators.remove(relation);
+        }
+        // The event's incoming edges were the last ones added
+        while self.edges.last().is_some_and(|edge| edge.to == id) {
+            let edge = self.edges.pop().unwrap();
+            self.relation_creators.insert(edge.relation, edge.from);
+        }
+        for parent in self.parents.pop().unwrap_or_default() {
+            self.children[parent.0 - self.first_id].retain(|child| *child != id);
+        }
+        self.children.pop();
+        Some(event)
+    }
+    
+    /// Returns the number of events in the graph.
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if the graph has no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns an event by its ID.
+    pub fn event(&self, id: CausalEventId) -> Option<&SimulationEvent> {
+        self.events.get(id.0.checked_sub(self.first_id)?)
+    }
+
+    /// Returns the ID of the oldest event kept, which is the first of `events()`.
+    pub fn first_event_id(&self) -> CausalEventId {
+        CausalEventId(self.first_id)
+    }
+
+    /// Returns all kept events in the order they were added.
+    pub fn events(&self) -> &[SimulationEvent] {
+        &self.events
+    }
+
+    /// Returns all causal edges.
+    pub fn edges(&self) -> &[CausalEdge] {
+        &self.edges
+    }
+
+    /// Finds the node for the event with the given step number.
+    pub fn find_by_step(&self, step_number: u64) -> Option<CausalEventId> {
+        self.events
+            .iter()
+            .position(|event| event.step_number() == step_number)
+            .map(|index| CausalEventId(self.first_id + index))
+    }
+
+    /// Returns the events the given event directly depends on.
+    pub fn parents(&self, id: CausalEventId) -> &[CausalEventId] {
+        Self::adjacent(&self.parents, self.first_id, id)
+    }
+
+    /// Returns the events that directly depend on the given event.
+    pub fn children(&self, id: CausalEventId) -> &[CausalEventId] {
+        Self::adjacent(&self.children, self.first_id, id)
+    }
+
+    /// Looks up the list of `id` in `adjacency`, which starts at `first_id`.
+    fn adjacent(adjacency: &[Vec<CausalEventId>], first_id: usize, id: CausalEventId) -> &[CausalEventId] {
+        id.0.checked_sub(first_id)
+            .and_then(|index| adjacency.get(index))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns every event the given event causally depends on, optionally limited
+    /// to `max_depth` causal steps. The event itself is not included.
+    pub fn past_light_cone(&self, id: CausalEventId, max_depth: Option<usize>) -> Vec<CausalEventId> {
+        self.light_cone(id, max_depth, &self.parents)
+    }
+
+    /// Returns every event that causally depends on the given event, optionally limited
+    /// to `max_depth` causal steps. The event itself is not included.
+    pub fn future_light_cone(&self, id: CausalEventId, max_depth: Option<usize>) -> Vec<CausalEventId> {
+        self.light_cone(id, max_depth, &self.children)
+    }
+
+    /// Breadth-first traversal along `adjacency`, returning the visited events sorted by ID.
+    fn light_cone(
+        &self,
+        id: CausalEventId,
+        max_depth: Option<usize>,
+        adjacency: &[Vec<CausalEventId>],
+    ) -> Vec<CausalEventId> {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((id, 0));
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            for &next in Self::adjacent(adjacency, self.first_id, current) {
+                if visited.insert(next) {
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+
+        let mut cone: Vec<_> = visited.into_iter().collect();
+        cone.sort();
+        cone
+    }
+
+    /// Clears all events and edges.
+    pub fn clear(&mut self) {
+        self.first_id = 0;
+        self.events.clear();
+        self.edges.clear();
+        self.parents.clear();
+        self.children.clear();
+        self.relation_creators.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypergraph::AtomId;
+    use crate::rules::RuleId;
+
+    fn event(step: u64, created: &[u64], removed: &[u64]) -> SimulationEvent {
+        SimulationEvent::new(
+            step,
+            RuleId::new(0),
+            vec![AtomId::new(step)],
+            created.iter().copied().map(RelationId::new).collect(),
+            removed.iter().copied().map(RelationId::new).collect(),
+        )
+    }
+
+    /// Initial relations 0 and 1. Event 0 splits 0 into 2,3; event 1 splits 1 into 4,5;
+    /// event 2 consumes 3 and 4; event 3 consumes 2.
+    fn diamond() -> CausalGraph {
+        let events = vec![
+            event(1, &[2, 3], &[0]),
+            event(2, &[4, 5], &[1]),
+            event(3, &[6], &[3, 4]),
+            event(4, &[7, 8], &[2]),
+        ];
+        CausalGraph::from_events(&events)
+    }
+
+    #[test]
+    fn test_edges_follow_consumed_relations() {
+        let graph = diamond();
+
+        assert_eq!(graph.event_count(), 4);
+        assert_eq!(graph.edges().len(), 3);
+        assert!(graph.parents(CausalEventId(0)).is_empty());
+        assert_eq!(graph.parents(CausalEventId(2)), &[CausalEventId(0), CausalEventId(1)]);
+        assert_eq!(graph.children(CausalEventId(0)), &[CausalEventId(2), CausalEventId(3)]);
+        assert!(graph.edges().contains(&CausalEdge {
+            from: CausalEventId(1),
+            to: CausalEventId(2),
+            relation: RelationId::new(4),
+        }));
+    }
+
+    #[test]
+    fn test_incremental_matches_batch() {
+        let batch = diamond();
+        let mut incremental = CausalGraph::new();
+        for event in batch.events() {
+            incremental.add_event(event.clone());
+        }
+        assert_eq!(incremental, batch);
+    }
+
+    #[test]
+    fn test_light_cones() {
+        let mut graph = diamond();
+        // Event 4 consumes relation 6 created by event 2
+        let last = graph.add_event(event(5, &[9], &[6]));
+
+        assert_eq!(
+            graph.past_light_cone(last, None),
+            vec![CausalEventId(0), CausalEventId(1), CausalEventId(2)]
+        );
+        assert_eq!(graph.past_light_cone(last, Some(1)), vec![CausalEventId(2)]);
+        assert_eq!(
+            graph.future_light_cone(CausalEventId(0), None),
+            vec![CausalEventId(2), CausalEventId(3), last]
+        );
+        assert!(graph.future_light_cone(last, None).is_empty());
+    }
+
+    #[test]
+    fn test_pop_event_restores_graph() {
+        let mut graph = diamond();
+        let before = graph.clone();
+        graph.add_event(event(5, &[9], &[6, 7]));
+        
+        assert_eq!(graph.pop_event().map(|e| e.step_number()), Some(5));
+        assert_eq!(graph, before);
+        
+        // Popping everything leaves an empty graph equal to a new one
+        while graph.pop_event().is_some() {}
+        assert_eq!(graph, CausalGraph::new());
+    }
+    
+    #[test]
+    fn test_find_by_step_and_clear() {
+        let mut graph = diamond();
+        assert_eq!(graph.find_by_step(3), Some(CausalEventId(2)));
+        assert_eq!(graph.find_by_step(99), None);
+
+        graph.clear();
+        assert!(graph.is_empty());
+        assert!(graph.edges().is_empty());
+    }
+
+    #[test]
+    fn test_max_events_forgets_oldest() {
+        let mut graph = diamond();
+        // Forgetting events 0 and 1 drops every edge into event 2
+        graph.set_max_events(Some(2));
+        assert_eq!(graph.event_count(), 2);
+        assert_eq!(graph.first_event_id(), CausalEventId(2));
+        assert!(graph.edges().is_empty());
+        assert!(graph.event(CausalEventId(0)).is_none());
+        assert!(graph.parents(CausalEventId(2)).is_empty());
+        assert_eq!(graph.find_by_step(3), Some(CausalEventId(2)));
+
+        // IDs keep counting, and relations created by kept events still link
+        let last = graph.add_event(event(5, &[9], &[6]));
+        assert_eq!(graph.first_event_id(), CausalEventId(3));
+        assert_eq!(last, CausalEventId(4));
+        assert!(graph.parents(last).is_empty());
+        let next = graph.add_event(event(6, &[10], &[9, 7]));
+        assert_eq!(graph.past_light_cone(next, None), vec![CausalEventId(4)]);
+        assert_eq!(graph.future_light_cone(CausalEventId(4), None), vec![next]);
+
+        assert_eq!(graph.pop_event().map(|e| e.step_number()), Some(6));
+        assert!(graph.children(CausalEventId(4)).is_empty());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod graph;
+
+pub use graph::*;
\ No newline at end of file
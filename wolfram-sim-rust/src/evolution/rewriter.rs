@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use crate::hypergraph::{Hypergraph, AtomId, RelationId};
-use crate::rules::{Rule, pattern::{Pattern, PatternElement, Variable}};
+use crate::rules::{Rule, pattern::PatternElement};
 use crate::matching::PatternMatch;
 
 /// Represents the result of applying a rule to a hypergraph.
@@ -79,13 +79,11 @@ pub fn apply_rule(
         for element in relation.elements() {
             if let PatternElement::Variable(var) = element {
                 // If this variable is not bound in the pattern match, we need to create a new atom
-                                 if pattern_match.binding.get_binding(var).is_none() {
-                     if !new_atom_bindings.contains_key(var) {
-                         let new_atom_id = hypergraph.create_atom();
-                         new_atom_bindings.insert(var.clone(), new_atom_id);
-                         new_atoms.push(new_atom_id);
-                     }
-                 }
+                if pattern_match.binding.get_binding(var).is_none() && !new_atom_bindings.contains_key(var) {
+                    let new_atom_id = hypergraph.create_atom();
+                    new_atom_bindings.insert(var.clone(), new_atom_id);
+                    new_atoms.push(new_atom_id);
+                }
             }
         }
     }
@@ -151,8 +149,8 @@ pub fn apply_first_available_rule(
 mod tests {
     use super::*;
     use crate::hypergraph::Hypergraph;
-    use crate::rules::{Rule, pattern::{Pattern, PatternRelation, PatternElement, Variable, Binding}};
-    use crate::matching::{find_pattern_matches, PatternMatch};
+    use crate::rules::{Rule, pattern::{Pattern, PatternRelation, PatternElement}};
+    use crate::matching::find_pattern_matches;
 
     #[test]
     fn test_apply_basic_edge_splitting_rule() {
@@ -3,7 +3,7 @@ use serde::{Serialize, Deserialize};
 
 /// Represents a unique identifier for an atom in a hypergraph.
 /// For the MVP, we'll use u64 as the underlying type for simplicity and performance.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct AtomId(pub u64);
 
 impl AtomId {
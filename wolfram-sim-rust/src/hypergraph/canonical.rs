@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use super::atom::AtomId;
+use super::hypergraph::Hypergraph;
+
+/// The canonical form of an ordered hypergraph, in the spirit of Wolfram's `CanonicalHypergraph`.
+/// Atoms are relabelled `0..atom_count` so that two hypergraphs have the same canonical
+/// form exactly when they are isomorphic (equal up to renaming atoms). Relation order
+/// within the hypergraph is irrelevant; atom order within each relation is significant.
+/// Metadata is ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CanonicalForm {
+    /// Relations over canonical atom labels, in canonical order
+    relations: Vec<Vec<u64>>,
+
+    /// Number of atoms, including atoms that appear in no relation
+    atom_count: usize,
+}
+
+impl CanonicalForm {
+    /// Computes the canonical form of a hypergraph.
+    pub fn of(hypergraph: &Hypergraph) -> Self {
+        Self::from_parts(
+            hypergraph.atom_ids().copied(),
+            hypergraph.relations().map(|relation| relation.atoms().to_vec()),
+        )
+    }
+
+    /// Computes the canonical form from a set of atoms and the atom lists of its relations.
+    /// Atoms referenced by relations but missing from `atom_ids` are included automatically.
+    pub fn from_parts<A, R>(atom_ids: A, relations: R) -> Self
+    where
+        A: IntoIterator<Item = AtomId>,
+        R: IntoIterator<Item = Vec<AtomId>>,
+    {
+        canonicalize(atom_ids, relations).0
+    }
+
+    /// Returns the relations over canonical atom labels.
+    pub fn relations(&self) -> &[Vec<u64>] {
+        &self.relations
+    }
+
+    /// Returns the number of atoms.
+    pub fn atom_count(&self) -> usize {
+        self.atom_count
+    }
+
+    /// Returns a hash of this canonical form that is stable across runs and platforms.
+    pub fn stable_hash(&self) -> u64 {
+        // 64-bit FNV-1a over the atom count and every relation, length-prefixed
+        const OFFSET: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+        let mut hash = OFFSET;
+        let mut feed = |value: u64| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+        feed(self.atom_count as u64);
+        feed(self.relations.len() as u64);
+        for relation in &self.relations {
+            feed(relation.len() as u64);
+            for &atom in relation {
+                feed(atom);
+            }
+        }
+        hash
+    }
+}
+
+/// Computes the canonical labelling of a hypergraph: a map from each atom to its canonical label.
+pub fn canonical_labeling(hypergraph: &Hypergraph) -> HashMap<AtomId, u64> {
+    canonicalize(
+        hypergraph.atom_ids().copied(),
+        hypergraph.relations().map(|relation| relation.atoms().to_vec()),
+    )
+    .1
+}
+
+/// Returns a stable hash of the hypergraph's canonical form.
+/// Isomorphic hypergraphs always have the same hash.
+pub fn canonical_hash(hypergraph: &Hypergraph) -> u64 {
+    CanonicalForm::of(hypergraph).stable_hash()
+}
+
+/// Checks whether two hypergraphs are equal up to relabelling of atoms.
+pub fn is_isomorphic(a: &Hypergraph, b: &Hypergraph) -> bool {
+    a.atom_count() == b.atom_count()
+        && a.relation_count() == b.relation_count()
+        && CanonicalForm::of(a) == CanonicalForm::of(b)
+}
+
+/// A connected component over local atom indices.
+struct Component {
+    atoms: Vec<usize>,
+    relations: Vec<Vec<usize>>,
+}
+
+/// Canonicalizes each connected component independently, then orders the components
+/// by their canonical forms. This keeps disjoint copies of the same structure from
+/// multiplying the search space.
+fn canonicalize<A, R>(atom_ids: A, relations: R) -> (CanonicalForm, HashMap<AtomId, u64>)
+where
+    A: IntoIterator<Item = AtomId>,
+    R: IntoIterator<Item = Vec<AtomId>>,
+{
+    let mut index: HashMap<AtomId, usize> = HashMap::new();
+    let mut atoms: Vec<AtomId> = Vec::new();
+    let mut intern = |atom: AtomId, atoms: &mut Vec<AtomId>| {
+        *index.entry(atom).or_insert_with(|| {
+            atoms.push(atom);
+            atoms.len() - 1
+        })
+    };
+    for atom in atom_ids {
+        intern(atom, &mut atoms);
+    }
+    let mut empty_relations = 0;
+    let mut local_relations: Vec<Vec<usize>> = Vec::new();
+    for relation in relations {
+        if relation.is_empty() {
+            empty_relations += 1;
+        } else {
+            local_relations.push(relation.into_iter().map(|atom| intern(atom, &mut atoms)).collect());
+        }
+    }
+
+    // Union-find over atoms to split into connected components
+    let mut parent: Vec<usize> = (0..atoms.len()).collect();
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
+    for relation in &local_relations {
+        let first = find(&mut parent, relation[0]);
+        for &atom in &relation[1..] {
+            let root = find(&mut parent, atom);
+            parent[root] = first;
+        }
+    }
+    let mut components: HashMap<usize, Component> = HashMap::new();
+    for atom in 0..atoms.len() {
+        let root = find(&mut parent, atom);
+        components
+            .entry(root)
+            .or_insert_with(|| Component { atoms: Vec::new(), relations: Vec::new() })
+            .atoms
+            .push(atom);
+    }
+    for relation in local_relations {
+        let root = find(&mut parent, relation[0]);
+        components.get_mut(&root).unwrap().relations.push(relation);
+    }
+
+    // Canonicalize each component, then order components by their canonical relations
+    let mut canonical_components: Vec<(Vec<Vec<u64>>, Vec<usize>)> = components
+        .into_values()
+        .map(|component| canonicalize_component(&component))
+        .collect();
+    canonical_components.sort_by(|a, b| (a.1.len(), &a.0).cmp(&(b.1.len(), &b.0)));
+
+    let mut canonical_relations: Vec<Vec<u64>> = vec![Vec::new(); empty_relations];
+    let mut labeling = HashMap::with_capacity(atoms.len());
+    let mut offset = 0u64;
+    for (relations, ordered_atoms) in canonical_components {
+        canonical_relations.extend(
+            relations
+                .into_iter()
+                .map(|relation| relation.into_iter().map(|label| label + offset).collect()),
+        );
+        for (label, atom) in ordered_atoms.iter().enumerate() {
+            labeling.insert(atoms[*atom], offset + label as u64);
+        }
+        offset += ordered_atoms.len() as u64;
+    }
+
+    (
+        CanonicalForm {
+            relations: canonical_relations,
+            atom_count: atoms.len(),
+        },
+        labeling,
+    )
+}
+
+/// Canonicalizes one connected component by colour refinement with individualization.
+/// Returns the sorted canonical relations and the component's atoms in label order.
+fn canonicalize_component(component: &Component) -> (Vec<Vec<u64>>, Vec<usize>) {
+    // Re-index the component's atoms densely
+    let local: HashMap<usize, usize> = component
+        .atoms
+        .iter()
+        .enumerate()
+        .map(|(i, &atom)| (atom, i))
+        .collect();
+    let relations: Vec<Vec<usize>> = component
+        .relations
+        .iter()
+        .map(|relation| relation.iter().map(|atom| local[atom]).collect())
+        .collect();
+    let mut incidence: Vec<Vec<(usize, usize)>> = vec![Vec::new(); component.atoms.len()];
+    for (r, relation) in relations.iter().enumerate() {
+        for (position, &atom) in relation.iter().enumerate() {
+            incidence[atom].push((r, position));
+        }
+    }
+
+    let refiner = Refiner { relations: &relations, incidence: &incidence };
+    let initial = refiner.refine(vec![0; component.atoms.len()]);
+    let mut search = Search::default();
+    refiner.search(initial, &mut Vec::new(), &mut search);
+
+    let Leaf { relations: canonical_relations, colors, .. } = search.best.expect("search always produces a labelling");
+    let mut ordered_atoms = vec![0; component.atoms.len()];
+    for (i, &color) in colors.iter().enumerate() {
+        ordered_atoms[color as usize] = component.atoms[i];
+    }
+    (canonical_relations, ordered_atoms)
+}
+
+/// A discrete colouring reached by the search, with the relations relabelled by it.
+#[derive(Clone)]
+struct Leaf {
+    relations: Vec<Vec<u64>>,
+    colors: Vec<u32>,
+    /// Atoms individualized on the way to this leaf
+    path: Vec<usize>,
+}
+
+impl Leaf {
+    /// Returns the automorphism taking `other` to this leaf, which must have the same relations.
+    fn automorphism_from(&self, other: &Leaf) -> Vec<usize> {
+        let mut atom_with_color = vec![0; self.colors.len()];
+        for (atom, &color) in self.colors.iter().enumerate() {
+            atom_with_color[color as usize] = atom;
+        }
+        other.colors.iter().map(|&color| atom_with_color[color as usize]).collect()
+    }
+
+    /// Returns how many individualized atoms `path` shares with this leaf's path.
+    fn common_prefix(&self, path: &[usize]) -> usize {
+        self.path.iter().zip(path).take_while(|(a, b)| a == b).count()
+    }
+}
+
+/// State of the search tree walk.
+#[derive(Default)]
+struct Search {
+    /// The first leaf reached, which all later leaves are compared with
+    first: Option<Leaf>,
+
+    /// The leaf with the smallest relations so far
+    best: Option<Leaf>,
+
+    /// Automorphisms found from leaves with equal relations
+    automorphisms: Vec<Vec<usize>>,
+}
+
+impl Search {
+    /// Checks whether `atom` is in the same orbit as one of `explored` under the known
+    /// automorphisms that fix every atom of `path`.
+    fn in_explored_orbit(&self, path: &[usize], explored: &[usize], atom: usize) -> bool {
+        let generators: Vec<&Vec<usize>> = self
+            .automorphisms
+            .iter()
+            .filter(|automorphism| path.iter().all(|&fixed| automorphism[fixed] == fixed))
+            .collect();
+        if generators.is_empty() {
+            return false;
+        }
+        let mut parent: Vec<usize> = (0..generators[0].len()).collect();
+        fn find(parent: &mut [usize], mut x: usize) -> usize {
+            while parent[x] != x {
+                parent[x] = parent[parent[x]];
+                x = parent[x];
+            }
+            x
+        }
+        for generator in generators {
+            for (a, &b) in generator.iter().enumerate() {
+                let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+                parent[root_a] = root_b;
+            }
+        }
+        let root = find(&mut parent, atom);
+        explored.iter().any(|&other| find(&mut parent, other) == root)
+    }
+}
+
+/// An atom's colour together with the sorted (arity, position, colours) of its incident relations.
+type Signature = (u32, Vec<(usize, usize, Vec<u32>)>);
+
+struct Refiner<'a> {
+    relations: &'a [Vec<usize>],
+    incidence: &'a [Vec<(usize, usize)>],
+}
+
+impl Refiner<'_> {
+    /// Refines an ordered colouring until stable. Each atom's new colour is the rank of
+    /// its old colour together with the colours seen through its incident relations,
+    /// so the result depends only on structure and never on the input atom IDs.
+    fn refine(&self, mut colors: Vec<u32>) -> Vec<u32> {
+        let mut classes = count_classes(&colors);
+        loop {
+            let signatures: Vec<Signature> = (0..colors.len())
+                .map(|atom| {
+                    let mut neighbourhood: Vec<_> = self.incidence[atom]
+                        .iter()
+                        .map(|&(r, position)| {
+                            let relation = &self.relations[r];
+                            (relation.len(), position, relation.iter().map(|&a| colors[a]).collect())
+                        })
+                        .collect();
+                    neighbourhood.sort();
+                    (colors[atom], neighbourhood)
+                })
+                .collect();
+
+            let mut ranked: Vec<&Signature> = signatures.iter().collect();
+            ranked.sort();
+            ranked.dedup();
+            let new_colors: Vec<u32> = signatures
+                .iter()
+                .map(|signature| ranked.binary_search(&signature).unwrap() as u32)
+                .collect();
+
+            let new_classes = ranked.len();
+            colors = new_colors;
+            if new_classes == classes {
+                return colors;
+            }
+            classes = new_classes;
+        }
+    }
+
+    /// Explores individualizations of the first non-singleton colour class and keeps the
+    /// lexicographically smallest relabelled relation list, pruning branches that an
+    /// automorphism maps onto a branch already explored.
+    ///
+    /// A leaf with the same relations as the first or best leaf yields an automorphism
+    /// that fixes the path the two leaves share and maps one branch below it onto the
+    /// other, so the rest of the current branch is abandoned, as in nauty. Returns the
+    /// depth to jump back to in that case. Nodes also skip atoms in the orbit of an atom
+    /// already explored under the automorphisms that fix their path. Without symmetry
+    /// the search is still exponential in the worst case, but symmetric structures such
+    /// as stars only explore two children per node.
+    fn search(&self, colors: Vec<u32>, path: &mut Vec<usize>, search: &mut Search) -> Option<usize> {
+        if count_classes(&colors) == colors.len() {
+            let mut relabelled: Vec<Vec<u64>> = self
+                .relations
+                .iter()
+                .map(|relation| relation.iter().map(|&atom| colors[atom] as u64).collect())
+                .collect();
+            relabelled.sort();
+            let leaf = Leaf { relations: relabelled, colors, path: path.clone() };
+
+            let (Some(first), Some(best)) = (&search.first, &search.best) else {
+                search.first = Some(leaf.clone());
+                search.best = Some(leaf);
+                return None;
+            };
+            let equivalent = [first, best].into_iter().find(|reference| reference.relations == leaf.relations);
+            if let Some(reference) = equivalent {
+                let depth = reference.common_prefix(path);
+                search.automorphisms.push(leaf.automorphism_from(reference));
+                return Some(depth);
+            }
+            if leaf.relations < best.relations {
+                search.best = Some(leaf);
+            }
+            return None;
+        }
+
+        // Target cell: the smallest colour shared by more than one atom
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for &color in &colors {
+            *counts.entry(color).or_insert(0) += 1;
+        }
+        let target = counts
+            .iter()
+            .filter(|(_, &count)| count > 1)
+            .map(|(&color, _)| color)
+            .min()
+            .unwrap();
+
+        let depth = path.len();
+        let mut explored = Vec::new();
+        for chosen in (0..colors.len()).filter(|&atom| colors[atom] == target) {
+            if search.in_explored_orbit(path, &explored, chosen) {
+                continue;
+            }
+            // The chosen atom sorts before the rest of its cell; other cells keep their order
+            let individualized: Vec<u32> = colors
+                .iter()
+                .enumerate()
+                .map(|(atom, &color)| {
+                    if color == target && atom != chosen {
+                        color * 2 + 1
+                    } else {
+                        color * 2
+                    }
+                })
+                .collect();
+            path.push(chosen);
+            let jump = self.search(self.refine(individualized), path, search);
+            path.pop();
+            explored.push(chosen);
+            match jump {
+                Some(target_depth) if target_depth < depth => return Some(target_depth),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+fn count_classes(colors: &[u32]) -> usize {
+    let mut sorted = colors.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    sorted.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(atom_count: u64, relations: &[&[u64]]) -> Hypergraph {
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<AtomId> = (0..atom_count).map(|_| hypergraph.create_atom()).collect();
+        for relation in relations {
+            hypergraph.create_relation(relation.iter().map(|&i| atoms[i as usize]).collect());
+        }
+        hypergraph
+    }
+
+    #[test]
+    fn test_relabelled_graphs_are_isomorphic() {
+        let a = build(4, &[&[0, 1], &[1, 2], &[2, 0], &[2, 3]]);
+        let b = build(4, &[&[3, 0], &[1, 3], &[0, 1], &[1, 2]]);
+
+        assert!(is_isomorphic(&a, &b));
+        assert_eq!(CanonicalForm::of(&a), CanonicalForm::of(&b));
+        assert_eq!(canonical_hash(&a), canonical_hash(&b));
+    }
+
+    #[test]
+    fn test_order_within_relations_matters() {
+        let path = build(3, &[&[0, 1], &[1, 2]]);
+        let fork = build(3, &[&[0, 1], &[0, 2]]);
+
+        assert!(!is_isomorphic(&path, &fork));
+        assert_ne!(canonical_hash(&path), canonical_hash(&fork));
+    }
+
+    #[test]
+    fn test_multiplicity_and_isolated_atoms() {
+        let double = build(2, &[&[0, 1], &[0, 1]]);
+        let single = build(2, &[&[0, 1]]);
+        assert!(!is_isomorphic(&double, &single));
+
+        let with_isolated = build(3, &[&[0, 1]]);
+        assert!(!is_isomorphic(&single, &with_isolated));
+        assert_eq!(CanonicalForm::of(&with_isolated).atom_count(), 3);
+    }
+
+    #[test]
+    fn test_symmetric_graphs() {
+        // Directed cycles are vertex-transitive, so refinement alone cannot break ties
+        let a = build(5, &[&[0, 1], &[1, 2], &[2, 3], &[3, 4], &[4, 0]]);
+        let b = build(5, &[&[2, 4], &[4, 1], &[1, 3], &[3, 0], &[0, 2]]);
+        assert!(is_isomorphic(&a, &b));
+
+        let two_triangles = build(6, &[&[0, 1], &[1, 2], &[2, 0], &[3, 4], &[4, 5], &[5, 3]]);
+        let hexagon = build(6, &[&[0, 1], &[1, 2], &[2, 3], &[3, 4], &[4, 5], &[5, 0]]);
+        assert!(!is_isomorphic(&two_triangles, &hexagon));
+    }
+
+    #[test]
+    fn test_many_disjoint_copies() {
+        let relations: Vec<Vec<u64>> = (0..40).map(|i| vec![2 * i, 2 * i + 1]).collect();
+        let refs: Vec<&[u64]> = relations.iter().map(Vec::as_slice).collect();
+        let a = build(80, &refs);
+        let form = CanonicalForm::of(&a);
+        assert_eq!(form.relations().len(), 40);
+        assert_eq!(form.relations()[0], vec![0, 1]);
+    }
+
+    #[test]
+    fn test_hyperedges_and_labeling() {
+        let a = build(3, &[&[0, 1, 2], &[2, 2, 1]]);
+        let b = build(3, &[&[1, 1, 0], &[2, 0, 1]]);
+        assert!(is_isomorphic(&a, &b));
+
+        // The labelling maps the hypergraph onto its canonical form
+        let labeling = canonical_labeling(&a);
+        let mut relabelled: Vec<Vec<u64>> = a
+            .relations()
+            .map(|r| r.atoms().iter().map(|atom| labeling[atom]).collect())
+            .collect();
+        relabelled.sort();
+        assert_eq!(relabelled, CanonicalForm::of(&a).relations());
+    }
+
+    #[test]
+    fn test_large_star_is_fast() {
+        // Refinement cannot tell the leaves apart, so this needs automorphism pruning
+        let relations: Vec<Vec<u64>> = (1..=50).map(|leaf| vec![0, leaf]).collect();
+        let refs: Vec<&[u64]> = relations.iter().map(Vec::as_slice).collect();
+        let star = build(51, &refs);
+        let reversed: Vec<Vec<u64>> = (0..50).map(|leaf| vec![50, leaf]).collect();
+        let reversed_refs: Vec<&[u64]> = reversed.iter().map(Vec::as_slice).collect();
+
+        let start = std::time::Instant::now();
+        assert!(is_isomorphic(&star, &build(51, &reversed_refs)));
+        assert_eq!(CanonicalForm::of(&star).relations()[0], vec![0, 1]);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_stable_hash_is_fixed() {
+        let single = build(2, &[&[0, 1]]);
+        let form = CanonicalForm::of(&single);
+        assert_eq!(form.relations(), &[vec![0, 1]]);
+        // Must not change between runs or releases, since hashes may be persisted
+        assert_eq!(form.stable_hash(), CanonicalForm::of(&build(2, &[&[1, 0]])).stable_hash());
+        assert_eq!(form.stable_hash(), 5359689606063244901);
+    }
+}
@@ -6,6 +6,7 @@ use super::relation::{Relation, RelationId};
 /// Represents a hypergraph structure consisting of atoms (vertices) and relations (hyperedges).
 /// In the Wolfram Physics Model, this structure evolves over time through the application of rewrite rules.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "HypergraphData")]
 pub struct Hypergraph {
     /// Collection of atoms, indexed by their IDs
     atoms: HashMap<AtomId, Atom>,
@@ -15,13 +16,24 @@ pub struct Hypergraph {
     
     /// Index mapping each atom to the relations it participates in.
     /// This improves query performance for finding relations containing a specific atom.
+    /// Atoms in no relation have no entry.
     atom_to_relations: HashMap<AtomId, HashSet<RelationId>>,
     
+    /// Index mapping each arity to the relations with that many atoms.
+    /// Used by the pattern matcher to seed searches that have no bound atoms.
+    /// Arities without relations have no entry. Not serialized; rebuilt on load.
+    #[serde(skip)]
+    arity_to_relations: HashMap<usize, HashSet<RelationId>>,
+    
     /// Counter for generating unique atom IDs
     next_atom_id: u64,
     
     /// Counter for generating unique relation IDs
     next_relation_id: u64,
+    
+    /// Clock for relation creation timestamps
+    #[serde(default)]
+    next_timestamp: u64,
 }
 
 impl Hypergraph {
@@ -31,8 +43,10 @@ impl Hypergraph {
             atoms: HashMap::new(),
             relations: HashMap::new(),
             atom_to_relations: HashMap::new(),
+            arity_to_relations: HashMap::new(),
             next_atom_id: 0,
             next_relation_id: 0,
+            next_timestamp: 0,
         }
     }
 
@@ -42,8 +56,10 @@ impl Hypergraph {
             atoms: HashMap::with_capacity(atom_capacity),
             relations: HashMap::with_capacity(relation_capacity),
             atom_to_relations: HashMap::with_capacity(atom_capacity),
+            arity_to_relations: HashMap::new(),
             next_atom_id: 0,
             next_relation_id: 0,
+            next_timestamp: 0,
         }
     }
     
@@ -65,7 +81,6 @@ impl Hypergraph {
         
         let atom = Atom::new(id);
         self.atoms.insert(id, atom);
-        self.atom_to_relations.insert(id, HashSet::new());
         
         id
     }
@@ -78,7 +93,6 @@ impl Hypergraph {
         
         let atom = Atom::with_metadata(id, metadata);
         self.atoms.insert(id, atom);
-        self.atom_to_relations.insert(id, HashSet::new());
         
         id
     }
@@ -88,13 +102,7 @@ impl Hypergraph {
     /// Returns true if a previous atom was replaced, false otherwise.
     pub fn add_atom(&mut self, atom: Atom) -> bool {
         let id = atom.id();
-        let replaced = self.atoms.insert(id, atom).is_some();
-        
-        if !self.atom_to_relations.contains_key(&id) {
-            self.atom_to_relations.insert(id, HashSet::new());
-        }
-        
-        replaced
+        self.atoms.insert(id, atom).is_some()
     }
     
     /// Removes an atom from the hypergraph by its ID.
@@ -106,20 +114,9 @@ impl Hypergraph {
             // Clone the set to avoid borrowing issues during iteration
             let relation_ids_vec: Vec<RelationId> = relation_ids.into_iter().collect();
             
-            // Remove all relations involving the atom
+            // Remove all relations involving the atom, which also updates the indices
             for rel_id in relation_ids_vec {
-                self.relations.remove(&rel_id);
-                
-                // Remove this relation ID from all other atoms' relation sets
-                if let Some(relation) = self.relations.get(&rel_id) {
-                    for other_atom_id in relation.atoms() {
-                        if *other_atom_id != atom_id {
-                            if let Some(other_rel_set) = self.atom_to_relations.get_mut(other_atom_id) {
-                                other_rel_set.remove(&rel_id);
-                            }
-                        }
-                    }
-                }
+                self.remove_relation(rel_id);
             }
         }
         
@@ -141,15 +138,11 @@ impl Hypergraph {
         let id = RelationId::new(self.next_relation_id);
         self.next_relation_id += 1;
         
-        let relation = Relation::new(id, atom_ids.clone());
+        let mut relation = Relation::new(id, atom_ids.clone());
+        relation.created_at = self.tick();
         self.relations.insert(id, relation);
         
-        // Update the atom_to_relations index
-        for atom_id in atom_ids {
-            if let Some(rel_set) = self.atom_to_relations.get_mut(&atom_id) {
-                rel_set.insert(id);
-            }
-        }
+        self.index_relation(id, &atom_ids);
         
         id
     }
@@ -168,21 +161,18 @@ impl Hypergraph {
         let id = RelationId::new(self.next_relation_id);
         self.next_relation_id += 1;
         
-        let relation = Relation::with_metadata(id, atom_ids.clone(), metadata);
+        let mut relation = Relation::with_metadata(id, atom_ids.clone(), metadata);
+        relation.created_at = self.tick();
         self.relations.insert(id, relation);
         
-        // Update the atom_to_relations index
-        for atom_id in atom_ids {
-            if let Some(rel_set) = self.atom_to_relations.get_mut(&atom_id) {
-                rel_set.insert(id);
-            }
-        }
+        self.index_relation(id, &atom_ids);
         
         id
     }
     
     /// Adds an existing relation to the hypergraph.
     /// If a relation with the same ID already exists, it will be replaced.
+    /// The relation keeps its creation timestamp, and later relations are stamped after it.
     /// Returns true if a previous relation was replaced, false otherwise.
     /// 
     /// # Panics
@@ -194,14 +184,12 @@ impl Hypergraph {
         }
         
         let id = relation.id();
-        let replaced = self.relations.insert(id, relation.clone()).is_some();
+        // Drop the index entries of any relation being replaced
+        let replaced = self.remove_relation(id).is_some();
         
-        // Update the atom_to_relations index
-        for atom_id in relation.atoms() {
-            if let Some(rel_set) = self.atom_to_relations.get_mut(atom_id) {
-                rel_set.insert(id);
-            }
-        }
+        self.index_relation(id, relation.atoms());
+        self.next_timestamp = self.next_timestamp.max(relation.created_at + 1);
+        self.relations.insert(id, relation);
         
         replaced
     }
@@ -210,12 +198,12 @@ impl Hypergraph {
     /// Returns the removed relation if it existed.
     pub fn remove_relation(&mut self, relation_id: RelationId) -> Option<Relation> {
         if let Some(relation) = self.relations.remove(&relation_id) {
-            // Remove this relation ID from all atoms' relation sets
+            // Remove this relation ID from all atoms' relation sets. Sets that become
+            // empty are dropped, so removing a relation restores the indices exactly.
             for atom_id in relation.atoms() {
-                if let Some(rel_set) = self.atom_to_relations.get_mut(atom_id) {
-                    rel_set.remove(&relation_id);
-                }
+                remove_from_index(&mut self.atom_to_relations, *atom_id, relation_id);
             }
+            remove_from_index(&mut self.arity_to_relations, relation.arity(), relation_id);
             
             Some(relation)
         } else {
@@ -275,6 +263,41 @@ impl Hypergraph {
         }
     }
     
+    /// Returns the IDs of all relations that involve the specified atom.
+    pub fn relation_ids_with_atom(&self, atom_id: AtomId) -> impl Iterator<Item = &RelationId> {
+        self.atom_to_relations.get(&atom_id).into_iter().flatten()
+    }
+    
+    /// Returns the number of relations that involve the specified atom.
+    pub fn atom_degree(&self, atom_id: AtomId) -> usize {
+        self.atom_to_relations.get(&atom_id).map_or(0, HashSet::len)
+    }
+    
+    /// Returns the IDs of all relations with the specified arity.
+    pub fn relation_ids_with_arity(&self, arity: usize) -> impl Iterator<Item = &RelationId> {
+        self.arity_to_relations.get(&arity).into_iter().flatten()
+    }
+    
+    /// Returns the number of relations with the specified arity.
+    pub fn arity_count(&self, arity: usize) -> usize {
+        self.arity_to_relations.get(&arity).map_or(0, HashSet::len)
+    }
+    
+    /// Returns the next relation creation timestamp and advances the clock.
+    fn tick(&mut self) -> u64 {
+        let timestamp = self.next_timestamp;
+        self.next_timestamp += 1;
+        timestamp
+    }
+    
+    /// Adds a relation to the atom and arity indices.
+    fn index_relation(&mut self, id: RelationId, atom_ids: &[AtomId]) {
+        for atom_id in atom_ids {
+            self.atom_to_relations.entry(*atom_id).or_default().insert(id);
+        }
+        self.arity_to_relations.entry(atom_ids.len()).or_default().insert(id);
+    }
+    
     /// Sets the next available atom ID.
     /// This is useful when loading a hypergraph from a file to ensure new atoms
     /// get unique IDs.
@@ -299,16 +322,20 @@ impl Hypergraph {
         self.relations.contains_key(&relation_id)
     }
     
-    /// Returns all atoms in the hypergraph as a vector.
+    /// Returns all atoms in the hypergraph as a vector, ordered by ID.
     /// This is useful for serialization and state management.
     pub fn get_all_atoms(&self) -> Vec<Atom> {
-        self.atoms.values().cloned().collect()
+        let mut atoms: Vec<Atom> = self.atoms.values().cloned().collect();
+        atoms.sort_by_key(Atom::id);
+        atoms
     }
     
-    /// Returns all relations in the hypergraph as a vector.
+    /// Returns all relations in the hypergraph as a vector, in creation order.
     /// This is useful for serialization and state management.
     pub fn get_all_relations(&self) -> Vec<Relation> {
-        self.relations.values().cloned().collect()
+        let mut relations: Vec<Relation> = self.relations.values().cloned().collect();
+        relations.sort_by_key(|relation| (relation.created_at, relation.id));
+        relations
     }
     
     /// Returns the next atom ID that will be assigned.
@@ -321,11 +348,29 @@ impl Hypergraph {
         self.next_relation_id
     }
     
+    /// Returns the creation timestamp the next created relation will get.
+    pub fn next_timestamp(&self) -> u64 {
+        self.next_timestamp
+    }
+    
+    /// Sets the creation timestamp the next created relation will get.
+    /// This is useful when restoring an earlier state.
+    pub fn set_next_timestamp(&mut self, next_timestamp: u64) {
+        self.next_timestamp = next_timestamp;
+    }
+    
+    /// Returns the canonical form of this hypergraph, identical for all hypergraphs
+    /// that are equal up to relabelling of atoms.
+    pub fn canonical_form(&self) -> super::canonical::CanonicalForm {
+        super::canonical::CanonicalForm::of(self)
+    }
+    
     /// Clears all atoms and relations from the hypergraph.
     pub fn clear(&mut self) {
         self.atoms.clear();
         self.relations.clear();
         self.atom_to_relations.clear();
+        self.arity_to_relations.clear();
         // Note: We don't reset the ID counters to allow for consistent unique IDs
     }
 }
@@ -336,6 +381,46 @@ impl Default for Hypergraph {
     }
 }
 
+/// The serialized fields of a hypergraph, from which the arity index is rebuilt.
+#[derive(Deserialize)]
+struct HypergraphData {
+    atoms: HashMap<AtomId, Atom>,
+    relations: HashMap<RelationId, Relation>,
+    atom_to_relations: HashMap<AtomId, HashSet<RelationId>>,
+    next_atom_id: u64,
+    next_relation_id: u64,
+    #[serde(default)]
+    next_timestamp: u64,
+}
+
+impl From<HypergraphData> for Hypergraph {
+    fn from(data: HypergraphData) -> Self {
+        let mut arity_to_relations: HashMap<usize, HashSet<RelationId>> = HashMap::new();
+        for (id, relation) in &data.relations {
+            arity_to_relations.entry(relation.arity()).or_default().insert(*id);
+        }
+        Hypergraph {
+            atoms: data.atoms,
+            relations: data.relations,
+            atom_to_relations: data.atom_to_relations,
+            arity_to_relations,
+            next_atom_id: data.next_atom_id,
+            next_relation_id: data.next_relation_id,
+            next_timestamp: data.next_timestamp,
+        }
+    }
+}
+
+/// Removes a relation from one set of an index, dropping the set once it is empty.
+fn remove_from_index<K: std::hash::Hash + Eq>(index: &mut HashMap<K, HashSet<RelationId>>, key: K, relation_id: RelationId) {
+    if let Some(rel_set) = index.get_mut(&key) {
+        rel_set.remove(&relation_id);
+        if rel_set.is_empty() {
+            index.remove(&key);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -431,6 +516,28 @@ mod tests {
         assert!(hypergraph.add_relation(relation));
     }
     
+    #[test]
+    fn test_relation_timestamps() {
+        let mut hypergraph = Hypergraph::new();
+        let atom1_id = hypergraph.create_atom();
+        let atom2_id = hypergraph.create_atom();
+        
+        let first = hypergraph.create_relation(vec![atom1_id, atom2_id]);
+        let second = hypergraph.create_relation_with_metadata(vec![atom2_id], "Edge".to_string());
+        assert_eq!(hypergraph.get_relation(first).unwrap().created_at(), 0);
+        assert_eq!(hypergraph.get_relation(second).unwrap().created_at(), 1);
+        
+        // Added relations keep their timestamp and push the clock past it
+        let mut restored = Relation::new(RelationId::new(42), vec![atom1_id]);
+        restored.created_at = 10;
+        hypergraph.add_relation(restored);
+        let third = hypergraph.create_relation(vec![atom1_id]);
+        assert_eq!(hypergraph.get_relation(third).unwrap().created_at(), 11);
+        
+        let order: Vec<RelationId> = hypergraph.get_all_relations().iter().map(Relation::id).collect();
+        assert_eq!(order, vec![first, second, RelationId::new(42), third]);
+    }
+    
     #[test]
     fn test_remove_relation() {
         let mut hypergraph = Hypergraph::new();
@@ -465,6 +572,49 @@ mod tests {
         assert!(relations_with_atom2.iter().any(|r| r.id() == relation2_id));
     }
     
+    #[test]
+    fn test_arity_index() {
+        let mut hypergraph = Hypergraph::new();
+        let atom1_id = hypergraph.create_atom();
+        let atom2_id = hypergraph.create_atom();
+        
+        let unary_id = hypergraph.create_relation(vec![atom1_id]);
+        let binary_id = hypergraph.create_relation(vec![atom1_id, atom2_id]);
+        hypergraph.create_relation(vec![atom2_id, atom1_id]);
+        
+        assert_eq!(hypergraph.arity_count(1), 1);
+        assert_eq!(hypergraph.arity_count(2), 2);
+        assert_eq!(hypergraph.relation_ids_with_arity(1).collect::<Vec<_>>(), vec![&unary_id]);
+        assert_eq!(hypergraph.atom_degree(atom1_id), 3);
+        
+        // Replacing a relation with one of a different arity moves it between buckets
+        hypergraph.add_relation(Relation::new(binary_id, vec![atom1_id, atom2_id, atom2_id]));
+        assert_eq!(hypergraph.arity_count(2), 1);
+        assert_eq!(hypergraph.arity_count(3), 1);
+        
+        hypergraph.remove_atom(atom2_id);
+        assert_eq!(hypergraph.arity_count(2), 0);
+        assert_eq!(hypergraph.arity_count(3), 0);
+        assert_eq!(hypergraph.atom_degree(atom1_id), 1);
+    }
+    
+    #[test]
+    fn test_arity_index_rebuilt_on_deserialize() {
+        let mut hypergraph = Hypergraph::new();
+        let atom1_id = hypergraph.create_atom();
+        let atom2_id = hypergraph.create_atom();
+        hypergraph.create_relation(vec![atom1_id, atom2_id]);
+        hypergraph.create_relation(vec![atom1_id, atom2_id, atom2_id]);
+        
+        // Hypergraphs saved before the arity index existed have no such field
+        let json = serde_json::to_string(&hypergraph).unwrap();
+        assert!(!json.contains("arity_to_relations"));
+        let loaded: Hypergraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, hypergraph);
+        assert_eq!(loaded.arity_count(2), 1);
+        assert_eq!(loaded.arity_count(3), 1);
+    }
+    
     #[test]
     fn test_removing_atom_removes_associated_relations() {
         let mut hypergraph = Hypergraph::new();
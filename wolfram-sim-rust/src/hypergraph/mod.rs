@@ -1,8 +1,11 @@
 pub mod atom;
 pub mod relation;
+#[allow(clippy::module_inception)]
 pub mod hypergraph;
+pub mod canonical;
 
 // Re-export main types for convenience
 pub use atom::{Atom, AtomId};
 pub use relation::{Relation, RelationId};
-pub use hypergraph::Hypergraph; 
\ No newline at end of file
+pub use hypergraph::Hypergraph;
+pub use canonical::{CanonicalForm, canonical_hash, canonical_labeling, is_isomorphic}; 
\ No newline at end of file
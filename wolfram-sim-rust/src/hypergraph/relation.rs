@@ -3,7 +3,7 @@ use serde::{Serialize, Deserialize};
 use super::atom::AtomId;
 
 /// Represents a unique identifier for a relation in a hypergraph.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct RelationId(pub u64);
 
 impl RelationId {
@@ -36,6 +36,11 @@ pub struct Relation {
     
     /// Optional metadata or additional information about the relation
     pub metadata: Option<String>,
+    
+    /// Creation timestamp assigned by the hypergraph, increasing in the order relations
+    /// were created. Event orderings use it to tell older relations from newer ones.
+    #[serde(default)]
+    pub created_at: u64,
 }
 
 impl Relation {
@@ -45,6 +50,7 @@ impl Relation {
             id,
             atoms,
             metadata: None,
+            created_at: 0,
         }
     }
 
@@ -54,6 +60,7 @@ impl Relation {
             id,
             atoms,
             metadata: Some(metadata),
+            created_at: 0,
         }
     }
 
@@ -91,6 +98,11 @@ impl Relation {
     pub fn set_metadata(&mut self, metadata: Option<String>) {
         self.metadata = metadata;
     }
+    
+    /// Returns the creation timestamp of this relation.
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
 }
 
 #[cfg(test)]
@@ -8,11 +8,15 @@ pub mod matching;
 pub mod evolution;
 pub mod simulation;
 pub mod serialization;
+pub mod causal;
+pub mod analysis;
 
 pub use hypergraph::*;
 pub use rules::*;
 pub use simulation::*;
 pub use serialization::*;
+pub use causal::*;
+pub use analysis::*;
 
 // For gRPC service generation
 pub mod wolfram_physics_simulator {
@@ -1,9 +1,9 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tonic::{transport::Server, Request, Response, Status};
 use tonic_web::GrpcWebLayer;
 use tokio_stream::wrappers::ReceiverStream;
-use tokio_stream::StreamExt;
-use std::collections::HashMap;
 
 // Use our crate's module structure
 use wolfram_sim_rust::wolfram_physics_simulator::{
@@ -12,40 +12,96 @@ use wolfram_sim_rust::wolfram_physics_simulator::{
     Relation as ProtoRelation, RunRequest, SimulationEvent as ProtoSimulationEvent, 
     SimulationStateUpdate, StepRequest, StepResponse, StopRequest, StopResponse,
     GetCurrentStateRequest, SaveHypergraphRequest, SaveHypergraphResponse,
-    LoadHypergraphRequest, LoadHypergraphResponse, PredefinedExampleInfo,
-    ListPredefinedExamplesRequest, ListPredefinedExamplesResponse,
+    LoadHypergraphRequest, LoadHypergraphResponse, UndoStepRequest, RedoStepRequest,
+    ListSavedHypergraphsRequest, ListSavedHypergraphsResponse, SavedHypergraphInfo,
+    Rule as ProtoRule, PatternRelation as ProtoPatternRelation, RuleError as ProtoRuleError,
+    pattern_element::Element as ProtoPatternElement,
+    ListPredefinedRulesRequest, ListPredefinedRulesResponse, PredefinedRuleInfo, RuleProperties as ProtoRuleProperties,
+    GetCausalGraphRequest, GetCausalGraphResponse, CausalEdge as ProtoCausalEdge,
+    GetStateAtStepRequest, GetStateAtStepResponse,
+    SaveSessionRequest, SaveSessionResponse, LoadSessionRequest, LoadSessionResponse,
+    ExportHypergraphRequest, ExportHypergraphResponse, ExportMetricsRequest, ExportMetricsResponse,
 };
 
 // Import our core data structures
-use wolfram_sim_rust::hypergraph::{Atom, AtomId, Relation, RelationId, Hypergraph};
-use wolfram_sim_rust::rules::rule::RuleSet;
+use wolfram_sim_rust::hypergraph::{Atom, AtomId, Relation, RelationId};
+use wolfram_sim_rust::rules::{
+    Rule, RuleId, Pattern, PredefinedRules, RuleProperties, parse_rule,
+    pattern::{PatternElement, PatternRelation},
+    rule::RuleSet,
+};
 use wolfram_sim_rust::simulation::{
-    manager::{SimulationManager, ContinuousSimulationConfig, StopReason},
+    manager::{SimulationManager, EventSelectionStrategy, ContinuousSimulationConfig}, EventOrdering,
+    metrics::MetricsRecorder,
     event::{HypergraphState, SimulationEvent},
 };
+use wolfram_sim_rust::analysis::{DimensionConfig, estimate_dimension};
 use wolfram_sim_rust::serialization::{
-    persistence::{PersistenceManager, SaveConfig},
+    persistence::{PersistenceManager, SaveConfig, SnapshotFormat},
+    storage::{SnapshotQuery, StoreBackend},
     examples::PredefinedExamples,
+    session::SimulationSession,
+    event_log::{self, EventLogConfig, EventLogHeader, EventLogWriter},
+    wolfram,
+    graph_export::{self, GraphFormat, HyperedgeExpansion},
 };
 
+/// Directory event logs are written to unless WOLFRAM_EVENT_LOG_DIR names another.
+const DEFAULT_EVENT_LOG_DIRECTORY: &str = "event_logs";
+
 /// Shared simulation state that can be accessed by multiple gRPC calls
 #[derive(Debug)]
 struct SimulationState {
     manager: SimulationManager,
     persistence: PersistenceManager,
+    /// Directory the event logs named by run requests are written to
+    event_log_directory: PathBuf,
+    /// Configuration of the most recent continuous run, saved with sessions
+    run_config: ContinuousSimulationConfig,
     is_running: bool,
+    /// Counts started runs, so a run loop only cleans up after itself
+    run_generation: u64,
     running_task_handle: Option<tokio::task::JoinHandle<()>>,
+    /// Metrics recorder of the last finished run, kept for export
+    finished_metrics: Option<MetricsRecorder>,
 }
 
 impl SimulationState {
-    fn new() -> Self {
+    fn new(persistence: PersistenceManager, event_log_directory: PathBuf) -> Self {
         SimulationState {
             manager: SimulationManager::new(),
-            persistence: PersistenceManager::new(),
+            persistence,
+            event_log_directory,
+            run_config: ContinuousSimulationConfig::default(),
             is_running: false,
+            run_generation: 0,
             running_task_handle: None,
+            finished_metrics: None,
         }
     }
+
+    /// Ends the current run, detaching its event log, which syncs it, and its
+    /// metrics recorder, which is kept for export.
+    fn finish_run(&mut self) {
+        self.is_running = false;
+        self.manager.set_event_log(None);
+        if let Some(recorder) = self.manager.set_metrics_recorder(None) {
+            self.finished_metrics = Some(recorder);
+        }
+    }
+
+    /// Aborts the running simulation task, if any, and ends its run.
+    fn stop_run(&mut self) {
+        if let Some(handle) = self.running_task_handle.take() {
+            handle.abort();
+        }
+        self.finish_run();
+    }
+
+    /// Returns the recorder of the current run, or else of the last finished one.
+    fn metrics_recorder(&self) -> Option<&MetricsRecorder> {
+        self.manager.metrics_recorder().or(self.finished_metrics.as_ref())
+    }
 }
 
 /// Define a struct that will implement our service with shared state
@@ -56,8 +112,14 @@ pub struct MyWolframPhysicsSimulator {
 
 impl MyWolframPhysicsSimulator {
     fn new() -> Self {
+        Self::with_persistence(PersistenceManager::new(), PathBuf::from(DEFAULT_EVENT_LOG_DIRECTORY))
+    }
+
+    /// Creates a service saving snapshots and sessions through `persistence`, and
+    /// writing event logs to `event_log_directory`.
+    fn with_persistence(persistence: PersistenceManager, event_log_directory: PathBuf) -> Self {
         MyWolframPhysicsSimulator {
-            state: Arc::new(Mutex::new(SimulationState::new())),
+            state: Arc::new(Mutex::new(SimulationState::new(persistence, event_log_directory))),
         }
     }
 }
@@ -68,6 +130,16 @@ impl Default for MyWolframPhysicsSimulator {
     }
 }
 
+/// Appends warnings to a status message, so clients learn of problems that did not
+/// stop the simulation, such as an event log that could not be written.
+fn with_warnings(message: String, warnings: &[String]) -> String {
+    match (message.is_empty(), warnings.is_empty()) {
+        (_, true) => message,
+        (true, false) => warnings.join("; "),
+        (false, false) => format!("{} ({})", message, warnings.join("; ")),
+    }
+}
+
 // Helper functions for converting between internal and protobuf types
 
 fn atom_to_proto(atom: &Atom) -> ProtoAtom {
@@ -131,6 +203,73 @@ fn proto_to_hypergraph_state(proto: &ProtoHypergraphState) -> Result<HypergraphS
     ))
 }
 
+fn proto_to_pattern(relations: &[ProtoPatternRelation]) -> Result<Pattern, String> {
+    let mut pattern = Pattern::new(Vec::with_capacity(relations.len()));
+    for relation in relations {
+        let elements: Result<Vec<_>, String> = relation.elements.iter()
+            .map(|e| match &e.element {
+                Some(ProtoPatternElement::Variable(name)) if !name.is_empty() => Ok(PatternElement::variable(name.clone())),
+                Some(ProtoPatternElement::Variable(_)) => Err("Variable name must not be empty".to_string()),
+                Some(ProtoPatternElement::AtomId(id)) => id.parse::<u64>()
+                    .map(|id| PatternElement::atom(AtomId::new(id)))
+                    .map_err(|_| format!("Invalid atom ID format: {}", id)),
+                None => Err("Pattern element has neither a variable nor an atom ID".to_string()),
+            })
+            .collect();
+        pattern.add_relation(PatternRelation::new(elements?));
+    }
+    Ok(pattern)
+}
+
+fn proto_to_rule(index: usize, proto: &ProtoRule) -> Result<Rule, String> {
+    let id = if proto.id.is_empty() {
+        RuleId::new(index as u64)
+    } else {
+        RuleId::new(proto.id.parse::<u64>().map_err(|_| format!("Invalid rule ID format: {}", proto.id))?)
+    };
+
+    let mut rule = if !proto.rule_text.is_empty() {
+        if !proto.pattern.is_empty() || !proto.replacement.is_empty() {
+            return Err("Specify either rule_text or pattern/replacement, not both".to_string());
+        }
+        parse_rule(id, &proto.rule_text).map_err(|e| format!("Failed to parse rule text: {}", e))?
+    } else {
+        Rule::new(id, proto_to_pattern(&proto.pattern)?, proto_to_pattern(&proto.replacement)?)
+    };
+
+    if !proto.name.is_empty() {
+        rule.set_name(Some(proto.name.clone()));
+    }
+    rule.validate()?;
+    Ok(rule)
+}
+
+/// Converts and validates client-supplied rules, collecting an error for every invalid rule.
+fn proto_to_rule_set(rules: &[ProtoRule]) -> Result<RuleSet, Vec<ProtoRuleError>> {
+    let mut rule_set = RuleSet::with_capacity(rules.len());
+    let mut errors = Vec::new();
+
+    for (index, proto) in rules.iter().enumerate() {
+        match proto_to_rule(index, proto) {
+            Ok(rule) if rule_set.get_rule(rule.id()).is_some() => errors.push(ProtoRuleError {
+                rule_index: index as i32,
+                message: format!("Duplicate rule ID {}", rule.id().value()),
+            }),
+            Ok(rule) => rule_set.add_rule(rule),
+            Err(message) => errors.push(ProtoRuleError {
+                rule_index: index as i32,
+                message,
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(rule_set)
+    } else {
+        Err(errors)
+    }
+}
+
 // Implement the gRPC service trait for our struct
 #[tonic::async_trait]
 impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
@@ -144,10 +283,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
         let mut state = self.state.lock().unwrap();
         
         // Stop any running simulation first
-        state.is_running = false;
-        if let Some(handle) = state.running_task_handle.take() {
-            handle.abort();
-        }
+        state.stop_run();
         
         // Initialize based on request parameters
         let hypergraph_state = if let Some(initial_state) = req.initial_hypergraph {
@@ -159,6 +295,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                         success: false,
                         message: format!("Invalid initial hypergraph: {}", e),
                         initial_hypergraph_state: None,
+                        rule_errors: vec![],
                     }));
                 }
             }
@@ -171,6 +308,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                         success: false,
                         message: format!("Unknown predefined example: {}", req.predefined_initial_state_id),
                         initial_hypergraph_state: None,
+                        rule_errors: vec![],
                     }));
                 }
             }
@@ -179,17 +317,80 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
             PredefinedExamples::empty_graph()
         };
         
+        // Use client-supplied rules if any, then predefined rules by ID, otherwise the basic rule set
+        let rule_set = if !req.rules.is_empty() {
+            match proto_to_rule_set(&req.rules) {
+                Ok(rule_set) => rule_set,
+                Err(rule_errors) => {
+                    return Ok(Response::new(InitializeResponse {
+                        success: false,
+                        message: format!("{} of {} rules are invalid", rule_errors.len(), req.rules.len()),
+                        initial_hypergraph_state: None,
+                        rule_errors,
+                    }));
+                }
+            }
+        } else if !req.rule_ids_to_use.is_empty() {
+            match PredefinedRules::create_rule_set(&req.rule_ids_to_use) {
+                Ok(rule_set) => rule_set,
+                Err(e) => {
+                    return Ok(Response::new(InitializeResponse {
+                        success: false,
+                        message: e,
+                        initial_hypergraph_state: None,
+                        rule_errors: vec![],
+                    }));
+                }
+            }
+        } else {
+            RuleSet::create_basic_ruleset()
+        };
+        
+        if req.rule_weights.iter().any(|weight| !weight.is_finite() || *weight < 0.0) {
+            return Ok(Response::new(InitializeResponse {
+                success: false,
+                message: "Rule weights must be finite and non-negative".to_string(),
+                initial_hypergraph_state: None,
+                rule_errors: vec![],
+            }));
+        }
+        
+        // An empty ordering list keeps the default first-match strategy
+        let event_ordering: Result<Vec<EventOrdering>, String> =
+            req.event_ordering.iter().map(|name| name.parse()).collect();
+        let event_ordering = match event_ordering {
+            Ok(event_ordering) => event_ordering,
+            Err(e) => {
+                return Ok(Response::new(InitializeResponse {
+                    success: false,
+                    message: e,
+                    initial_hypergraph_state: None,
+                    rule_errors: vec![],
+                }));
+            }
+        };
+        
         // Create new simulation manager with the specified state
-        let rule_set = RuleSet::create_basic_ruleset();
         match SimulationManager::from_state(&hypergraph_state, rule_set) {
-            Ok(manager) => {
+            Ok(mut manager) => {
+                if req.random_selection {
+                    manager.set_event_selection_strategy(EventSelectionStrategy::Random {
+                        rule_weights: req.rule_weights.clone(),
+                    });
+                } else if !event_ordering.is_empty() {
+                    manager.set_event_selection_strategy(EventSelectionStrategy::Ordered(event_ordering));
+                }
+                if let Some(seed) = req.random_seed {
+                    manager.set_random_seed(seed);
+                }
                 state.manager = manager;
                 let current_state = state.manager.get_current_state();
                 
                 Ok(Response::new(InitializeResponse {
                     success: true,
-                    message: "Simulation initialized successfully".to_string(),
+                    message: format!("Simulation initialized successfully with {} rules", state.manager.rule_set().len()),
                     initial_hypergraph_state: Some(hypergraph_state_to_proto(&current_state)),
+                    rule_errors: vec![],
                 }))
             }
             Err(e) => {
@@ -197,6 +398,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                     success: false,
                     message: format!("Failed to initialize simulation: {}", e),
                     initial_hypergraph_state: None,
+                    rule_errors: vec![],
                 }))
             }
         }
@@ -212,21 +414,42 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
         let mut state = self.state.lock().unwrap();
         
         let num_steps = req.num_steps.max(1) as u64;
-        let results = state.manager.step_multiple(num_steps);
-        
-        // Convert results to protocol buffer format
-        let events: Vec<ProtoSimulationEvent> = results.iter()
-            .filter_map(|r| r.event.as_ref().map(simulation_event_to_proto))
-            .collect();
-        
-        let success = !results.is_empty() && results.iter().any(|r| r.success);
-        let message = if success {
-            format!("Executed {} steps successfully", results.len())
+        let (events, success, message) = if req.by_generation {
+            let results = state.manager.step_generations(num_steps);
+            
+            let events: Vec<ProtoSimulationEvent> = results.iter()
+                .flat_map(|r| r.events.iter().map(simulation_event_to_proto))
+                .collect();
+            
+            let completed = results.iter().filter(|r| r.success).count();
+            let message = if completed > 0 {
+                format!("Executed {} generations ({} events) successfully", completed, events.len())
+            } else {
+                results.first()
+                    .and_then(|r| r.message.clone())
+                    .unwrap_or_else(|| "No generations could be executed".to_string())
+            };
+            let warnings: Vec<String> = results.into_iter().flat_map(|r| r.warnings).collect();
+            (events, completed > 0, with_warnings(message, &warnings))
         } else {
-            results.first()
-                .and_then(|r| r.message.as_ref())
-                .unwrap_or(&"No steps could be executed".to_string())
-                .clone()
+            let results = state.manager.step_multiple(num_steps);
+            
+            // Convert results to protocol buffer format
+            let events: Vec<ProtoSimulationEvent> = results.iter()
+                .filter_map(|r| r.event.as_ref().map(simulation_event_to_proto))
+                .collect();
+            
+            let success = !results.is_empty() && results.iter().any(|r| r.success);
+            let message = if success {
+                format!("Executed {} steps successfully", results.len())
+            } else {
+                results.first()
+                    .and_then(|r| r.message.as_ref())
+                    .unwrap_or(&"No steps could be executed".to_string())
+                    .clone()
+            };
+            let warnings: Vec<String> = results.into_iter().flat_map(|r| r.warnings).collect();
+            (events, success, with_warnings(message, &warnings))
         };
         
         let current_state = state.manager.get_current_state();
@@ -237,6 +460,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
             current_step_number: state.manager.step_number() as i64,
             success,
             message,
+            current_generation: state.manager.generation() as i64,
         }))
     }
 
@@ -250,6 +474,42 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
         
         let req = request.into_inner();
         let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let run_config = ContinuousSimulationConfig {
+            max_steps: req.max_steps.map(|steps| steps.max(0) as u64),
+            stop_on_fixed_point: req.stop_on_fixed_point,
+            ..ContinuousSimulationConfig::default()
+        };
+        
+        let metrics = req.metrics_interval.map(MetricsRecorder::with_default_collectors);
+        let run_generation = {
+            let mut state = self.state.lock().unwrap();
+            if state.is_running {
+                return Err(Status::failed_precondition("A simulation is already running; stop it before starting another"));
+            }
+            // Open the event log before starting, so a bad name is reported to the caller.
+            // Logs are named within the server's log directory, never by path.
+            let event_log = match req.event_log_path.as_deref().filter(|name| !name.is_empty()) {
+                Some(name) => {
+                    let header = EventLogHeader::capture(&state.manager);
+                    let opened = event_log::event_log_path(&state.event_log_directory, name)
+                        .and_then(|path| EventLogWriter::open(path, header, EventLogConfig::default()));
+                    match opened {
+                        Ok(writer) => Some(writer),
+                        Err(e) => return Err(Status::invalid_argument(format!("Failed to open event log: {}", e))),
+                    }
+                }
+                None => None,
+            };
+            state.manager.set_event_log(event_log);
+            // A run without a metrics interval leaves the last run's metrics for export
+            if let Some(metrics) = metrics {
+                state.manager.set_metrics_recorder(Some(metrics));
+            }
+            state.run_config = run_config;
+            state.is_running = true;
+            state.run_generation += 1;
+            state.run_generation
+        };
         
         // Clone the state Arc to move into the spawned task
         let state_arc = Arc::clone(&self.state);
@@ -275,12 +535,19 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                         vec![]
                     };
                     
+                    // Estimating the dimension explores balls around many atoms, so it
+                    // only runs at the requested interval
+                    let dimension_due = req
+                        .dimension_interval
+                        .is_some_and(|interval| state.manager.step_number().is_multiple_of(interval.max(1)));
                     let update = SimulationStateUpdate {
                         current_graph: Some(hypergraph_state_to_proto(&current_state)),
                         recent_events: events,
                         step_number: state.manager.step_number() as i64,
                         is_running: state.is_running,
-                        status_message: step_result.message.unwrap_or_default(),
+                        status_message: with_warnings(step_result.message.unwrap_or_default(), &step_result.warnings),
+                        estimated_dimension: dimension_due.then(|| estimated_dimension(&state.manager)).flatten(),
+                        metrics: metrics_at_current_step(&state.manager),
                     };
                     
                     (step_result.success, update)
@@ -300,6 +567,8 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                             step_number: state.manager.step_number() as i64,
                             is_running: false,
                             status_message: "Simulation reached fixed point - no more applicable rules".to_string(),
+                            estimated_dimension: req.dimension_interval.and_then(|_| estimated_dimension(&state.manager)),
+                            metrics: Default::default(),
                         }
                     };
                     let _ = tx.send(Ok(final_update)).await;
@@ -308,13 +577,21 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                 
                 tokio::time::sleep(update_interval).await;
             }
+            
+            // A later run may have replaced this one, in which case the event log,
+            // metrics recorder and running flag are already its own
+            let mut state = state_arc.lock().unwrap();
+            if state.run_generation == run_generation {
+                state.finish_run();
+            }
         });
         
-        // Mark simulation as running and store the handle
+        // Store the handle, unless the run was already stopped and replaced
         {
             let mut state = self.state.lock().unwrap();
-            state.is_running = true;
-            state.running_task_handle = Some(handle);
+            if state.run_generation == run_generation && state.is_running {
+                state.running_task_handle = Some(handle);
+            }
         }
         
         Ok(Response::new(ReceiverStream::new(rx)))
@@ -327,11 +604,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
         println!("Got a stop_simulation request: {:?}", request);
         
         let mut state = self.state.lock().unwrap();
-        state.is_running = false;
-        
-        if let Some(handle) = state.running_task_handle.take() {
-            handle.abort();
-        }
+        state.stop_run();
         
         let final_state = state.manager.get_current_state();
         
@@ -348,6 +621,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
     ) -> Result<Response<SimulationStateUpdate>, Status> {
         println!("Got a get_current_state request: {:?}", request);
         
+        let req = request.into_inner();
         let state = self.state.lock().unwrap();
         let current_state = state.manager.get_current_state();
         
@@ -357,6 +631,8 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
             step_number: state.manager.step_number() as i64,
             is_running: state.is_running,
             status_message: "Current state retrieved successfully".to_string(),
+            estimated_dimension: req.estimate_dimension.then(|| estimated_dimension(&state.manager)).flatten(),
+            metrics: metrics_at_current_step(&state.manager),
         }))
     }
 
@@ -367,28 +643,42 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
         println!("Got a save_hypergraph request: {:?}", request);
         
         let req = request.into_inner();
-        let state = self.state.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
         
         let current_state = state.manager.get_current_state();
         
+        let format = if req.format.is_empty() {
+            SnapshotFormat::Json
+        } else {
+            match req.format.parse() {
+                Ok(format) => format,
+                Err(e) => {
+                    return Ok(Response::new(SaveHypergraphResponse {
+                        success: false,
+                        message: e,
+                        file_path: String::new(),
+                        key: String::new(),
+                    }));
+                }
+            }
+        };
+        
         let config = SaveConfig {
-            create_directories: true,
             overwrite_existing: req.overwrite_existing,
             pretty_print: req.pretty_print,
+            format,
+            tags: req.tags,
         };
         
-        let file_path = if req.filename.as_ref().map_or(true, |s| s.is_empty()) {
-            None
-        } else {
-            req.filename.as_ref().map(|s| std::path::Path::new(s))
-        };
+        let name = req.filename.as_deref().filter(|s| !s.is_empty());
         
-        match state.persistence.save_hypergraph_state(&current_state, file_path, Some(config)) {
-            Ok(saved_path) => {
+        match state.persistence.save_hypergraph_state(&current_state, name, Some(config)) {
+            Ok(info) => {
                 Ok(Response::new(SaveHypergraphResponse {
                     success: true,
                     message: "Hypergraph saved successfully".to_string(),
-                    file_path: saved_path.to_string_lossy().to_string(),
+                    file_path: state.persistence.location(&info.key),
+                    key: info.key,
                 }))
             }
             Err(e) => {
@@ -396,6 +686,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                     success: false,
                     message: format!("Failed to save hypergraph: {}", e),
                     file_path: String::new(),
+                    key: String::new(),
                 }))
             }
         }
@@ -411,10 +702,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
         let mut state = self.state.lock().unwrap();
         
         // Stop any running simulation first
-        state.is_running = false;
-        if let Some(handle) = state.running_task_handle.take() {
-            handle.abort();
-        }
+        state.stop_run();
         
         let loaded_state = match req.source {
             Some(source) => match source {
@@ -431,7 +719,7 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                     }
                 }
                 wolfram_sim_rust::wolfram_physics_simulator::load_hypergraph_request::Source::FileContent(content) => {
-                    match serde_json::from_str::<HypergraphState>(&content) {
+                    match state.persistence.parse_hypergraph_state(content.as_bytes()) {
                         Ok(state) => state,
                         Err(e) => {
                             return Ok(Response::new(LoadHypergraphResponse {
@@ -442,13 +730,25 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
                         }
                     }
                 }
+                wolfram_sim_rust::wolfram_physics_simulator::load_hypergraph_request::Source::WolframExpression(expression) => {
+                    match wolfram::parse_hypergraph(&expression) {
+                        Ok(state) => state,
+                        Err(e) => {
+                            return Ok(Response::new(LoadHypergraphResponse {
+                                success: false,
+                                message: format!("Failed to parse Wolfram expression: {}", e),
+                                loaded_state: None,
+                            }));
+                        }
+                    }
+                }
                 wolfram_sim_rust::wolfram_physics_simulator::load_hypergraph_request::Source::FilePath(path) => {
                     match state.persistence.load_hypergraph_state(&path) {
                         Ok(state) => state,
                         Err(e) => {
                             return Ok(Response::new(LoadHypergraphResponse {
                                 success: false,
-                                message: format!("Failed to load hypergraph from file: {}", e),
+                                message: format!("Failed to load saved hypergraph: {}", e),
                                 loaded_state: None,
                             }));
                         }
@@ -482,6 +782,423 @@ impl WolframPhysicsSimulatorService for MyWolframPhysicsSimulator {
             }
         }
     }
+
+    async fn list_saved_hypergraphs(
+        &self,
+        request: Request<ListSavedHypergraphsRequest>,
+    ) -> Result<Response<ListSavedHypergraphsResponse>, Status> {
+        println!("Got a list_saved_hypergraphs request: {:?}", request);
+        
+        let req = request.into_inner();
+        let state = self.state.lock().unwrap();
+        
+        let query = SnapshotQuery {
+            min_step: req.min_step,
+            max_step: req.max_step,
+            min_atoms: req.min_atoms.map(|n| n as usize),
+            max_atoms: req.max_atoms.map(|n| n as usize),
+            tags: req.tags,
+            limit: req.limit.map(|n| n as usize),
+        };
+        
+        match state.persistence.query_saved_hypergraphs(&query) {
+            Ok(snapshots) => Ok(Response::new(ListSavedHypergraphsResponse {
+                success: true,
+                message: format!("Found {} saved hypergraphs", snapshots.len()),
+                snapshots: snapshots
+                    .into_iter()
+                    .map(|info| SavedHypergraphInfo {
+                        key: info.key,
+                        step_number: info.step_number,
+                        atom_count: info.atom_count as u64,
+                        relation_count: info.relation_count as u64,
+                        tags: info.tags,
+                        saved_at: info.saved_at.to_rfc3339(),
+                    })
+                    .collect(),
+            })),
+            Err(e) => Ok(Response::new(ListSavedHypergraphsResponse {
+                success: false,
+                message: format!("Failed to list saved hypergraphs: {}", e),
+                snapshots: Vec::new(),
+            })),
+        }
+    }
+
+    async fn list_predefined_rules(
+        &self,
+        request: Request<ListPredefinedRulesRequest>,
+    ) -> Result<Response<ListPredefinedRulesResponse>, Status> {
+        println!("Got a list_predefined_rules request: {:?}", request);
+        
+        let rules = PredefinedRules::get_all_rule_info()
+            .into_iter()
+            .map(|info| PredefinedRuleInfo {
+                name: info.name.to_string(),
+                description: info.description.to_string(),
+                rule_text: info.notation.to_string(),
+                recommended_initial_state: PredefinedRules::get_recommended_initial_state(info.name)
+                    .as_ref()
+                    .map(hypergraph_state_to_proto),
+                properties: PredefinedRules::get_rule(info.name).map(|rule| rule_properties_to_proto(&rule.properties())),
+            })
+            .collect();
+        
+        Ok(Response::new(ListPredefinedRulesResponse { rules }))
+    }
+
+    async fn get_causal_graph(
+        &self,
+        request: Request<GetCausalGraphRequest>,
+    ) -> Result<Response<GetCausalGraphResponse>, Status> {
+        println!("Got a get_causal_graph request: {:?}", request);
+        
+        let req = request.into_inner();
+        let state = self.state.lock().unwrap();
+        let graph = state.manager.causal_graph();
+        
+        // Events are identified by their step number, matching simulation_event_to_proto
+        let event_id = |id| graph.event(id).map(|e| e.step_number().to_string()).unwrap_or_default();
+        
+        let (past_light_cone, future_light_cone) = match &req.focus_event_id {
+            Some(focus) => {
+                let focus = match focus.parse::<u64>().ok().and_then(|step| graph.find_by_step(step)) {
+                    Some(id) => id,
+                    None => {
+                        return Ok(Response::new(GetCausalGraphResponse {
+                            success: false,
+                            message: format!("Unknown event: {}", focus),
+                            events: vec![],
+                            edges: vec![],
+                            past_light_cone: vec![],
+                            future_light_cone: vec![],
+                        }));
+                    }
+                };
+                let depth = req.light_cone_depth.map(|d| d.max(0) as usize);
+                (
+                    graph.past_light_cone(focus, depth).into_iter().map(event_id).collect(),
+                    graph.future_light_cone(focus, depth).into_iter().map(event_id).collect(),
+                )
+            }
+            None => (vec![], vec![]),
+        };
+        
+        Ok(Response::new(GetCausalGraphResponse {
+            success: true,
+            message: format!("Causal graph has {} events and {} edges", graph.event_count(), graph.edges().len()),
+            events: graph.events().iter().map(simulation_event_to_proto).collect(),
+            edges: graph.edges().iter()
+                .map(|edge| ProtoCausalEdge {
+                    from_event_id: event_id(edge.from),
+                    to_event_id: event_id(edge.to),
+                    relation_id: edge.relation.value().to_string(),
+                })
+                .collect(),
+            past_light_cone,
+            future_light_cone,
+        }))
+    }
+
+    async fn undo_step(
+        &self,
+        request: Request<UndoStepRequest>,
+    ) -> Result<Response<StepResponse>, Status> {
+        println!("Got an undo_step request: {:?}", request);
+        
+        let req = request.into_inner();
+        let mut state = self.state.lock().unwrap();
+        if state.is_running {
+            return Ok(Response::new(history_step_response(&state.manager, vec![], "Stop the running simulation before undoing".to_string())));
+        }
+        
+        let undone = state.manager.undo(req.num_steps.max(1) as u64);
+        let message = if undone.is_empty() {
+            "Nothing to undo".to_string()
+        } else {
+            format!("Undid {} events", undone.len())
+        };
+        Ok(Response::new(history_step_response(&state.manager, undone, message)))
+    }
+
+    async fn redo_step(
+        &self,
+        request: Request<RedoStepRequest>,
+    ) -> Result<Response<StepResponse>, Status> {
+        println!("Got a redo_step request: {:?}", request);
+        
+        let req = request.into_inner();
+        let mut state = self.state.lock().unwrap();
+        if state.is_running {
+            return Ok(Response::new(history_step_response(&state.manager, vec![], "Stop the running simulation before redoing".to_string())));
+        }
+        
+        let redone = state.manager.redo(req.num_steps.max(1) as u64);
+        let message = if redone.is_empty() {
+            "Nothing to redo".to_string()
+        } else {
+            format!("Redid {} events", redone.len())
+        };
+        Ok(Response::new(history_step_response(&state.manager, redone, message)))
+    }
+
+    async fn get_state_at_step(
+        &self,
+        request: Request<GetStateAtStepRequest>,
+    ) -> Result<Response<GetStateAtStepResponse>, Status> {
+        println!("Got a get_state_at_step request: {:?}", request);
+        
+        let req = request.into_inner();
+        let state = self.state.lock().unwrap();
+        
+        if req.step_number < 0 {
+            return Ok(Response::new(GetStateAtStepResponse {
+                success: false,
+                message: format!("Invalid step number: {}", req.step_number),
+                state: None,
+            }));
+        }
+        
+        match state.manager.state_at_step(req.step_number as u64) {
+            Ok(past_state) => Ok(Response::new(GetStateAtStepResponse {
+                success: true,
+                message: format!("Reconstructed state at step {}", req.step_number),
+                state: Some(hypergraph_state_to_proto(&past_state)),
+            })),
+            Err(e) => Ok(Response::new(GetStateAtStepResponse {
+                success: false,
+                message: format!("Failed to reconstruct state: {}", e),
+                state: None,
+            })),
+        }
+    }
+
+    async fn save_session(
+        &self,
+        request: Request<SaveSessionRequest>,
+    ) -> Result<Response<SaveSessionResponse>, Status> {
+        println!("Got a save_session request: {:?}", request);
+        
+        let req = request.into_inner();
+        let mut state = self.state.lock().unwrap();
+        
+        let session = match SimulationSession::capture(&state.manager, state.run_config.clone()) {
+            Ok(session) => session,
+            Err(e) => {
+                return Ok(Response::new(SaveSessionResponse {
+                    success: false,
+                    message: format!("Failed to capture session: {}", e),
+                    file_path: String::new(),
+                    key: String::new(),
+                }));
+            }
+        };
+        
+        let config = SaveConfig {
+            overwrite_existing: req.overwrite_existing,
+            pretty_print: req.pretty_print,
+            ..SaveConfig::default()
+        };
+        
+        let name = req.filename.as_deref().filter(|s| !s.is_empty());
+        
+        match state.persistence.save_session(&session, name, Some(config)) {
+            Ok(key) => Ok(Response::new(SaveSessionResponse {
+                success: true,
+                message: format!("Session saved with {} events", session.events.len()),
+                file_path: state.persistence.session_location(&key),
+                key,
+            })),
+            Err(e) => Ok(Response::new(SaveSessionResponse {
+                success: false,
+                message: format!("Failed to save session: {}", e),
+                file_path: String::new(),
+                key: String::new(),
+            })),
+        }
+    }
+
+    async fn load_session(
+        &self,
+        request: Request<LoadSessionRequest>,
+    ) -> Result<Response<LoadSessionResponse>, Status> {
+        println!("Got a load_session request: {:?}", request);
+        
+        let req = request.into_inner();
+        let mut state = self.state.lock().unwrap();
+        
+        // Stop any running simulation first
+        state.stop_run();
+        
+        let failure = |message: String| LoadSessionResponse {
+            success: false,
+            message,
+            current_state: None,
+            current_step_number: 0,
+            recorded_events: 0,
+        };
+        
+        let session = match req.source {
+            Some(wolfram_sim_rust::wolfram_physics_simulator::load_session_request::Source::FileContent(content)) => {
+                state.persistence.parse_session(&content)
+            }
+            Some(wolfram_sim_rust::wolfram_physics_simulator::load_session_request::Source::FilePath(path)) => {
+                state.persistence.load_session(&path)
+            }
+            None => return Ok(Response::new(failure("No source specified for loading session".to_string()))),
+        };
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => return Ok(Response::new(failure(format!("Failed to load session: {}", e)))),
+        };
+        
+        match session.restore() {
+            Ok(manager) => {
+                state.manager = manager;
+                state.run_config = session.config.clone();
+                Ok(Response::new(LoadSessionResponse {
+                    success: true,
+                    message: format!("Session loaded with {} rules", session.rule_set.len()),
+                    current_state: Some(hypergraph_state_to_proto(&session.current_state)),
+                    current_step_number: session.current_state.step_number() as i64,
+                    recorded_events: session.events.len() as i32,
+                }))
+            }
+            Err(e) => Ok(Response::new(failure(format!("Failed to restore session: {}", e)))),
+        }
+    }
+    
+    async fn export_hypergraph(
+        &self,
+        request: Request<ExportHypergraphRequest>,
+    ) -> Result<Response<ExportHypergraphResponse>, Status> {
+        println!("Got an export_hypergraph request: {:?}", request);
+        
+        let req = request.into_inner();
+        let state = self.state.lock().unwrap();
+        
+        let failure = |message: String| ExportHypergraphResponse {
+            success: false,
+            message,
+            content: String::new(),
+            file_extension: String::new(),
+        };
+        
+        let expansion = if req.expansion.is_empty() {
+            HyperedgeExpansion::default()
+        } else {
+            match req.expansion.parse() {
+                Ok(expansion) => expansion,
+                Err(e) => return Ok(Response::new(failure(e))),
+            }
+        };
+        
+        let (content, file_extension) = match req.format.as_str() {
+            "wolfram" if req.causal_graph => {
+                return Ok(Response::new(failure("The causal graph cannot be exported as a Wolfram expression".to_string())));
+            }
+            "wolfram" => (wolfram::hypergraph_to_wolfram(state.manager.hypergraph()), "wl"),
+            name => {
+                let format = if name.is_empty() {
+                    GraphFormat::GraphMl
+                } else {
+                    match name.parse::<GraphFormat>() {
+                        Ok(format) => format,
+                        Err(e) => return Ok(Response::new(failure(e))),
+                    }
+                };
+                let content = if req.causal_graph {
+                    graph_export::export_causal_graph(state.manager.causal_graph(), format)
+                } else {
+                    graph_export::export_hypergraph(state.manager.hypergraph(), format, expansion)
+                };
+                (content, format.extension())
+            }
+        };
+        
+        Ok(Response::new(ExportHypergraphResponse {
+            success: true,
+            message: format!("Exported {} bytes", content.len()),
+            content,
+            file_extension: file_extension.to_string(),
+        }))
+    }
+    
+    async fn export_metrics(
+        &self,
+        request: Request<ExportMetricsRequest>,
+    ) -> Result<Response<ExportMetricsResponse>, Status> {
+        println!("Got an export_metrics request: {:?}", request);
+        
+        let req = request.into_inner();
+        let state = self.state.lock().unwrap();
+        
+        let failure = |message: String| ExportMetricsResponse { success: false, message, content: String::new() };
+        
+        let Some(recorder) = state.metrics_recorder() else {
+            return Ok(Response::new(failure("No metrics recorded; start a run with metrics_interval set".to_string())));
+        };
+        let series = recorder.series();
+        let content = match req.format.as_str() {
+            "" | "csv" => series.to_csv(),
+            "json" => match series.to_json(true) {
+                Ok(json) => json,
+                Err(e) => return Ok(Response::new(failure(format!("Failed to serialize metrics: {}", e)))),
+            },
+            other => return Ok(Response::new(failure(format!("Unknown metrics format: {} (expected csv or json)", other)))),
+        };
+        
+        let message = match recorder.dropped_samples() {
+            0 => format!("Exported {} samples", series.samples.len()),
+            dropped => format!("Exported the latest {} samples; {} older ones were dropped", series.samples.len(), dropped),
+        };
+        Ok(Response::new(ExportMetricsResponse {
+            success: true,
+            message,
+            content,
+        }))
+    }
+}
+
+/// Converts rule properties to their protobuf representation.
+fn rule_properties_to_proto(properties: &RuleProperties) -> ProtoRuleProperties {
+    ProtoRuleProperties {
+        signature: properties.signature.to_string(),
+        relation_growth: properties.relation_growth,
+        atom_growth: properties.atom_growth,
+        arity_conserving: properties.arity_conserving,
+        creates_disconnected_pieces: properties.creates_disconnected_pieces,
+        left_connected: properties.left_connected,
+        right_connected: properties.right_connected,
+    }
+}
+
+/// Builds the response for an undo or redo; it succeeds if any event was undone or redone.
+fn history_step_response(manager: &SimulationManager, events: Vec<SimulationEvent>, message: String) -> StepResponse {
+    StepResponse {
+        new_hypergraph_state: Some(hypergraph_state_to_proto(&manager.get_current_state())),
+        success: !events.is_empty(),
+        events_occurred: events.iter().map(simulation_event_to_proto).collect(),
+        current_step_number: manager.step_number() as i64,
+        message,
+        current_generation: manager.generation() as i64,
+    }
+}
+
+/// Estimates the dimension of the current hypergraph, sampling atoms with the simulation's seed.
+fn estimated_dimension(manager: &SimulationManager) -> Option<f64> {
+    let config = DimensionConfig { seed: manager.random_seed(), ..Default::default() };
+    estimate_dimension(manager.hypergraph(), &config).map(|estimate| estimate.dimension)
+}
+
+/// Returns the metrics sampled at the current step, if the attached recorder took a sample there.
+fn metrics_at_current_step(manager: &SimulationManager) -> HashMap<String, f64> {
+    manager
+        .metrics_recorder()
+        .and_then(|recorder| recorder.last_sample())
+        .filter(|sample| sample.step_number == manager.step_number())
+        .map(|sample| sample.values.clone().into_iter().collect())
+        .unwrap_or_default()
 }
 
 #[tokio::main]
@@ -509,8 +1226,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("  {}: {}", example_name, info);
     }
 
+    // Snapshots and sessions go to the store named by WOLFRAM_SNAPSHOT_STORE, e.g.
+    // "sqlite:saved_hypergraphs/snapshots.db"; the default is the saved_hypergraphs directory
+    let backend: StoreBackend = match std::env::var("WOLFRAM_SNAPSHOT_STORE") {
+        Ok(value) => value.parse()?,
+        Err(_) => StoreBackend::default(),
+    };
+    println!("Saving snapshots to {}", backend);
+    let persistence = PersistenceManager::with_store(backend.open()?);
+    let event_log_directory = PathBuf::from(
+        std::env::var("WOLFRAM_EVENT_LOG_DIR").unwrap_or_else(|_| DEFAULT_EVENT_LOG_DIRECTORY.to_string()),
+    );
+    println!("Writing event logs to {}", event_log_directory.display());
+
     let addr = "0.0.0.0:50051".parse()?;
-    let simulator_service = MyWolframPhysicsSimulator::new();
+    let simulator_service = MyWolframPhysicsSimulator::with_persistence(persistence, event_log_directory);
 
     println!("WolframPhysicsSimulatorService listening on {}", addr);
 
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use crate::evolution::RewriteResult;
+use crate::hypergraph::{Hypergraph, RelationId};
+use crate::rules::rule::RuleSet;
+use super::isomorphism::{find_pattern_matches, find_pattern_matches_with_relation, PatternMatch};
+
+/// An incrementally maintained index of every match of every rule in a rule set.
+/// After each rewrite, matches that used a removed relation are dropped and new
+/// matches are searched for only around the newly created relations, so the cost
+/// of a step depends on the size of the rewrite rather than of the hypergraph.
+///
+/// Rules are identified by their position in the rule set the index was built from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchIndex {
+    /// For each rule, its matches sorted by matched relations (in pattern order),
+    /// so that a match can be picked by position in constant time
+    matches: Vec<Vec<PatternMatch>>,
+
+    /// For each relation, the (rule index, match key) of every match that uses it
+    relation_matches: HashMap<RelationId, Vec<(usize, Vec<RelationId>)>>,
+}
+
+impl MatchIndex {
+    /// Builds the index by matching every rule against the whole hypergraph.
+    pub fn new(rule_set: &RuleSet, hypergraph: &Hypergraph) -> Self {
+        let mut index = MatchIndex {
+            matches: vec![Vec::new(); rule_set.len()],
+            relation_matches: HashMap::new(),
+        };
+        for (rule_index, rule) in rule_set.iter().enumerate() {
+            // Sort once rather than inserting each match in place
+            let mut matches = find_pattern_matches(rule.pattern(), hypergraph);
+            matches.sort_by(|a, b| a.matched_relations.cmp(&b.matched_relations));
+            matches.dedup_by(|a, b| a.matched_relations == b.matched_relations);
+            for pattern_match in &matches {
+                index.link(rule_index, &pattern_match.matched_relations);
+            }
+            index.matches[rule_index] = matches;
+        }
+        index
+    }
+
+    /// Updates the index after a successful rewrite of `hypergraph`.
+    pub fn update(&mut self, rule_set: &RuleSet, hypergraph: &Hypergraph, result: &RewriteResult) {
+        for relation_id in &result.removed_relations {
+            self.invalidate(*relation_id);
+        }
+        for (rule_index, rule) in rule_set.iter().enumerate() {
+            for relation_id in &result.new_relations {
+                for pattern_match in find_pattern_matches_with_relation(rule.pattern(), hypergraph, *relation_id) {
+                    self.insert(rule_index, pattern_match);
+                }
+            }
+        }
+    }
+
+    /// Returns the matches of the rule at `rule_index`, ordered by matched relation IDs.
+    pub fn matches(&self, rule_index: usize) -> impl Iterator<Item = &PatternMatch> {
+        self.matches.get(rule_index).into_iter().flatten()
+    }
+
+    /// Returns the match at `position` among the matches of the rule at `rule_index`,
+    /// in the order of `matches`, in constant time.
+    pub fn match_at(&self, rule_index: usize, position: usize) -> Option<&PatternMatch> {
+        self.matches.get(rule_index)?.get(position)
+    }
+
+    /// Returns the number of matches of the rule at `rule_index`.
+    pub fn match_count(&self, rule_index: usize) -> usize {
+        self.matches.get(rule_index).map_or(0, Vec::len)
+    }
+
+    /// Returns the number of rules the index was built for.
+    pub fn rule_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Returns the total number of matches across all rules.
+    pub fn total_matches(&self) -> usize {
+        self.matches.iter().map(Vec::len).sum()
+    }
+
+    /// Returns true if no rule has any match.
+    pub fn is_empty(&self) -> bool {
+        self.matches.iter().all(Vec::is_empty)
+    }
+
+    /// Adds a match, ignoring it if it is already indexed.
+    fn insert(&mut self, rule_index: usize, pattern_match: PatternMatch) {
+        let matches = &self.matches[rule_index];
+        let Err(position) = matches.binary_search_by(|m| m.matched_relations.cmp(&pattern_match.matched_relations))
+        else {
+            return;
+        };
+        self.link(rule_index, &pattern_match.matched_relations);
+        self.matches[rule_index].insert(position, pattern_match);
+    }
+
+    /// Records that the match of the rule at `rule_index` with the given key uses each of its relations.
+    fn link(&mut self, rule_index: usize, key: &[RelationId]) {
+        for relation_id in key {
+            let entries = self.relation_matches.entry(*relation_id).or_default();
+            // A match can use the same relation only once, but keep the entry list free of repeats
+            if !entries.iter().any(|(index, existing)| *index == rule_index && *existing == key) {
+                entries.push((rule_index, key.to_vec()));
+            }
+        }
+    }
+
+    /// Drops every match that uses the given relation.
+    fn invalidate(&mut self, relation_id: RelationId) {
+        let Some(entries) = self.relation_matches.remove(&relation_id) else {
+            return;
+        };
+        for (rule_index, key) in entries {
+            let matches = &mut self.matches[rule_index];
+            if let Ok(position) = matches.binary_search_by(|m| m.matched_relations.cmp(&key)) {
+                matches.remove(position);
+            }
+            // Unlink the match from the other relations it used
+            for other in key.iter().filter(|other| **other != relation_id) {
+                if let Some(other_entries) = self.relation_matches.get_mut(other) {
+                    other_entries.retain(|(index, existing)| !(*index == rule_index && *existing == key));
+                    if other_entries.is_empty() {
+                        self.relation_matches.remove(other);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::apply_rule;
+    use crate::rules::parse_rule_set;
+
+    /// Applies the first indexed match for `steps` steps, checking the index
+    /// against a full rematch after every step.
+    fn check_against_rematch(rules: &str, edges: &[[u64; 2]], steps: usize) {
+        let rule_set = parse_rule_set(rules).unwrap();
+        let mut hypergraph = Hypergraph::new();
+        let atom_count = edges.iter().flatten().max().map_or(0, |max| max + 1);
+        let atoms: Vec<_> = (0..atom_count).map(|_| hypergraph.create_atom()).collect();
+        for [from, to] in edges {
+            hypergraph.create_relation(vec![atoms[*from as usize], atoms[*to as usize]]);
+        }
+
+        let mut index = MatchIndex::new(&rule_set, &hypergraph);
+        for _ in 0..steps {
+            let Some((rule_index, pattern_match)) = (0..rule_set.len())
+                .find_map(|rule_index| index.matches(rule_index).next().map(|m| (rule_index, m.clone())))
+            else {
+                break;
+            };
+            let rule = rule_set.rule_at(rule_index).unwrap();
+            let result = apply_rule(&mut hypergraph, rule, &pattern_match);
+            assert!(result.success);
+            index.update(&rule_set, &hypergraph, &result);
+
+            let rebuilt = MatchIndex::new(&rule_set, &hypergraph);
+            assert_eq!(index.matches, rebuilt.matches);
+            assert_eq!(index.relation_matches.len(), rebuilt.relation_matches.len());
+            for rule_index in 0..rule_set.len() {
+                for (position, pattern_match) in index.matches(rule_index).enumerate() {
+                    assert_eq!(index.match_at(rule_index, position), Some(pattern_match));
+                }
+                assert_eq!(index.match_at(rule_index, index.match_count(rule_index)), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_relation_rule() {
+        check_against_rematch("{{x,y}} -> {{x,z},{z,y}}", &[[0, 1], [1, 2]], 10);
+    }
+
+    #[test]
+    fn test_multi_relation_rules() {
+        check_against_rematch(
+            "{{{x,y},{y,z}} -> {{x,z},{z,w},{w,y}}, {{x,y},{x,z}} -> {{y,z},{x,w}}}",
+            &[[0, 1], [1, 2], [2, 0], [0, 3]],
+            25,
+        );
+    }
+
+    #[test]
+    fn test_invalidation_removes_all_uses() {
+        let rule_set = parse_rule_set("{{x,y},{y,z}} -> {{x,z}}").unwrap();
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<_> = (0..4).map(|_| hypergraph.create_atom()).collect();
+        hypergraph.create_relation(vec![atoms[0], atoms[1]]);
+        hypergraph.create_relation(vec![atoms[1], atoms[2]]);
+        hypergraph.create_relation(vec![atoms[2], atoms[3]]);
+
+        let mut index = MatchIndex::new(&rule_set, &hypergraph);
+        assert_eq!(index.total_matches(), 2);
+
+        let first = index.matches(0).next().unwrap().clone();
+        let result = apply_rule(&mut hypergraph, rule_set.iter().next().unwrap(), &first);
+        index.update(&rule_set, &hypergraph, &result);
+
+        // {0,2},{2,3} is the only path left
+        assert_eq!(index.total_matches(), 1);
+        assert_eq!(index.relation_matches.len(), 2);
+    }
+}
@@ -1,5 +1,4 @@
-use std::collections::{HashSet, HashMap};
-use crate::hypergraph::{Hypergraph, AtomId, RelationId};
+use crate::hypergraph::{AtomId, Hypergraph, RelationId};
 use crate::rules::pattern::{Pattern, PatternElement, Binding, Variable};
 
 /// Represents a match found during pattern matching.
@@ -23,133 +22,263 @@ impl PatternMatch {
 }
 
 /// Finds all matches of a pattern within the given hypergraph.
-/// This implements a basic sub-hypergraph isomorphism algorithm.
+/// Matches are returned ordered by the IDs of their matched relations.
 pub fn find_pattern_matches(pattern: &Pattern, hypergraph: &Hypergraph) -> Vec<PatternMatch> {
     let mut matches = Vec::new();
     
-    // For MVP, we implement a basic backtracking search
     if pattern.is_empty() {
         return matches;
     }
     
-    // Get all relation IDs from the hypergraph
-    let hypergraph_relations: Vec<_> = hypergraph.relation_ids().copied().collect();
-    
-    // Start the pattern matching from the beginning
-    let mut binding = Binding::new();
-    let mut matched_relations = Vec::new();
-    
-    find_all_matches(
-        pattern,
-        hypergraph,
-        0, // Start with first pattern relation
-        &mut binding,
-        &mut matched_relations,
-        &hypergraph_relations,
-        &mut matches,
-    );
+    let compiled = CompiledPattern::new(pattern);
+    let order = compiled.plan(hypergraph, None);
+    Search::new(&compiled, hypergraph, order, None, &mut matches).extend(0);
     
+    matches.sort_by(|a, b| a.matched_relations.cmp(&b.matched_relations));
     matches
 }
 
-/// Finds all possible matches by exploring all combinations.
-fn find_all_matches(
+/// Finds all matches of a pattern that use the given relation, which is how
+/// matches are discovered around newly created relations without rescanning
+/// the whole hypergraph. Matches are ordered as in `find_pattern_matches`.
+pub fn find_pattern_matches_with_relation(
     pattern: &Pattern,
     hypergraph: &Hypergraph,
-    pattern_relation_index: usize,
-    binding: &mut Binding,
-    matched_relations: &mut Vec<RelationId>,
-    available_relations: &[RelationId],
-    all_matches: &mut Vec<PatternMatch>,
-) {
-    // Base case: if we've matched all pattern relations, we have a complete match
-    if pattern_relation_index >= pattern.relations().len() {
-        all_matches.push(PatternMatch::new(binding.clone(), matched_relations.clone()));
-        return;
-    }
+    relation_id: RelationId,
+) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
     
-    let pattern_relation = &pattern.relations()[pattern_relation_index];
+    let Some(relation) = hypergraph.get_relation(relation_id) else {
+        return matches;
+    };
     
-    // Try to match this pattern relation against each available hypergraph relation
-    for &relation_id in available_relations {
-        // Skip if this relation is already matched
-        if matched_relations.contains(&relation_id) {
+    // Try the relation in every pattern position it could fill; the rest of the
+    // search is then seeded from the atoms it binds
+    let compiled = CompiledPattern::new(pattern);
+    for (index, pattern_relation) in compiled.relations.iter().enumerate() {
+        if pattern_relation.len() != relation.arity() {
             continue;
         }
-        
-        if let Some(hypergraph_relation) = hypergraph.get_relation(relation_id) {
-            // Check if this hypergraph relation can match the pattern relation
-            if let Some(new_binding) = try_match_relation(pattern_relation, hypergraph_relation, binding) {
-                // Save current state for backtracking
-                let old_binding = binding.clone();
-                let old_matched_count = matched_relations.len();
-                
-                // Apply the new binding
-                *binding = new_binding;
-                matched_relations.push(relation_id);
-                
-                // Recursively try to match the remaining pattern relations
-                find_all_matches(
-                    pattern,
-                    hypergraph,
-                    pattern_relation_index + 1,
-                    binding,
-                    matched_relations,
-                    available_relations,
-                    all_matches,
-                );
-                
-                // Backtrack: restore previous state
-                *binding = old_binding;
-                matched_relations.truncate(old_matched_count);
+        let order = compiled.plan(hypergraph, Some(index));
+        Search::new(&compiled, hypergraph, order, Some(relation_id), &mut matches).extend(0);
+    }
+    
+    matches.sort_by(|a, b| a.matched_relations.cmp(&b.matched_relations));
+    matches
+}
+
+/// A pattern element with variables replaced by slot indices.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Atom(AtomId),
+    Variable(usize),
+}
+
+/// A pattern prepared for searching, with its variables numbered.
+struct CompiledPattern {
+    variables: Vec<Variable>,
+    relations: Vec<Vec<Slot>>,
+}
+
+impl CompiledPattern {
+    fn new(pattern: &Pattern) -> Self {
+        let mut variables: Vec<Variable> = Vec::new();
+        let relations: Vec<Vec<Slot>> = pattern
+            .relations()
+            .iter()
+            .map(|relation| {
+                relation
+                    .elements()
+                    .iter()
+                    .map(|element| match element {
+                        PatternElement::Atom(atom_id) => Slot::Atom(*atom_id),
+                        PatternElement::Variable(var) => {
+                            let index = variables.iter().position(|v| v == var).unwrap_or_else(|| {
+                                variables.push(var.clone());
+                                variables.len() - 1
+                            });
+                            Slot::Variable(index)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        CompiledPattern { variables, relations }
+    }
+
+    /// Orders pattern relations by selectivity, starting with `first` if given. Each
+    /// step picks the relation with the most elements already fixed (concrete atoms or
+    /// variables bound by earlier relations), so the search is seeded from bound atoms
+    /// wherever possible; ties go to the relation with the fewest candidates.
+    fn plan(&self, hypergraph: &Hypergraph, first: Option<usize>) -> Vec<usize> {
+        let relations = &self.relations;
+        let mut bound = vec![false; self.variables.len()];
+        let mut remaining: Vec<usize> = (0..relations.len()).collect();
+        let mut order = Vec::with_capacity(relations.len());
+
+        let estimate = |relation: &[Slot]| -> usize {
+            relation
+                .iter()
+                .filter_map(|slot| match slot {
+                    Slot::Atom(atom_id) => Some(hypergraph.atom_degree(*atom_id)),
+                    Slot::Variable(_) => None,
+                })
+                .min()
+                .unwrap_or_else(|| hypergraph.arity_count(relation.len()))
+        };
+
+        while !remaining.is_empty() {
+            let (position, _) = remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, &index)| {
+                    if first == Some(index) {
+                        return (false, std::cmp::Reverse(usize::MAX), 0, 0);
+                    }
+                    let relation = &relations[index];
+                    let fixed = relation
+                        .iter()
+                        .filter(|slot| match slot {
+                            Slot::Atom(_) => true,
+                            Slot::Variable(var) => bound[*var],
+                        })
+                        .count();
+                    (fixed == 0, std::cmp::Reverse(fixed), estimate(relation), index)
+                })
+                .unwrap();
+            let index = remaining.remove(position);
+            for slot in &relations[index] {
+                if let Slot::Variable(var) = slot {
+                    bound[*var] = true;
+                }
             }
+            order.push(index);
         }
+        order
     }
 }
 
+/// Backtracking search state. Variable bindings live in `slots` and are undone
+/// on backtrack, so no bindings are cloned until a complete match is found.
+struct Search<'a> {
+    pattern: &'a CompiledPattern,
+    hypergraph: &'a Hypergraph,
+    order: Vec<usize>,
+    /// If set, the first relation in `order` may only match this relation
+    seed: Option<RelationId>,
+    slots: Vec<Option<AtomId>>,
+    matched: Vec<Option<RelationId>>,
+    matches: &'a mut Vec<PatternMatch>,
+}
 
-
-/// Attempts to match a pattern relation against a hypergraph relation.
-/// Returns Some(new_binding) if successful, None if the match fails.
-fn try_match_relation(
-    pattern_relation: &crate::rules::pattern::PatternRelation,
-    hypergraph_relation: &crate::hypergraph::Relation,
-    current_binding: &Binding,
-) -> Option<Binding> {
-    // Relations must have the same arity to match
-    if pattern_relation.arity() != hypergraph_relation.arity() {
-        return None;
+impl<'a> Search<'a> {
+    fn new(
+        pattern: &'a CompiledPattern,
+        hypergraph: &'a Hypergraph,
+        order: Vec<usize>,
+        seed: Option<RelationId>,
+        matches: &'a mut Vec<PatternMatch>,
+    ) -> Self {
+        Search {
+            pattern,
+            hypergraph,
+            order,
+            seed,
+            slots: vec![None; pattern.variables.len()],
+            matched: vec![None; pattern.relations.len()],
+            matches,
+        }
     }
-    
-    let mut new_binding = current_binding.clone();
-    
-    // Try to match each element in the pattern relation
-    for (pattern_element, &hypergraph_atom_id) in 
-        pattern_relation.elements().iter().zip(hypergraph_relation.atoms().iter()) {
-        
-        match pattern_element {
-            PatternElement::Atom(pattern_atom_id) => {
-                // Concrete atom must match exactly
-                if *pattern_atom_id != hypergraph_atom_id {
-                    return None;
+
+    fn extend(&mut self, depth: usize) {
+        if depth == self.order.len() {
+            self.record_match();
+            return;
+        }
+
+        let index = self.order[depth];
+        let relation = &self.pattern.relations[index];
+        let hypergraph = self.hypergraph;
+        let seed = self.seed.filter(|_| depth == 0);
+
+        // Otherwise seed from the fixed atom with the fewest incident relations, if there is one
+        let fixed_atom = relation
+            .iter()
+            .filter_map(|slot| match *slot {
+                Slot::Atom(atom_id) => Some(atom_id),
+                Slot::Variable(var) => self.slots[var],
+            })
+            .min_by_key(|atom_id| hypergraph.atom_degree(*atom_id));
+        let candidates: Box<dyn Iterator<Item = &RelationId>> = match (&seed, fixed_atom) {
+            (Some(relation_id), _) => Box::new(std::iter::once(relation_id)),
+            (None, Some(atom_id)) => Box::new(hypergraph.relation_ids_with_atom(atom_id)),
+            (None, None) => Box::new(hypergraph.relation_ids_with_arity(relation.len())),
+        };
+
+        for &relation_id in candidates {
+            if self.matched.contains(&Some(relation_id)) {
+                continue;
+            }
+            let Some(candidate) = hypergraph.get_relation(relation_id) else {
+                continue;
+            };
+            if candidate.arity() != relation.len() {
+                continue;
+            }
+            if let Some(newly_bound) = self.try_bind(relation, candidate.atoms()) {
+                self.matched[index] = Some(relation_id);
+                self.extend(depth + 1);
+                self.matched[index] = None;
+                for var in newly_bound {
+                    self.slots[var] = None;
                 }
             }
-            PatternElement::Variable(var) => {
-                // Try to bind the variable to this atom
-                if !new_binding.bind(var.clone(), hypergraph_atom_id) {
-                    return None; // Binding conflict
+        }
+    }
+
+    /// Binds the pattern relation's variables against the candidate's atoms.
+    /// Returns the variables that were newly bound, or None (with nothing bound) on conflict.
+    fn try_bind(&mut self, relation: &[Slot], atoms: &[AtomId]) -> Option<Vec<usize>> {
+        let mut newly_bound = Vec::new();
+        for (slot, &atom_id) in relation.iter().zip(atoms) {
+            let consistent = match *slot {
+                Slot::Atom(pattern_atom_id) => pattern_atom_id == atom_id,
+                Slot::Variable(var) => match self.slots[var] {
+                    Some(bound) => bound == atom_id,
+                    None => {
+                        self.slots[var] = Some(atom_id);
+                        newly_bound.push(var);
+                        true
+                    }
+                },
+            };
+            if !consistent {
+                for var in newly_bound {
+                    self.slots[var] = None;
                 }
+                return None;
             }
         }
+        Some(newly_bound)
+    }
+
+    fn record_match(&mut self) {
+        let mut binding = Binding::new();
+        for (var, atom_id) in self.pattern.variables.iter().zip(&self.slots) {
+            if let Some(atom_id) = atom_id {
+                binding.bind(var.clone(), *atom_id);
+            }
+        }
+        // Matched relations are reported in pattern order, not search order
+        let matched_relations = self.matched.iter().map(|id| id.expect("all relations matched")).collect();
+        self.matches.push(PatternMatch::new(binding, matched_relations));
     }
-    
-    Some(new_binding)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hypergraph::{Hypergraph, AtomId};
+    use crate::hypergraph::Hypergraph;
     use crate::rules::pattern::{Pattern, PatternRelation, PatternElement, Variable};
 
     #[test]
@@ -240,4 +369,50 @@ mod tests {
         
         assert!(matches.is_empty());
     }
-} 
\ No newline at end of file
+
+    fn parse(text: &str) -> Pattern {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_multi_relation_match() {
+        // a -> b -> c, b -> d
+        let mut hypergraph = Hypergraph::new();
+        let a = hypergraph.create_atom();
+        let b = hypergraph.create_atom();
+        let c = hypergraph.create_atom();
+        let d = hypergraph.create_atom();
+        let ab = hypergraph.create_relation(vec![a, b]);
+        let bc = hypergraph.create_relation(vec![b, c]);
+        let bd = hypergraph.create_relation(vec![b, d]);
+        
+        let matches = find_pattern_matches(&parse("{{x,y},{y,z}}"), &hypergraph);
+        assert_eq!(matches.len(), 2);
+        // Relations are reported in pattern order, and matches ordered by relation IDs
+        assert_eq!(matches[0].matched_relations, vec![ab, bc]);
+        assert_eq!(matches[1].matched_relations, vec![ab, bd]);
+        assert_eq!(matches[1].binding.get_binding(&Variable::new("z")), Some(d));
+        
+        // Two edges out of the same atom, in either order
+        let matches = find_pattern_matches(&parse("{{x,y},{x,z}}"), &hypergraph);
+        assert_eq!(matches.len(), 2);
+    }
+
+    #[test]
+    fn test_repeated_and_disconnected_relations() {
+        let mut hypergraph = Hypergraph::new();
+        let a = hypergraph.create_atom();
+        let b = hypergraph.create_atom();
+        hypergraph.create_relation(vec![a, b]);
+        hypergraph.create_relation(vec![a, b]);
+        hypergraph.create_relation(vec![b]);
+        
+        // Identical pattern relations must match distinct relations
+        assert_eq!(find_pattern_matches(&parse("{{x,y},{x,y}}"), &hypergraph).len(), 2);
+        // Disconnected pieces match independently
+        assert_eq!(find_pattern_matches(&parse("{{x,y},{z}}"), &hypergraph).len(), 2);
+        assert_eq!(find_pattern_matches(&parse("{{x},{y}}"), &hypergraph).len(), 0);
+        // A repeated variable within a relation
+        assert!(find_pattern_matches(&parse("{{x,x}}"), &hypergraph).is_empty());
+    }
+}
@@ -1,3 +1,5 @@
 pub mod isomorphism;
+pub mod index;
 
-pub use isomorphism::*; 
\ No newline at end of file
+pub use isomorphism::*;
+pub use index::MatchIndex;
@@ -0,0 +1,238 @@
+use std::cmp::Ordering;
+
+use super::pattern::{Pattern, PatternElement, PatternRelation};
+use super::properties::RuleSignature;
+use super::rule::{Rule, RuleId};
+
+/// Streams every rule with a given signature, once per isomorphism class.
+///
+/// Two rules are the same up to isomorphism when one turns into the other by renaming
+/// variables and reordering relations within a side, since neither changes how the rule
+/// rewrites a hypergraph. Variables are named `1`, `2`, ... in order of first appearance,
+/// as in WolframModel, and rules get sequential IDs starting at 0.
+///
+/// Rules are built as assignments of variables to the slots of the signature, written as
+/// restricted growth strings: each slot holds a variable already used or the next new
+/// one, which covers every rule exactly once up to renaming. A string is yielded when no
+/// reordering of relations relabels it into a smaller one.
+#[derive(Debug, Clone)]
+pub struct RuleEnumerator {
+    signature: RuleSignature,
+    require_connected: bool,
+    left_arities: Vec<usize>,
+    right_arities: Vec<usize>,
+    /// Every reordering of relations that keeps the arity at each position, as indices
+    /// into the relations of both sides
+    orderings: Vec<Vec<usize>>,
+    /// Variable index of each slot, left side first
+    labels: Vec<usize>,
+    next_id: u64,
+    done: bool,
+}
+
+impl RuleEnumerator {
+    /// Creates an enumerator over every rule with `signature`. Signatures with an empty
+    /// left side have no valid rules and yield nothing.
+    pub fn new(signature: RuleSignature) -> Self {
+        let left_arities: Vec<usize> = signature.left.arities().collect();
+        let right_arities: Vec<usize> = signature.right.arities().collect();
+        let slot_count = signature.left.total_arity() + signature.right.total_arity();
+
+        // Relations with the same arity sit next to each other, so reorderings are
+        // products of permutations of those runs
+        let mut orderings = vec![Vec::new()];
+        let mut offset = 0;
+        for side in [&signature.left, &signature.right] {
+            for &(count, _) in side.terms() {
+                orderings = orderings
+                    .iter()
+                    .flat_map(|prefix| {
+                        permutations(count).into_iter().map(move |permutation| {
+                            let mut ordering = prefix.clone();
+                            ordering.extend(permutation.into_iter().map(|i| offset + i));
+                            ordering
+                        })
+                    })
+                    .collect();
+                offset += count;
+            }
+        }
+
+        RuleEnumerator {
+            done: left_arities.is_empty(),
+            signature,
+            require_connected: false,
+            left_arities,
+            right_arities,
+            orderings,
+            labels: vec![0; slot_count],
+            next_id: 0,
+        }
+    }
+
+    /// Only yields rules with a connected pattern whose replacement adds nothing
+    /// disconnected from it, so the rule and every event are connected.
+    pub fn require_connected(mut self, require_connected: bool) -> Self {
+        self.require_connected = require_connected;
+        self
+    }
+
+    /// Returns the signature being enumerated.
+    pub fn signature(&self) -> &RuleSignature {
+        &self.signature
+    }
+
+    /// Moves to the next restricted growth string, returning false after the last one.
+    fn advance(&mut self) -> bool {
+        // A slot can be raised while it does not exceed every earlier label
+        let mut prefix_max = Vec::with_capacity(self.labels.len());
+        let mut max = 0;
+        for &label in &self.labels {
+            max = max.max(label);
+            prefix_max.push(max);
+        }
+        for i in (1..self.labels.len()).rev() {
+            if self.labels[i] <= prefix_max[i - 1] {
+                self.labels[i] += 1;
+                self.labels[i + 1..].fill(0);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns the start and end slot of each relation, left side first.
+    fn relation_slots(&self) -> Vec<(usize, usize)> {
+        let mut start = 0;
+        self.left_arities
+            .iter()
+            .chain(&self.right_arities)
+            .map(|&arity| {
+                start += arity;
+                (start - arity, start)
+            })
+            .collect()
+    }
+
+    /// Checks that no reordering of relations relabels the current string into a smaller one.
+    fn is_canonical(&self) -> bool {
+        let slots = self.relation_slots();
+        self.orderings.iter().all(|ordering| {
+            let mut relabeled: Vec<Option<usize>> = vec![None; self.labels.len()];
+            let mut next = 0;
+            let reordered = ordering.iter().flat_map(|&relation| {
+                let (start, end) = slots[relation];
+                self.labels[start..end].iter()
+            });
+            for (&label, &current) in reordered.zip(&self.labels) {
+                let new_label = *relabeled[label].get_or_insert_with(|| {
+                    next += 1;
+                    next - 1
+                });
+                match new_label.cmp(&current) {
+                    Ordering::Less => return false,
+                    Ordering::Greater => return true,
+                    Ordering::Equal => {}
+                }
+            }
+            true
+        })
+    }
+
+    fn current_rule(&self) -> Rule {
+        let slots = self.relation_slots();
+        let relation = |&(start, end): &(usize, usize)| {
+            PatternRelation::new(
+                self.labels[start..end]
+                    .iter()
+                    .map(|label| PatternElement::variable((label + 1).to_string()))
+                    .collect(),
+            )
+        };
+        let (left, right) = slots.split_at(self.left_arities.len());
+        Rule::new(
+            RuleId::new(self.next_id),
+            Pattern::new(left.iter().map(relation).collect()),
+            Pattern::new(right.iter().map(relation).collect()),
+        )
+    }
+}
+
+impl Iterator for RuleEnumerator {
+    type Item = Rule;
+
+    fn next(&mut self) -> Option<Rule> {
+        while !self.done {
+            let rule = self.is_canonical().then(|| self.current_rule());
+            self.done = !self.advance();
+            if let Some(rule) = rule {
+                let properties = rule.properties();
+                if !self.require_connected || (properties.left_connected && !properties.creates_disconnected_pieces) {
+                    self.next_id += 1;
+                    return Some(rule);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns every permutation of `0..n`.
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    (0..n).fold(vec![Vec::new()], |permutations, _| {
+        permutations
+            .iter()
+            .flat_map(|permutation| {
+                (0..n).filter(|i| !permutation.contains(i)).map(move |i| {
+                    let mut extended = permutation.clone();
+                    extended.push(i);
+                    extended
+                })
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn enumerate(signature: &str, require_connected: bool) -> Vec<Rule> {
+        RuleEnumerator::new(signature.parse().unwrap()).require_connected(require_connected).collect()
+    }
+
+    #[test]
+    fn test_counts_up_to_isomorphism() {
+        // Set partitions of four slots, with no relations to reorder
+        assert_eq!(enumerate("1_2 -> 1_2", false).len(), 15);
+        assert_eq!(enumerate("1_2 -> 1_2", true).len(), 11);
+
+        // The eleven two-edge directed multigraphs, three of them disconnected
+        let pairs = enumerate("2_2 -> 0", false);
+        assert_eq!(pairs.len(), 11);
+        assert_eq!(enumerate("2_2 -> 0", true).len(), 8);
+        let notations: HashSet<String> = pairs.iter().map(|rule| rule.pattern().to_string()).collect();
+        assert!(notations.contains("{{1,2},{2,3}}"));
+        assert!(!notations.contains("{{1,2},{3,1}}"));
+
+        assert_eq!(enumerate("0 -> 1_2", false).len(), 0);
+    }
+
+    #[test]
+    fn test_rules_are_distinct_and_valid() {
+        let rules = enumerate("{{2,2}} -> {{3,2}}", true);
+        assert!(!rules.is_empty());
+        assert_eq!(rules.iter().map(|rule| rule.id().value()).collect::<Vec<_>>(), (0..rules.len() as u64).collect::<Vec<_>>());
+
+        let signature: RuleSignature = "2_2 -> 3_2".parse().unwrap();
+        let notations: HashSet<String> = rules.iter().map(|rule| rule.to_string()).collect();
+        assert_eq!(notations.len(), rules.len());
+        for rule in &rules {
+            assert_eq!(rule.signature(), signature);
+            assert!(rule.validate().is_ok());
+            assert!(rule.properties().left_connected);
+        }
+        assert_eq!(rules[0].to_string(), "{{1,1},{1,1}} -> {{1,1},{1,1},{1,1}}");
+    }
+}
@@ -1,6 +1,14 @@
 pub mod rule;
 pub mod pattern;
+pub mod parser;
+pub mod registry;
+pub mod properties;
+pub mod enumeration;
 
 // Re-export main types for convenience
 pub use rule::{Rule, RuleId};
-pub use pattern::{Pattern, Variable, Binding}; 
\ No newline at end of file
+pub use pattern::{Pattern, Variable, Binding};
+pub use parser::{parse_pattern, parse_rule, parse_rule_set, RuleParseError, RuleParseResult};
+pub use registry::{PredefinedRules, RuleInfo};
+pub use properties::{RuleProperties, RuleSignature, SideSignature};
+pub use enumeration::RuleEnumerator; 
\ No newline at end of file
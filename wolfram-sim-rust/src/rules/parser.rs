@@ -0,0 +1,599 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::hypergraph::AtomId;
+use crate::rules::pattern::{Pattern, PatternElement, PatternRelation};
+use crate::rules::rule::{Rule, RuleId, RuleSet};
+
+/// Result type for rule parsing operations.
+pub type RuleParseResult<T> = Result<T, RuleParseError>;
+
+/// Error produced when rule text cannot be parsed.
+/// Line and column are 1-based and point at the offending character.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} at line {line}, column {column}")]
+pub struct RuleParseError {
+    /// Human-readable description of the problem
+    pub message: String,
+
+    /// Line on which the error occurred (1-based)
+    pub line: usize,
+
+    /// Column on which the error occurred (1-based)
+    pub column: usize,
+}
+
+impl RuleParseError {
+    /// Creates a new parse error at the given position.
+    pub fn new<S: Into<String>>(message: S, line: usize, column: usize) -> Self {
+        RuleParseError {
+            message: message.into(),
+            line,
+            column,
+        }
+    }
+
+    pub(crate) fn at(message: impl Into<String>, expr: &Expr) -> Self {
+        Self::new(message, expr.line, expr.column)
+    }
+}
+
+/// A parsed Wolfram Language expression, limited to the subset needed for
+/// rules and hypergraphs: lists, symbols, integers, strings, heads and rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Expr {
+    pub(crate) kind: ExprKind,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ExprKind {
+    /// `{a, b, ...}`
+    List(Vec<Expr>),
+    /// A symbol such as `x`, or a pattern such as `x_` (the underscore is dropped)
+    Symbol(String),
+    /// A non-negative integer literal
+    Integer(u64),
+    /// A string literal such as `"A"`
+    Str(String),
+    /// A head applied to arguments, e.g. `Symbol["A"]` or `Atom[3]`
+    Apply(String, Vec<Expr>),
+    /// `lhs -> rhs` or `lhs :> rhs`
+    Rule(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Short description of the expression used in error messages.
+    pub(crate) fn describe(&self) -> &'static str {
+        match self.kind {
+            ExprKind::List(_) => "a list",
+            ExprKind::Symbol(_) => "a symbol",
+            ExprKind::Integer(_) => "an integer",
+            ExprKind::Str(_) => "a string",
+            ExprKind::Apply(_, _) => "an expression",
+            ExprKind::Rule(_, _) => "a rule",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Arrow,
+    Ident(String, bool),
+    Integer(u64),
+    Str(String),
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LBrace => write!(f, "'{{'"),
+            Token::RBrace => write!(f, "'}}'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::Comma => write!(f, "','"),
+            Token::Arrow => write!(f, "'->'"),
+            Token::Ident(name, true) => write!(f, "'{}_'", name),
+            Token::Ident(name, false) => write!(f, "'{}'", name),
+            Token::Integer(n) => write!(f, "'{}'", n),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+/// Tokenizer with line/column tracking.
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Lexer {
+            chars: input.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Skips whitespace and `(* ... *)` comments.
+    fn skip_trivia(&mut self) -> RuleParseResult<()> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('(') => {
+                    let (line, column) = (self.line, self.column);
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.peek() != Some(&'*') {
+                        return Ok(());
+                    }
+                    self.bump();
+                    self.bump();
+                    let mut previous = '\0';
+                    loop {
+                        match self.bump() {
+                            Some(')') if previous == '*' => break,
+                            Some(c) => previous = c,
+                            None => return Err(RuleParseError::new("unterminated comment", line, column)),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Returns the next token along with the position it started at.
+    fn next_token(&mut self) -> RuleParseResult<(Token, usize, usize)> {
+        self.skip_trivia()?;
+        let (line, column) = (self.line, self.column);
+
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Ok((Token::Eof, line, column)),
+        };
+
+        let token = match c {
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ',' => Token::Comma,
+            '→' | '⧴' => Token::Arrow,
+            '-' | ':' => {
+                if self.chars.peek() == Some(&'>') {
+                    self.bump();
+                    Token::Arrow
+                } else {
+                    return Err(RuleParseError::new(format!("unexpected character '{}'", c), line, column));
+                }
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\\') => match self.bump() {
+                            Some(escaped) => value.push(escaped),
+                            None => return Err(RuleParseError::new("unterminated string", line, column)),
+                        },
+                        Some(other) => value.push(other),
+                        None => return Err(RuleParseError::new("unterminated string", line, column)),
+                    }
+                }
+                Token::Str(value)
+            }
+            c if c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some(&d) = self.chars.peek() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    digits.push(d);
+                    self.bump();
+                }
+                let value = digits
+                    .parse::<u64>()
+                    .map_err(|_| RuleParseError::new(format!("integer '{}' is out of range", digits), line, column))?;
+                Token::Integer(value)
+            }
+            c if c.is_alphabetic() || c == '$' => {
+                let mut name = c.to_string();
+                while let Some(&d) = self.chars.peek() {
+                    if !(d.is_alphanumeric() || d == '$') {
+                        break;
+                    }
+                    name.push(d);
+                    self.bump();
+                }
+                let is_pattern = self.chars.peek() == Some(&'_');
+                if is_pattern {
+                    self.bump();
+                }
+                Token::Ident(name, is_pattern)
+            }
+            other => {
+                return Err(RuleParseError::new(format!("unexpected character '{}'", other), line, column));
+            }
+        };
+
+        Ok((token, line, column))
+    }
+}
+
+/// Recursive-descent parser over the token stream.
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    current: (Token, usize, usize),
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> RuleParseResult<Self> {
+        let mut lexer = Lexer::new(input);
+        let current = lexer.next_token()?;
+        Ok(Parser { lexer, current })
+    }
+
+    fn advance(&mut self) -> RuleParseResult<(Token, usize, usize)> {
+        let next = self.lexer.next_token()?;
+        Ok(std::mem::replace(&mut self.current, next))
+    }
+
+    fn error_here(&self, message: String) -> RuleParseError {
+        RuleParseError::new(message, self.current.1, self.current.2)
+    }
+
+    fn expect(&mut self, expected: Token) -> RuleParseResult<()> {
+        if self.current.0 == expected {
+            self.advance()?;
+            Ok(())
+        } else {
+            Err(self.error_here(format!("expected {} but found {}", expected, self.current.0)))
+        }
+    }
+
+    /// expression := primary ( '->' expression )?
+    fn parse_expression(&mut self) -> RuleParseResult<Expr> {
+        let lhs = self.parse_primary()?;
+        if self.current.0 == Token::Arrow {
+            self.advance()?;
+            let rhs = self.parse_primary()?;
+            if self.current.0 == Token::Arrow {
+                return Err(self.error_here("chained rules are not supported".to_string()));
+            }
+            let (line, column) = (lhs.line, lhs.column);
+            return Ok(Expr {
+                kind: ExprKind::Rule(Box::new(lhs), Box::new(rhs)),
+                line,
+                column,
+            });
+        }
+        Ok(lhs)
+    }
+
+    fn parse_primary(&mut self) -> RuleParseResult<Expr> {
+        let (token, line, column) = self.advance()?;
+        let kind = match token {
+            Token::LBrace => ExprKind::List(self.parse_sequence(Token::RBrace)?),
+            Token::Integer(n) => ExprKind::Integer(n),
+            Token::Str(s) => ExprKind::Str(s),
+            Token::Ident(name, is_pattern) => {
+                if !is_pattern && self.current.0 == Token::LBracket {
+                    self.advance()?;
+                    ExprKind::Apply(name, self.parse_sequence(Token::RBracket)?)
+                } else {
+                    ExprKind::Symbol(name)
+                }
+            }
+            other => {
+                return Err(RuleParseError::new(format!("unexpected {}", other), line, column));
+            }
+        };
+        Ok(Expr { kind, line, column })
+    }
+
+    /// Parses comma-separated expressions up to and including `close`.
+    fn parse_sequence(&mut self, close: Token) -> RuleParseResult<Vec<Expr>> {
+        let mut items = Vec::new();
+        if self.current.0 == close {
+            self.advance()?;
+            return Ok(items);
+        }
+        loop {
+            items.push(self.parse_expression()?);
+            if self.current.0 == Token::Comma {
+                self.advance()?;
+            } else if self.current.0 == close {
+                self.advance()?;
+                return Ok(items);
+            } else {
+                return Err(self.error_here(format!(
+                    "expected ',' or {} but found {}",
+                    close, self.current.0
+                )));
+            }
+        }
+    }
+}
+
+/// Parses a complete Wolfram Language expression, rejecting trailing input.
+pub(crate) fn parse_expression(input: &str) -> RuleParseResult<Expr> {
+    let mut parser = Parser::new(input)?;
+    let expr = parser.parse_expression()?;
+    parser.expect(Token::Eof)?;
+    Ok(expr)
+}
+
+fn expr_to_element(expr: &Expr) -> RuleParseResult<PatternElement> {
+    match &expr.kind {
+        ExprKind::Symbol(name) => Ok(PatternElement::variable(name.clone())),
+        // WolframModel treats integers in rules as pattern labels, not concrete atoms
+        ExprKind::Integer(n) => Ok(PatternElement::variable(n.to_string())),
+        ExprKind::Apply(head, args) if head == "Atom" => match args.as_slice() {
+            [Expr { kind: ExprKind::Integer(n), .. }] => Ok(PatternElement::atom(AtomId::new(*n))),
+            _ => Err(RuleParseError::at("Atom[...] expects a single integer ID", expr)),
+        },
+        _ => Err(RuleParseError::at(
+            format!("expected a variable or Atom[id] but found {}", expr.describe()),
+            expr,
+        )),
+    }
+}
+
+fn expr_to_pattern(expr: &Expr) -> RuleParseResult<Pattern> {
+    let relations = match &expr.kind {
+        ExprKind::List(items) => items,
+        _ => {
+            return Err(RuleParseError::at(
+                format!("expected a list of relations but found {}", expr.describe()),
+                expr,
+            ))
+        }
+    };
+
+    let mut pattern = Pattern::new(Vec::with_capacity(relations.len()));
+    for relation in relations {
+        let elements = match &relation.kind {
+            ExprKind::List(elements) => elements,
+            _ => {
+                return Err(RuleParseError::at(
+                    format!("expected a relation such as {{x,y}} but found {}", relation.describe()),
+                    relation,
+                ))
+            }
+        };
+        let elements = elements
+            .iter()
+            .map(expr_to_element)
+            .collect::<RuleParseResult<Vec<_>>>()?;
+        pattern.add_relation(PatternRelation::new(elements));
+    }
+    Ok(pattern)
+}
+
+fn expr_to_rule(id: RuleId, expr: &Expr) -> RuleParseResult<Rule> {
+    match &expr.kind {
+        ExprKind::Rule(lhs, rhs) => {
+            let pattern = expr_to_pattern(lhs)?;
+            if pattern.is_empty() {
+                return Err(RuleParseError::at("left-hand side must contain at least one relation", lhs));
+            }
+            let replacement = expr_to_pattern(rhs)?;
+            Ok(Rule::new(id, pattern, replacement))
+        }
+        _ => Err(RuleParseError::at(
+            format!("expected a rule 'lhs -> rhs' but found {}", expr.describe()),
+            expr,
+        )),
+    }
+}
+
+/// Parses a pattern written in Wolfram notation, e.g. `{{x,y},{y,z}}`.
+pub fn parse_pattern(input: &str) -> RuleParseResult<Pattern> {
+    expr_to_pattern(&parse_expression(input)?)
+}
+
+/// Parses a single rule written in Wolfram notation, e.g.
+/// `{{x,y},{x,z}} -> {{x,z},{y,z},{w,z}}`, and assigns it the given ID.
+///
+/// Symbols (optionally written as patterns like `x_`) and integers become variables;
+/// `Atom[n]` denotes a concrete atom. Both `->` and `:>` are accepted.
+pub fn parse_rule(id: RuleId, input: &str) -> RuleParseResult<Rule> {
+    expr_to_rule(id, &parse_expression(input)?)
+}
+
+/// Parses either a single rule or a list of rules such as `{r1, r2}`.
+/// Rules are assigned sequential IDs starting at 0, in the order written.
+pub fn parse_rule_set(input: &str) -> RuleParseResult<RuleSet> {
+    let expr = parse_expression(input)?;
+    let mut rule_set = RuleSet::new();
+    match &expr.kind {
+        ExprKind::List(items) if items.iter().all(|item| matches!(item.kind, ExprKind::Rule(_, _))) => {
+            for (index, item) in items.iter().enumerate() {
+                rule_set.add_rule(expr_to_rule(RuleId::new(index as u64), item)?);
+            }
+        }
+        _ => rule_set.add_rule(expr_to_rule(RuleId::new(0), &expr)?),
+    }
+    Ok(rule_set)
+}
+
+impl FromStr for Pattern {
+    type Err = RuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_pattern(s)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleParseError;
+
+    /// Parses a rule with ID 0. Use [`parse_rule`] to choose the ID.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_rule(RuleId::new(0), s)
+    }
+}
+
+impl fmt::Display for PatternElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternElement::Atom(atom_id) => write!(f, "Atom[{}]", atom_id.value()),
+            PatternElement::Variable(var) => write!(f, "{}", var.name()),
+        }
+    }
+}
+
+impl fmt::Display for PatternRelation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, element) in self.elements().iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", element)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, relation) in self.relations().iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", relation)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.pattern(), self.replacement())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::pattern::Variable;
+
+    #[test]
+    fn test_parse_basic_rule() {
+        let rule = parse_rule(RuleId::new(7), "{{x,y},{x,z}} -> {{x,z},{y,z},{w,z}}").unwrap();
+
+        assert_eq!(rule.id(), RuleId::new(7));
+        assert_eq!(rule.pattern().len(), 2);
+        assert_eq!(rule.replacement().len(), 3);
+        assert_eq!(
+            rule.replacement().relations()[2].elements()[0].as_variable(),
+            Some(&Variable::new("w"))
+        );
+    }
+
+    #[test]
+    fn test_parse_matches_hand_built_rule() {
+        let parsed: Rule = "{{x,y}} -> {{x,z},{z,y}}".parse().unwrap();
+        let built = Rule::create_basic_edge_splitting_rule();
+
+        assert_eq!(parsed.pattern(), built.pattern());
+        assert_eq!(parsed.replacement(), built.replacement());
+    }
+
+    #[test]
+    fn test_integers_patterns_and_atoms() {
+        let rule = parse_rule(RuleId::new(0), "{{x_, 1}, {Atom[5], y_}} :> {}").unwrap();
+        let elements = rule.pattern().relations()[0].elements();
+
+        assert_eq!(elements[0], PatternElement::variable("x"));
+        assert_eq!(elements[1], PatternElement::variable("1"));
+        assert_eq!(rule.pattern().relations()[1].elements()[0], PatternElement::atom(AtomId::new(5)));
+        assert!(rule.replacement().is_empty());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let inputs = [
+            "{{x,y}} -> {{x,z},{z,y}}",
+            "{{1,2,3},{2,4,5}} -> {{6,6,3},{2,6,1},{6,4,5},{5,4,3}}",
+            "{{Atom[3],x}} -> {{x,Atom[3]},{}}",
+        ];
+        for input in inputs {
+            let rule: Rule = input.parse().unwrap();
+            assert_eq!(rule.to_string(), input);
+            let reparsed: Rule = rule.to_string().parse().unwrap();
+            assert_eq!(reparsed, rule);
+        }
+    }
+
+    #[test]
+    fn test_parse_rule_set() {
+        let rule_set = parse_rule_set("{{{x,y}} -> {{x,y},{y,z}}, {{x}} -> {{x,x}}}").unwrap();
+        assert_eq!(rule_set.len(), 2);
+        assert!(rule_set.get_rule(RuleId::new(1)).is_some());
+
+        let single = parse_rule_set("{{x,y}} -> {{y,x}}").unwrap();
+        assert_eq!(single.len(), 1);
+    }
+
+    #[test]
+    fn test_comments_and_whitespace() {
+        let rule: Rule = "(* signature 12 -> 22 *)\n{{x, y}}\n  -> {{x, z}, {z, y}}".parse().unwrap();
+        assert_eq!(rule.to_string(), "{{x,y}} -> {{x,z},{z,y}}");
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let err = "{{x,y}}\n -> {{x,,z}}".parse::<Rule>().unwrap_err();
+        assert_eq!((err.line, err.column), (2, 9));
+
+        let err = "{{x,y}} -> ".parse::<Rule>().unwrap_err();
+        assert_eq!((err.line, err.column), (1, 12));
+        assert!(err.message.contains("end of input"));
+
+        let err = "{{x,y}}".parse::<Rule>().unwrap_err();
+        assert_eq!((err.line, err.column), (1, 1));
+
+        let err = "{} -> {{x}}".parse::<Rule>().unwrap_err();
+        assert!(err.message.contains("at least one relation"));
+
+        let err = "{{x,y}} -> {{x,\"a\"}}".parse::<Rule>().unwrap_err();
+        assert_eq!((err.line, err.column), (1, 16));
+        assert_eq!(err.to_string(), format!("{} at line 1, column 16", err.message));
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        let pattern: Pattern = "{{x,y},{y,z}}".parse().unwrap();
+        assert_eq!(pattern.len(), 2);
+        assert_eq!(pattern.to_string(), "{{x,y},{y,z}}");
+
+        assert!("{x,y}".parse::<Pattern>().is_err());
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt;
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
-use crate::hypergraph::{AtomId, Relation, RelationId};
+use crate::hypergraph::AtomId;
 
 /// Represents a variable in a rule pattern that can match any atom.
 /// Variables are used in patterns to represent atoms that can match
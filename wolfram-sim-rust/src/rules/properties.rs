@@ -0,0 +1,306 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::hypergraph::AtomId;
+use super::parser::{parse_expression, ExprKind};
+use super::pattern::{Pattern, PatternElement};
+use super::rule::Rule;
+
+/// Relation counts by arity on one side of a rule, e.g. `1_2 2_3` for one binary
+/// and two ternary relations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SideSignature {
+    /// `(count, arity)` pairs in increasing order of arity, with non-zero counts
+    terms: Vec<(usize, usize)>,
+}
+
+impl SideSignature {
+    /// Creates a signature from `(count, arity)` pairs. Pairs with the same arity are
+    /// added up and zero counts dropped.
+    pub fn new(terms: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let mut by_arity: BTreeMap<usize, usize> = BTreeMap::new();
+        for (count, arity) in terms {
+            *by_arity.entry(arity).or_default() += count;
+        }
+        SideSignature {
+            terms: by_arity.into_iter().filter(|&(_, count)| count > 0).map(|(arity, count)| (count, arity)).collect(),
+        }
+    }
+
+    /// Returns the signature of a pattern.
+    pub fn of(pattern: &Pattern) -> Self {
+        Self::new(pattern.relations().iter().map(|relation| (1, relation.arity())))
+    }
+
+    /// Returns the `(count, arity)` pairs in increasing order of arity.
+    pub fn terms(&self) -> &[(usize, usize)] {
+        &self.terms
+    }
+
+    /// Returns the arity of every relation, in increasing order.
+    pub fn arities(&self) -> impl Iterator<Item = usize> + '_ {
+        self.terms.iter().flat_map(|&(count, arity)| std::iter::repeat_n(arity, count))
+    }
+
+    /// Returns the number of relations.
+    pub fn relation_count(&self) -> usize {
+        self.terms.iter().map(|&(count, _)| count).sum()
+    }
+
+    /// Returns the total number of elements over all relations.
+    pub fn total_arity(&self) -> usize {
+        self.terms.iter().map(|&(count, arity)| count * arity).sum()
+    }
+}
+
+impl fmt::Display for SideSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        let terms: Vec<String> = self.terms.iter().map(|(count, arity)| format!("{}_{}", count, arity)).collect();
+        write!(f, "{}", terms.join(" "))
+    }
+}
+
+impl FromStr for SideSignature {
+    type Err = String;
+
+    /// Parses `1_2 2_3`, or the Wolfram form `{{1,2},{2,3}}` of `{count, arity}` pairs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "0" {
+            return Ok(SideSignature::default());
+        }
+        if s.trim_start().starts_with('{') {
+            return from_wolfram_notation(s);
+        }
+        let terms = s
+            .split_whitespace()
+            .map(|term| {
+                let (count, arity) = term
+                    .split_once('_')
+                    .ok_or_else(|| format!("Invalid signature term: {} (expected count_arity, e.g. 2_2)", term))?;
+                let number = |text: &str| text.parse::<usize>().map_err(|_| format!("Invalid signature term: {}", term));
+                Ok((number(count)?, number(arity)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if terms.is_empty() {
+            return Err("Empty signature".to_string());
+        }
+        Ok(SideSignature::new(terms))
+    }
+}
+
+fn from_wolfram_notation(s: &str) -> Result<SideSignature, String> {
+    let invalid = || format!("Invalid signature: {} (expected {{count, arity}} pairs, e.g. {{{{2,2}}}})", s.trim());
+    let expr = parse_expression(s).map_err(|_| invalid())?;
+    let ExprKind::List(items) = expr.kind else {
+        return Err(invalid());
+    };
+    let terms = items
+        .iter()
+        .map(|item| match &item.kind {
+            ExprKind::List(pair) => match pair.as_slice() {
+                [count, arity] => match (&count.kind, &arity.kind) {
+                    (ExprKind::Integer(count), ExprKind::Integer(arity)) => Ok((*count as usize, *arity as usize)),
+                    _ => Err(invalid()),
+                },
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(SideSignature::new(terms))
+}
+
+/// Signature of a rule, such as `1_2 -> 2_2` for rules that turn one binary relation into two.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RuleSignature {
+    /// Signature of the pattern
+    pub left: SideSignature,
+
+    /// Signature of the replacement
+    pub right: SideSignature,
+}
+
+impl RuleSignature {
+    /// Returns the signature of a rule.
+    pub fn of(rule: &Rule) -> Self {
+        RuleSignature {
+            left: SideSignature::of(rule.pattern()),
+            right: SideSignature::of(rule.replacement()),
+        }
+    }
+}
+
+impl fmt::Display for RuleSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.left, self.right)
+    }
+}
+
+impl FromStr for RuleSignature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (left, right) = s
+            .split_once("->")
+            .ok_or_else(|| format!("Invalid rule signature: {} (expected e.g. 1_2 -> 2_2)", s))?;
+        Ok(RuleSignature { left: left.parse()?, right: right.parse()? })
+    }
+}
+
+/// Structural properties of a rule, for classifying and filtering rule spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleProperties {
+    /// Relation counts by arity on each side
+    pub signature: RuleSignature,
+
+    /// Relations added by each event, negative if the rule removes relations
+    pub relation_growth: i64,
+
+    /// Atoms created by each event: the variables that only appear in the replacement.
+    /// Atoms are never removed, so this is also the net atom growth.
+    pub atom_growth: u64,
+
+    /// Whether each event leaves the total arity, the number of atom slots over all
+    /// relations, unchanged
+    pub arity_conserving: bool,
+
+    /// Whether the replacement has a connected piece that shares no atom with the
+    /// pattern, so events add structure disconnected from what they matched
+    pub creates_disconnected_pieces: bool,
+
+    /// Whether the pattern is connected, so matches are local
+    pub left_connected: bool,
+
+    /// Whether the replacement is connected
+    pub right_connected: bool,
+}
+
+impl RuleProperties {
+    /// Analyzes a rule.
+    pub fn of(rule: &Rule) -> Self {
+        let signature = RuleSignature::of(rule);
+        let left_vertices = vertices(rule.pattern());
+        let new_atoms = vertices(rule.replacement()).difference(&left_vertices).count();
+        let right_components = components(rule.replacement());
+
+        RuleProperties {
+            relation_growth: signature.right.relation_count() as i64 - signature.left.relation_count() as i64,
+            atom_growth: new_atoms as u64,
+            arity_conserving: signature.left.total_arity() == signature.right.total_arity(),
+            creates_disconnected_pieces: right_components
+                .iter()
+                .any(|component| component.is_disjoint(&left_vertices)),
+            left_connected: components(rule.pattern()).len() <= 1,
+            right_connected: right_components.len() <= 1,
+            signature,
+        }
+    }
+}
+
+/// A variable or concrete atom of a pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Vertex<'a> {
+    Variable(&'a str),
+    Atom(AtomId),
+}
+
+fn vertex(element: &PatternElement) -> Vertex<'_> {
+    match element {
+        PatternElement::Variable(variable) => Vertex::Variable(variable.name()),
+        PatternElement::Atom(atom_id) => Vertex::Atom(*atom_id),
+    }
+}
+
+fn vertices(pattern: &Pattern) -> HashSet<Vertex<'_>> {
+    pattern.relations().iter().flat_map(|relation| relation.elements().iter().map(vertex)).collect()
+}
+
+/// Splits the vertices of a pattern into connected pieces, where relations connect
+/// the vertices they contain. Empty relations form no piece.
+fn components(pattern: &Pattern) -> Vec<HashSet<Vertex<'_>>> {
+    // Union-find over vertex indices
+    let mut index: HashMap<Vertex<'_>, usize> = HashMap::new();
+    let mut parent: Vec<usize> = Vec::new();
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
+
+    for relation in pattern.relations() {
+        let mut first = None;
+        for element in relation.elements() {
+            let id = *index.entry(vertex(element)).or_insert_with(|| {
+                parent.push(parent.len());
+                parent.len() - 1
+            });
+            match first {
+                None => first = Some(id),
+                Some(root) => {
+                    let (a, b) = (find(&mut parent, root), find(&mut parent, id));
+                    parent[b] = a;
+                }
+            }
+        }
+    }
+
+    let mut pieces: HashMap<usize, HashSet<Vertex<'_>>> = HashMap::new();
+    for (vertex, id) in index {
+        let root = find(&mut parent, id);
+        pieces.entry(root).or_default().insert(vertex);
+    }
+    pieces.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::parser::parse_rule;
+    use crate::rules::RuleId;
+
+    fn properties(notation: &str) -> RuleProperties {
+        parse_rule(RuleId::new(0), notation).unwrap().properties()
+    }
+
+    #[test]
+    fn test_signatures() {
+        let growth = properties("{{x,y}} -> {{x,y},{y,z}}");
+        assert_eq!(growth.signature.to_string(), "1_2 -> 2_2");
+        assert_eq!(growth.relation_growth, 1);
+        assert_eq!(growth.atom_growth, 1);
+        assert!(!growth.arity_conserving);
+
+        let mixed = properties("{{x,y,z},{x,w}} -> {{x,y},{y,z},{z,w,v}}");
+        assert_eq!(mixed.signature.to_string(), "1_2 1_3 -> 2_2 1_3");
+        assert_eq!("1_2 1_3 -> 2_2 1_3".parse::<RuleSignature>().unwrap(), mixed.signature);
+        assert_eq!("2_2->0".parse::<RuleSignature>().unwrap().to_string(), "2_2 -> 0");
+        assert!("2x2 -> 1_2".parse::<RuleSignature>().is_err());
+        assert_eq!("{{2,2}} -> {{3,2},{1,3}}".parse::<RuleSignature>().unwrap().to_string(), "2_2 -> 3_2 1_3");
+        assert!("{{2}} -> {}".parse::<RuleSignature>().is_err());
+    }
+
+    #[test]
+    fn test_conservation_and_connectivity() {
+        let rotation = properties("{{x,y},{y,z}} -> {{y,x},{z,y}}");
+        assert!(rotation.arity_conserving);
+        assert_eq!(rotation.relation_growth, 0);
+        assert_eq!(rotation.atom_growth, 0);
+        assert!(rotation.left_connected && rotation.right_connected);
+        assert!(!rotation.creates_disconnected_pieces);
+
+        let disconnected = properties("{{x,y},{z,w}} -> {{x,y},{z,w},{u,v}}");
+        assert!(!disconnected.left_connected);
+        assert!(disconnected.creates_disconnected_pieces);
+
+        let split = properties("{{x,y},{y,z}} -> {{x,w},{z,v}}");
+        assert!(split.left_connected);
+        assert!(!split.right_connected);
+        assert!(!split.creates_disconnected_pieces);
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+
+use crate::hypergraph::{Atom, AtomId, Relation, RelationId};
+use crate::rules::parser::{parse_pattern, parse_rule};
+use crate::rules::rule::{Rule, RuleId, RuleSet};
+use crate::simulation::HypergraphState;
+
+/// A catalogue entry for a well-known rule.
+struct RuleEntry {
+    name: &'static str,
+    notation: &'static str,
+    description: &'static str,
+    initial_condition: &'static str,
+}
+
+/// The catalogue, in listing order. Rules use Wolfram notation and initial conditions
+/// use nested-list notation where each integer is an atom.
+const RULES: &[RuleEntry] = &[
+    RuleEntry {
+        name: "edge_splitting",
+        notation: "{{x,y}} -> {{x,z},{z,y}}",
+        description: "Splits every edge in two by inserting a new atom. Grows a path from a single edge.",
+        initial_condition: "{{0,1}}",
+    },
+    RuleEntry {
+        name: "edge_extension",
+        notation: "{{x,y}} -> {{x,y},{y,z}}",
+        description: "Keeps each edge and attaches a new edge at its head. The simplest signature 1_2 -> 2_2 growth rule.",
+        initial_condition: "{{0,0}}",
+    },
+    RuleEntry {
+        name: "star_growth",
+        notation: "{{x,y}} -> {{x,y},{x,z}}",
+        description: "Keeps each edge and attaches a new edge at its tail, growing star-like structures.",
+        initial_condition: "{{0,0}}",
+    },
+    RuleEntry {
+        name: "binary_tree",
+        notation: "{{x,y}} -> {{y,z},{y,w}}",
+        description: "Replaces each edge with two edges branching from its head, producing a binary tree.",
+        initial_condition: "{{0,0}}",
+    },
+    RuleEntry {
+        name: "edge_reversal_growth",
+        notation: "{{x,y}} -> {{z,y},{y,x}}",
+        description: "Reverses each edge and adds a new incoming edge at its head.",
+        initial_condition: "{{0,0}}",
+    },
+    RuleEntry {
+        name: "edge_trisection",
+        notation: "{{x,y}} -> {{x,z},{z,w},{w,y}}",
+        description: "Subdivides every edge into three, inserting two new atoms.",
+        initial_condition: "{{0,1}}",
+    },
+    RuleEntry {
+        name: "loop_spawning",
+        notation: "{{x,y}} -> {{x,y},{y,y},{y,z}}",
+        description: "Keeps each edge, adds a self-loop at its head and extends outward.",
+        initial_condition: "{{0,0}}",
+    },
+    RuleEntry {
+        name: "triangle_growth",
+        notation: "{{x,y}} -> {{x,y},{y,z},{z,x}}",
+        description: "Closes a new triangle on every edge.",
+        initial_condition: "{{0,1}}",
+    },
+    RuleEntry {
+        name: "sig22_fan",
+        notation: "{{x,y},{x,z}} -> {{x,z},{y,z},{w,z}}",
+        description: "Signature 2_2 -> 3_2 rule on two edges sharing a tail; redirects edges into a common atom.",
+        initial_condition: "{{0,0},{0,0}}",
+    },
+    RuleEntry {
+        name: "sig22_quad",
+        notation: "{{x,y},{x,z}} -> {{x,z},{x,w},{y,w},{z,w}}",
+        description: "Signature 2_2 -> 4_2 rule on two edges sharing a tail. Grows a complex, roughly uniform network.",
+        initial_condition: "{{0,0},{0,0}}",
+    },
+    RuleEntry {
+        name: "sig22_quad_keep",
+        notation: "{{x,y},{x,z}} -> {{x,y},{x,w},{y,w},{z,w}}",
+        description: "Variant of sig22_quad that keeps the first edge instead of the second.",
+        initial_condition: "{{0,0},{0,0}}",
+    },
+    RuleEntry {
+        name: "sig22_path_shortcut",
+        notation: "{{x,y},{y,z}} -> {{x,z},{z,y},{y,w}}",
+        description: "Signature 2_2 -> 3_2 rule on a two-edge path; adds a shortcut and a new pendant edge.",
+        initial_condition: "{{0,0},{0,0}}",
+    },
+    RuleEntry {
+        name: "sig22_path_cycle",
+        notation: "{{x,y},{y,z}} -> {{x,y},{y,z},{z,w},{w,x}}",
+        description: "Closes every two-edge path into a four-cycle through a new atom.",
+        initial_condition: "{{0,1},{1,2}}",
+    },
+    RuleEntry {
+        name: "sig22_merge",
+        notation: "{{x,y},{z,y}} -> {{x,z},{y,z},{w,z}}",
+        description: "Signature 2_2 -> 3_2 rule on two edges sharing a head.",
+        initial_condition: "{{0,0},{0,0}}",
+    },
+    RuleEntry {
+        name: "sig22_double_branch",
+        notation: "{{x,y},{x,z}} -> {{y,w},{y,z},{z,w},{x,w}}",
+        description: "Signature 2_2 -> 4_2 rule that rewires a fork into a tetrahedral arrangement.",
+        initial_condition: "{{0,0},{0,0}}",
+    },
+    RuleEntry {
+        name: "sig22_conserving",
+        notation: "{{x,y},{y,z}} -> {{y,x},{z,y}}",
+        description: "Reverses two-edge paths. Conserves both the number of edges and atoms.",
+        initial_condition: "{{0,1},{1,2},{2,3}}",
+    },
+    RuleEntry {
+        name: "triangle",
+        notation: "{{x,y,z}} -> {{x,u,w},{y,v,u},{z,w,v}}",
+        description: "Replaces each ternary relation with three, producing nested Sierpinski-like triangles.",
+        initial_condition: "{{0,1,2}}",
+    },
+    RuleEntry {
+        name: "sig23_surface",
+        notation: "{{x,y,y},{y,z,u}} -> {{u,z,z},{u,x,v},{y,u,v}}",
+        description: "Signature 2_3 -> 3_3 rule that grows a two-dimensional, surface-like structure.",
+        initial_condition: "{{0,0,0},{0,0,0}}",
+    },
+    RuleEntry {
+        name: "sig13_branching",
+        notation: "{{x,y,z}} -> {{x,y,w},{y,w,z}}",
+        description: "Splits each ternary relation into two sharing a new atom.",
+        initial_condition: "{{0,0,0}}",
+    },
+    RuleEntry {
+        name: "sig23_pair",
+        notation: "{{x,y,z},{x,u,v}} -> {{x,y,w},{x,w,u},{z,v,w}}",
+        description: "Signature 2_3 -> 3_3 rule on two ternary relations sharing their first atom.",
+        initial_condition: "{{0,0,0},{0,0,0}}",
+    },
+    RuleEntry {
+        name: "unary_growth",
+        notation: "{{x}} -> {{x},{x,y},{y}}",
+        description: "Mixed-arity rule that sprouts a new atom from every unary relation.",
+        initial_condition: "{{0}}",
+    },
+    RuleEntry {
+        name: "self_loop_growth",
+        notation: "{{x,x}} -> {{x,x},{x,y},{y,y}}",
+        description: "Matches only self-loops; spawns a new looped atom connected to each one.",
+        initial_condition: "{{0,0}}",
+    },
+    RuleEntry {
+        name: "cycle_growth",
+        notation: "{{x,y}} -> {{x,z},{z,y},{z,z}}",
+        description: "Subdivides edges and tags each new atom with a self-loop, growing decorated cycles.",
+        initial_condition: "{{0,1},{1,2},{2,0}}",
+    },
+    RuleEntry {
+        name: "sig33_rewire",
+        notation: "{{x,y},{y,z},{z,x}} -> {{x,y},{y,w},{w,z},{z,x},{w,x}}",
+        description: "Matches directed triangles and subdivides one side, keeping the triangle closed.",
+        initial_condition: "{{0,1},{1,2},{2,0}}",
+    },
+];
+
+/// Predefined rules from the Wolfram Physics Project, the rule counterpart of
+/// [`PredefinedExamples`](crate::serialization::PredefinedExamples).
+pub struct PredefinedRules;
+
+impl PredefinedRules {
+    /// Returns a list of all available predefined rule names.
+    pub fn list_rules() -> Vec<&'static str> {
+        RULES.iter().map(|entry| entry.name).collect()
+    }
+
+    fn entry(name: &str) -> Option<&'static RuleEntry> {
+        RULES.iter().find(|entry| entry.name == name)
+    }
+
+    /// Gets a predefined rule by name. The rule is given ID 0.
+    pub fn get_rule(name: &str) -> Option<Rule> {
+        Self::get_rule_with_id(name, RuleId::new(0))
+    }
+
+    /// Gets a predefined rule by name with the specified ID.
+    pub fn get_rule_with_id(name: &str, id: RuleId) -> Option<Rule> {
+        let entry = Self::entry(name)?;
+        let mut rule = parse_rule(id, entry.notation).ok()?;
+        rule.set_name(Some(entry.name.to_string()));
+        Some(rule)
+    }
+
+    /// Gets the Wolfram notation of a predefined rule.
+    pub fn get_notation(name: &str) -> Option<&'static str> {
+        Self::entry(name).map(|entry| entry.notation)
+    }
+
+    /// Gets a description of a predefined rule.
+    pub fn get_description(name: &str) -> Option<&'static str> {
+        Self::entry(name).map(|entry| entry.description)
+    }
+
+    /// Gets the recommended initial condition for a predefined rule.
+    pub fn get_recommended_initial_state(name: &str) -> Option<HypergraphState> {
+        let entry = Self::entry(name)?;
+        initial_state_from_notation(entry.initial_condition).ok()
+    }
+
+    /// Builds a rule set from predefined rule names.
+    /// Rules are assigned sequential IDs in the order given.
+    pub fn create_rule_set<S: AsRef<str>>(names: &[S]) -> Result<RuleSet, String> {
+        let mut rule_set = RuleSet::with_capacity(names.len());
+        for (index, name) in names.iter().enumerate() {
+            let name = name.as_ref();
+            let rule = Self::get_rule_with_id(name, RuleId::new(index as u64))
+                .ok_or_else(|| format!("Unknown predefined rule: {}", name))?;
+            rule_set.add_rule(rule);
+        }
+        Ok(rule_set)
+    }
+
+    /// Validates that every predefined rule and initial condition parses and is consistent.
+    pub fn validate_all_rules() -> Result<(), String> {
+        for entry in RULES {
+            let rule = parse_rule(RuleId::new(0), entry.notation)
+                .map_err(|e| format!("Rule '{}': {}", entry.name, e))?;
+            rule.validate()
+                .map_err(|e| format!("Rule '{}': {}", entry.name, e))?;
+            initial_state_from_notation(entry.initial_condition)
+                .map_err(|e| format!("Rule '{}': invalid initial condition: {}", entry.name, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Helper struct for detailed rule information.
+pub struct RuleInfo {
+    pub name: &'static str,
+    pub notation: &'static str,
+    pub description: &'static str,
+    pub initial_condition: &'static str,
+}
+
+impl PredefinedRules {
+    /// Gets detailed information about all predefined rules.
+    pub fn get_all_rule_info() -> Vec<RuleInfo> {
+        RULES
+            .iter()
+            .map(|entry| RuleInfo {
+                name: entry.name,
+                notation: entry.notation,
+                description: entry.description,
+                initial_condition: entry.initial_condition,
+            })
+            .collect()
+    }
+}
+
+/// Builds a hypergraph state from nested-list notation such as `{{0,1},{1,2}}`.
+/// Atoms are numbered in order of first appearance.
+fn initial_state_from_notation(notation: &str) -> Result<HypergraphState, String> {
+    let pattern = parse_pattern(notation).map_err(|e| e.to_string())?;
+    let mut atom_ids: HashMap<String, AtomId> = HashMap::new();
+    let mut atoms = Vec::new();
+    let mut relations = Vec::new();
+
+    for (index, relation) in pattern.relations().iter().enumerate() {
+        let mut relation_atoms = Vec::with_capacity(relation.arity());
+        for element in relation.elements() {
+            let label = element
+                .as_variable()
+                .map(|var| var.name().to_string())
+                .ok_or_else(|| "initial conditions must not use Atom[...]".to_string())?;
+            let next_id = AtomId::new(atom_ids.len() as u64);
+            let atom_id = *atom_ids.entry(label).or_insert_with(|| {
+                atoms.push(Atom::new(next_id));
+                next_id
+            });
+            relation_atoms.push(atom_id);
+        }
+        relations.push(Relation::new(RelationId::new(index as u64), relation_atoms));
+    }
+
+    let next_atom_id = atoms.len() as u64;
+    let next_relation_id = relations.len() as u64;
+    Ok(HypergraphState::new(atoms, relations, 0, next_atom_id, next_relation_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::SimulationManager;
+
+    #[test]
+    fn test_all_rules_are_valid() {
+        let result = PredefinedRules::validate_all_rules();
+        assert!(result.is_ok(), "All rules should be valid: {:?}", result.err());
+        assert!(PredefinedRules::list_rules().len() >= 20);
+    }
+
+    #[test]
+    fn test_rule_names_are_unique() {
+        let names = PredefinedRules::list_rules();
+        let unique: std::collections::HashSet<_> = names.iter().collect();
+        assert_eq!(unique.len(), names.len());
+    }
+
+    #[test]
+    fn test_get_rule() {
+        let rule = PredefinedRules::get_rule("edge_splitting").unwrap();
+        let basic = Rule::create_basic_edge_splitting_rule();
+        assert_eq!(rule.pattern(), basic.pattern());
+        assert_eq!(rule.replacement(), basic.replacement());
+        assert_eq!(rule.name(), Some("edge_splitting"));
+
+        assert!(PredefinedRules::get_rule("nonexistent").is_none());
+        assert!(PredefinedRules::get_description("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_create_rule_set() {
+        let rule_set = PredefinedRules::create_rule_set(&["triangle", "sig22_quad"]).unwrap();
+        assert_eq!(rule_set.len(), 2);
+        assert_eq!(rule_set.get_rule(RuleId::new(1)).unwrap().name(), Some("sig22_quad"));
+
+        let err = PredefinedRules::create_rule_set(&["triangle", "bogus"]).unwrap_err();
+        assert!(err.contains("bogus"));
+    }
+
+    #[test]
+    fn test_recommended_initial_state() {
+        let state = PredefinedRules::get_recommended_initial_state("sig22_quad").unwrap();
+        assert_eq!(state.atoms().len(), 1);
+        assert_eq!(state.relations().len(), 2);
+        assert_eq!(state.next_atom_id(), 1);
+        assert_eq!(state.next_relation_id(), 2);
+
+        let state = PredefinedRules::get_recommended_initial_state("triangle").unwrap();
+        assert_eq!(state.atoms().len(), 3);
+        assert_eq!(state.relations()[0].arity(), 3);
+    }
+
+    #[test]
+    fn test_every_rule_applies_to_its_initial_state() {
+        for name in PredefinedRules::list_rules() {
+            let state = PredefinedRules::get_recommended_initial_state(name).unwrap();
+            let rule_set = PredefinedRules::create_rule_set(&[name]).unwrap();
+            let mut manager = SimulationManager::from_state(&state, rule_set).unwrap();
+            assert!(manager.step().success, "Rule '{}' should apply to its initial condition", name);
+        }
+    }
+}
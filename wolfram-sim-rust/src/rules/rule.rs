@@ -2,6 +2,7 @@ use std::fmt;
 use serde::{Serialize, Deserialize};
 
 use crate::rules::pattern::{Pattern, PatternElement, PatternRelation};
+use crate::rules::properties::{RuleProperties, RuleSignature};
 
 /// Represents a unique identifier for a rule in the Wolfram Physics Model.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -90,6 +91,43 @@ impl Rule {
         self.name = name;
     }
 
+    /// Returns the signature of this rule, such as `1_2 -> 2_2`.
+    pub fn signature(&self) -> RuleSignature {
+        RuleSignature::of(self)
+    }
+
+    /// Analyzes the structure of this rule: growth, arity conservation and connectivity.
+    pub fn properties(&self) -> RuleProperties {
+        RuleProperties::of(self)
+    }
+
+    /// Checks that this rule can be applied during a simulation.
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pattern.is_empty() {
+            return Err("Rule pattern must contain at least one relation".to_string());
+        }
+
+        // Concrete atoms on the right-hand side must already be matched on the left,
+        // otherwise the rewrite would reference atoms that may not exist.
+        let pattern_atoms: std::collections::HashSet<_> = self.pattern.relations()
+            .iter()
+            .flat_map(|relation| relation.elements().iter().filter_map(PatternElement::as_atom))
+            .collect();
+        for relation in self.replacement.relations() {
+            for atom_id in relation.elements().iter().filter_map(PatternElement::as_atom) {
+                if !pattern_atoms.contains(&atom_id) {
+                    return Err(format!(
+                        "Replacement references atom {} which does not appear in the pattern",
+                        atom_id.value()
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Creates the classic "{{x,y}} -> {{x,z},{z,y}}" rule used in many Wolfram Physics Model examples.
     pub fn create_basic_edge_splitting_rule() -> Self {
         // Pattern: {{x,y}}
@@ -120,7 +158,7 @@ impl Rule {
 }
 
 /// A collection of rules that can be applied during a simulation.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RuleSet {
     /// The rules in this rule set, indexed by their IDs
     rules: Vec<Rule>,
@@ -149,6 +187,11 @@ impl RuleSet {
         self.rules.iter().find(|rule| rule.id() == rule_id)
     }
 
+    /// Returns the rule at `index`, in the order rules were added.
+    pub fn rule_at(&self, index: usize) -> Option<&Rule> {
+        self.rules.get(index)
+    }
+
     /// Returns the number of rules in this rule set.
     pub fn len(&self) -> usize {
         self.rules.len()
@@ -279,6 +322,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate() {
+        assert!(Rule::create_basic_edge_splitting_rule().validate().is_ok());
+
+        let empty = Rule::new(RuleId::new(1), Pattern::new(vec![]), Pattern::new(vec![]));
+        assert!(empty.validate().is_err());
+
+        let dangling = Rule::new(
+            RuleId::new(2),
+            Pattern::from_elements(vec![PatternElement::variable("x")]),
+            Pattern::from_elements(vec![PatternElement::atom(crate::hypergraph::AtomId::new(9))]),
+        );
+        assert!(dangling.validate().unwrap_err().contains("atom 9"));
+    }
+
     #[test]
     fn test_rule_set() {
         let mut ruleset = RuleSet::new();
@@ -0,0 +1,286 @@
+use std::io::{Read, Write};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+use crate::hypergraph::{Atom, AtomId, Relation, RelationId};
+use crate::simulation::{HypergraphState, SimulationRng};
+use super::persistence::{PersistenceError, PersistenceResult};
+
+/// Magic bytes at the start of every binary snapshot.
+pub const BINARY_MAGIC: &[u8; 4] = b"WPHB";
+
+/// Version of the binary layout written by this build.
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+
+/// Flag bit marking a compressed body.
+const FLAG_COMPRESSED: u8 = 1;
+
+/// Returns true if `bytes` start like a binary snapshot.
+pub fn is_binary_snapshot(bytes: &[u8]) -> bool {
+    bytes.starts_with(BINARY_MAGIC)
+}
+
+/// Encodes a state as a binary snapshot, optionally compressing the body.
+///
+/// A snapshot starts with `BINARY_MAGIC`, a version byte and a flags byte; bit 0 of
+/// the flags marks a DEFLATE-compressed body. The body is a sequence of LEB128 varints.
+/// IDs, timestamps and atom references are stored as zigzag deltas from the previous
+/// value of the same kind, so the mostly increasing IDs of a simulated hypergraph take
+/// one or two bytes each.
+pub fn encode_state(state: &HypergraphState, compress: bool) -> PersistenceResult<Vec<u8>> {
+    let mut body = Vec::new();
+    write_varint(&mut body, state.step_number());
+    write_varint(&mut body, state.next_atom_id());
+    write_varint(&mut body, state.next_relation_id());
+    write_varint(&mut body, state.next_timestamp());
+    match state.rng() {
+        Some(rng) => {
+            body.push(1);
+            write_varint(&mut body, rng.seed());
+            write_varint(&mut body, rng.raw_state());
+        }
+        None => body.push(0),
+    }
+
+    write_varint(&mut body, state.atoms().len() as u64);
+    let mut previous_atom = 0;
+    for atom in state.atoms() {
+        write_delta(&mut body, &mut previous_atom, atom.id().value());
+        write_metadata(&mut body, atom.metadata());
+    }
+
+    write_varint(&mut body, state.relations().len() as u64);
+    let mut previous_relation = 0;
+    let mut previous_timestamp = 0;
+    let mut previous_member = 0;
+    for relation in state.relations() {
+        write_delta(&mut body, &mut previous_relation, relation.id().value());
+        write_delta(&mut body, &mut previous_timestamp, relation.created_at());
+        write_varint(&mut body, relation.arity() as u64);
+        for atom_id in relation.atoms() {
+            write_delta(&mut body, &mut previous_member, atom_id.value());
+        }
+        write_metadata(&mut body, relation.metadata());
+    }
+
+    let mut bytes = Vec::with_capacity(body.len() + 6);
+    bytes.extend_from_slice(BINARY_MAGIC);
+    bytes.push(BINARY_FORMAT_VERSION);
+    if compress {
+        bytes.push(FLAG_COMPRESSED);
+        let mut encoder = DeflateEncoder::new(bytes, Compression::default());
+        encoder.write_all(&body)?;
+        Ok(encoder.finish()?)
+    } else {
+        bytes.push(0);
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+}
+
+/// Decodes a binary snapshot written by `encode_state`.
+pub fn decode_state(bytes: &[u8]) -> PersistenceResult<HypergraphState> {
+    if !is_binary_snapshot(bytes) || bytes.len() < 6 {
+        return Err(PersistenceError::InvalidData("Not a binary hypergraph snapshot".to_string()));
+    }
+    let version = bytes[4];
+    if version != BINARY_FORMAT_VERSION {
+        return Err(PersistenceError::UnsupportedVersion {
+            found: version.into(),
+            supported: BINARY_FORMAT_VERSION.into(),
+        });
+    }
+    let flags = bytes[5];
+
+    let decompressed;
+    let body = if flags & FLAG_COMPRESSED != 0 {
+        let mut buffer = Vec::new();
+        DeflateDecoder::new(&bytes[6..])
+            .read_to_end(&mut buffer)
+            .map_err(|e| PersistenceError::InvalidData(format!("Failed to decompress snapshot: {}", e)))?;
+        decompressed = buffer;
+        &decompressed[..]
+    } else {
+        &bytes[6..]
+    };
+
+    let mut reader = Reader { bytes: body, position: 0 };
+    let step_number = reader.varint()?;
+    let next_atom_id = reader.varint()?;
+    let next_relation_id = reader.varint()?;
+    let next_timestamp = reader.varint()?;
+    let rng = match reader.byte()? {
+        0 => None,
+        1 => Some(SimulationRng::from_raw(reader.varint()?, reader.varint()?)),
+        tag => return Err(PersistenceError::InvalidData(format!("Invalid random generator tag {}", tag))),
+    };
+
+    let atom_count = reader.count()?;
+    let mut atoms = Vec::with_capacity(atom_count);
+    let mut previous_atom = 0;
+    for _ in 0..atom_count {
+        let mut atom = Atom::new(AtomId::new(reader.delta(&mut previous_atom)?));
+        atom.set_metadata(reader.metadata()?);
+        atoms.push(atom);
+    }
+
+    let relation_count = reader.count()?;
+    let mut relations = Vec::with_capacity(relation_count);
+    let mut previous_relation = 0;
+    let mut previous_timestamp = 0;
+    let mut previous_member = 0;
+    for _ in 0..relation_count {
+        let id = RelationId::new(reader.delta(&mut previous_relation)?);
+        let created_at = reader.delta(&mut previous_timestamp)?;
+        let arity = reader.count()?;
+        let mut members = Vec::with_capacity(arity);
+        for _ in 0..arity {
+            members.push(AtomId::new(reader.delta(&mut previous_member)?));
+        }
+        let mut relation = Relation::new(id, members);
+        relation.created_at = created_at;
+        relation.set_metadata(reader.metadata()?);
+        relations.push(relation);
+    }
+
+    if reader.position != body.len() {
+        return Err(PersistenceError::InvalidData("Trailing data after snapshot".to_string()));
+    }
+
+    let mut state = HypergraphState::new(atoms, relations, step_number, next_atom_id, next_relation_id)
+        .with_next_timestamp(next_timestamp);
+    state.rng = rng;
+    Ok(state)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Writes `value` as a zigzag-encoded difference from `previous`, then remembers it.
+fn write_delta(out: &mut Vec<u8>, previous: &mut u64, value: u64) {
+    let delta = value.wrapping_sub(*previous) as i64;
+    write_varint(out, ((delta << 1) ^ (delta >> 63)) as u64);
+    *previous = value;
+}
+
+/// Writes absent metadata as 0 and present metadata as its length plus one, then its bytes.
+fn write_metadata(out: &mut Vec<u8>, metadata: Option<&str>) {
+    match metadata {
+        Some(text) => {
+            write_varint(out, text.len() as u64 + 1);
+            out.extend_from_slice(text.as_bytes());
+        }
+        None => write_varint(out, 0),
+    }
+}
+
+/// Cursor over a snapshot body.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn truncated() -> PersistenceError {
+        PersistenceError::InvalidData("Snapshot is truncated".to_string())
+    }
+
+    fn byte(&mut self) -> PersistenceResult<u8> {
+        let byte = *self.bytes.get(self.position).ok_or_else(Self::truncated)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> PersistenceResult<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(PersistenceError::InvalidData("Varint is too long".to_string()))
+    }
+
+    /// Reads a length, rejecting lengths that cannot fit in the remaining bytes.
+    fn count(&mut self) -> PersistenceResult<usize> {
+        let count = self.varint()?;
+        if count > (self.bytes.len() - self.position) as u64 {
+            return Err(Self::truncated());
+        }
+        Ok(count as usize)
+    }
+
+    fn delta(&mut self, previous: &mut u64) -> PersistenceResult<u64> {
+        let zigzag = self.varint()?;
+        let delta = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+        *previous = previous.wrapping_add(delta as u64);
+        Ok(*previous)
+    }
+
+    fn metadata(&mut self) -> PersistenceResult<Option<String>> {
+        let length = self.count()?;
+        if length == 0 {
+            return Ok(None);
+        }
+        let end = self.position + length - 1;
+        let text = std::str::from_utf8(&self.bytes[self.position..end])
+            .map_err(|e| PersistenceError::InvalidData(format!("Invalid metadata: {}", e)))?;
+        self.position = end;
+        Ok(Some(text.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::rule::RuleSet;
+    use crate::serialization::PredefinedExamples;
+    use crate::simulation::SimulationManager;
+
+    fn grown_state(steps: u64) -> HypergraphState {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.step_multiple(steps);
+        manager.get_current_state()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut state = grown_state(20);
+        state.atoms[0].set_metadata(Some("origin".to_string()));
+        state.relations[1].set_metadata(Some("énergie".to_string()));
+
+        for compress in [false, true] {
+            let bytes = encode_state(&state, compress).unwrap();
+            assert!(is_binary_snapshot(&bytes));
+            assert_eq!(decode_state(&bytes).unwrap(), state);
+        }
+    }
+
+    #[test]
+    fn test_much_smaller_than_json() {
+        let state = grown_state(500);
+        let json = serde_json::to_vec(&state).unwrap();
+        let binary = encode_state(&state, false).unwrap();
+        let compressed = encode_state(&state, true).unwrap();
+        assert!(binary.len() * 5 < json.len(), "{} vs {}", binary.len(), json.len());
+        assert!(compressed.len() < binary.len());
+    }
+
+    #[test]
+    fn test_corrupt_snapshots_are_rejected() {
+        let bytes = encode_state(&grown_state(5), false).unwrap();
+        assert!(decode_state(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_state(b"{\"atoms\": []}").is_err());
+
+        let mut newer = bytes.clone();
+        newer[4] = BINARY_FORMAT_VERSION + 1;
+        assert!(decode_state(&newer).is_err());
+    }
+}
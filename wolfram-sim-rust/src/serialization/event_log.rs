@@ -0,0 +1,393 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+
+use crate::rules::rule::RuleSet;
+use crate::simulation::{EventSelectionStrategy, HypergraphState, SimulationEvent, SimulationManager};
+use super::persistence::{PersistenceError, PersistenceResult};
+use super::storage::check_key;
+use super::versioning;
+
+/// Returns the path of the event log called `name` in `directory`. Like snapshot keys,
+/// names must be plain file names: empty names, names starting with `.` and names
+/// with path separators are refused, so a log cannot be placed outside `directory`.
+pub fn event_log_path<P: AsRef<Path>>(directory: P, name: &str) -> PersistenceResult<PathBuf> {
+    check_key(name).map_err(|_| PersistenceError::InvalidPath(format!("Invalid event log name: {:?}", name)))?;
+    Ok(directory.as_ref().join(name))
+}
+
+/// How often an event log is forced to disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventLogConfig {
+    /// Sync after this many appended events (0 = sync after every event)
+    pub sync_every_events: usize,
+
+    /// Sync when this much time has passed since the last sync, whatever the event count
+    pub sync_interval: Duration,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        EventLogConfig {
+            sync_every_events: 256,
+            sync_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// First line of an event log: everything needed to replay the logged events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventLogHeader {
+    /// State the first logged event was applied to
+    pub initial_state: HypergraphState,
+
+    /// Rules of the run
+    pub rule_set: RuleSet,
+
+    /// Event selection strategy
+    pub strategy: EventSelectionStrategy,
+
+    /// Seed of the random number generator used for event selection
+    pub random_seed: u64,
+}
+
+impl EventLogHeader {
+    /// Describes a run starting from the manager's current state.
+    pub fn capture(manager: &SimulationManager) -> Self {
+        EventLogHeader {
+            initial_state: manager.get_current_state(),
+            rule_set: manager.rule_set().clone(),
+            strategy: manager.event_selection_strategy().clone(),
+            random_seed: manager.random_seed(),
+        }
+    }
+}
+
+/// Append-only log of simulation events: a header line in a `FormatHeader` envelope,
+/// then one JSON object per event and line.
+///
+/// Compact JSON escapes newlines inside strings, so a newline always ends a record and
+/// no length prefix is needed. Writes only ever append, so a crash can only leave the
+/// final line partly written; it has no newline, and is ignored by readers and cut off
+/// when the log is reopened. A complete line that does not parse is reported as an
+/// error rather than skipped.
+///
+/// Writes are buffered and synced to disk in batches, so a crash loses at most the
+/// events of the current batch. Undone events stay in the log; events applied after
+/// an undo start again from the undone step number.
+#[derive(Debug)]
+pub struct EventLogWriter {
+    path: PathBuf,
+    header: EventLogHeader,
+    writer: BufWriter<File>,
+    config: EventLogConfig,
+    /// Events appended since the last sync
+    pending: usize,
+    last_sync: Instant,
+}
+
+impl EventLogWriter {
+    /// Opens the log at `path` for appending, creating it with `header` if needed.
+    /// An existing log keeps its own header, and the events appended continue the run
+    /// it records. A partly written final line left by a crash is cut off first.
+    /// Existing files that are not event logs are refused and left untouched.
+    pub fn open<P: AsRef<Path>>(path: P, header: EventLogHeader, config: EventLogConfig) -> PersistenceResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(&path)?;
+        let mut header = header;
+        if file.metadata()?.len() > 0 {
+            if let Some(existing) = check_is_event_log(&path)? {
+                header = existing;
+            }
+            truncate_torn_line(&mut file)?;
+        }
+        if file.metadata()?.len() == 0 {
+            let line = versioning::event_log_header_to_versioned_json(&header)?;
+            file.write_all(line.as_bytes())?;
+            file.write_all(b"\n")?;
+            file.sync_data()?;
+        }
+
+        Ok(EventLogWriter {
+            path,
+            header,
+            writer: BufWriter::new(file),
+            config,
+            pending: 0,
+            last_sync: Instant::now(),
+        })
+    }
+
+    /// Returns the path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the header of the log.
+    pub fn header(&self) -> &EventLogHeader {
+        &self.header
+    }
+
+    /// Appends an event, syncing the log if a batch is complete.
+    pub fn append(&mut self, event: &SimulationEvent) -> PersistenceResult<()> {
+        serde_json::to_writer(&mut self.writer, event)?;
+        self.writer.write_all(b"\n")?;
+        self.pending += 1;
+
+        if self.pending >= self.config.sync_every_events.max(1)
+            || self.last_sync.elapsed() >= self.config.sync_interval
+        {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Writes all buffered events and forces them to disk.
+    pub fn sync(&mut self) -> PersistenceResult<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        self.pending = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+impl Drop for EventLogWriter {
+    fn drop(&mut self) {
+        if self.pending > 0 {
+            let _ = self.sync();
+        }
+    }
+}
+
+/// Checks that the file at `path` is an event log: a header line followed by events,
+/// except for a partly written line. Returns the header, unless the file has none yet
+/// because it ends within the header line.
+fn check_is_event_log(path: &Path) -> PersistenceResult<Option<EventLogHeader>> {
+    let not_a_log = |e: PersistenceError| PersistenceError::InvalidData(format!("{} is not an event log: {}", path.display(), e));
+    let reader = match EventLogReader::open(path) {
+        Ok(reader) => reader,
+        Err(PersistenceError::InvalidData(_)) if !fs::read(path)?.contains(&b'\n') => return Ok(None),
+        Err(e) => return Err(not_a_log(e)),
+    };
+    let header = reader.header().clone();
+    for event in reader {
+        event.map_err(not_a_log)?;
+    }
+    Ok(Some(header))
+}
+
+/// Removes everything after the last newline of `file`, provided it is the start of
+/// an event rather than text that was never part of the log.
+fn truncate_torn_line(file: &mut File) -> PersistenceResult<()> {
+    let length = file.metadata()?.len();
+    if length == 0 {
+        return Ok(());
+    }
+
+    // Scan backwards in blocks for the last newline
+    let mut end = length;
+    let mut buffer = [0u8; 4096];
+    while end > 0 {
+        let start = end.saturating_sub(buffer.len() as u64);
+        let block = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(block)?;
+        if let Some(offset) = block.iter().rposition(|&byte| byte == b'\n') {
+            let keep = start + offset as u64 + 1;
+            if keep != length {
+                check_torn_event(file, keep)?;
+                file.set_len(keep)?;
+            }
+            return Ok(());
+        }
+        end = start;
+    }
+    check_torn_event(file, 0)?;
+    file.set_len(0)?;
+    Ok(())
+}
+
+/// Checks that the partial line starting at `offset` begins like an event.
+fn check_torn_event(file: &mut File, offset: u64) -> PersistenceResult<()> {
+    let mut first = [0u8; 1];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut first)?;
+    if first[0] != b'{' {
+        return Err(PersistenceError::InvalidData("Final line of the file is not a partly written event".to_string()));
+    }
+    Ok(())
+}
+
+/// Lazy iterator over the events of a log written by `EventLogWriter`.
+/// A partly written final line, as left by a crash, ends the iteration.
+#[derive(Debug)]
+pub struct EventLogReader {
+    header: EventLogHeader,
+    reader: BufReader<File>,
+    line: String,
+    line_number: usize,
+}
+
+impl EventLogReader {
+    /// Opens the log at `path` for reading, reading its header line.
+    pub fn open<P: AsRef<Path>>(path: P) -> PersistenceResult<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(PersistenceError::FileNotFound(path.display().to_string()));
+        }
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.ends_with('\n') {
+            return Err(PersistenceError::InvalidData("Missing event log header".to_string()));
+        }
+        let header = versioning::event_log_header_from_versioned_json(line.as_bytes())?;
+        Ok(EventLogReader { header, reader, line, line_number: 1 })
+    }
+
+    /// Returns the header of the log.
+    pub fn header(&self) -> &EventLogHeader {
+        &self.header
+    }
+
+    /// Rebuilds the logged run from the log alone, by replaying its events from the
+    /// header's initial state. An event numbered at or before the current step was
+    /// applied after an undo, so the steps from it on are undone first.
+    pub fn recover<P: AsRef<Path>>(path: P) -> PersistenceResult<SimulationManager> {
+        let reader = Self::open(path)?;
+        let header = reader.header.clone();
+        let mut manager =
+            SimulationManager::from_state(&header.initial_state, header.rule_set).map_err(PersistenceError::InvalidData)?;
+        manager.set_event_selection_strategy(header.strategy);
+        if header.initial_state.rng().is_none() {
+            manager.set_random_seed(header.random_seed);
+        }
+        for event in reader {
+            let event = event?;
+            manager.undo((manager.step_number() + 1).saturating_sub(event.step_number()));
+            manager.replay_event(&event).map_err(PersistenceError::InvalidData)?;
+        }
+        Ok(manager)
+    }
+}
+
+impl Iterator for EventLogReader {
+    type Item = PersistenceResult<SimulationEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.line.clear();
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => None,
+            Ok(_) if !self.line.ends_with('\n') => None,
+            Ok(_) => {
+                self.line_number += 1;
+                Some(serde_json::from_str(&self.line).map_err(|e| {
+                    PersistenceError::InvalidData(format!("Invalid event on line {}: {}", self.line_number, e))
+                }))
+            }
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypergraph::{AtomId, RelationId};
+    use crate::rules::RuleId;
+    use tempfile::TempDir;
+
+    fn event(step: u64) -> SimulationEvent {
+        SimulationEvent::new(step, RuleId::new(1), vec![AtomId::new(step)], vec![RelationId::new(step)], vec![])
+    }
+
+    fn header() -> EventLogHeader {
+        EventLogHeader::capture(&SimulationManager::new())
+    }
+
+    #[test]
+    fn test_append_and_read_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("run").join("events.jsonl");
+        let config = EventLogConfig { sync_every_events: 2, ..Default::default() };
+
+        let mut writer = EventLogWriter::open(&path, header(), config.clone()).unwrap();
+        for step in 1..=5 {
+            writer.append(&event(step)).unwrap();
+        }
+        drop(writer);
+
+        // Reopening appends after the existing events
+        let mut writer = EventLogWriter::open(&path, header(), config).unwrap();
+        writer.append(&event(6)).unwrap();
+        writer.sync().unwrap();
+
+        let reader = EventLogReader::open(&path).unwrap();
+        assert_eq!(reader.header(), &header());
+        let events: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(events, (1..=6).map(event).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_log_names_stay_in_directory() {
+        let directory = Path::new("event_logs");
+        assert_eq!(event_log_path(directory, "run.jsonl").unwrap(), directory.join("run.jsonl"));
+        for name in ["", "..", "../run.jsonl", "/tmp/run.jsonl", "logs/run.jsonl", "logs\\run.jsonl", ".hidden"] {
+            assert!(event_log_path(directory, name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_torn_final_line_is_ignored_and_repaired() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+
+        let mut writer = EventLogWriter::open(&path, header(), EventLogConfig::default()).unwrap();
+        writer.append(&event(1)).unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+
+        // Simulate a crash in the middle of a write
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"step_number\": 2, \"rule").unwrap();
+        drop(file);
+        assert_eq!(EventLogReader::open(&path).unwrap().count(), 1);
+
+        let mut writer = EventLogWriter::open(&path, header(), EventLogConfig::default()).unwrap();
+        writer.append(&event(2)).unwrap();
+        drop(writer);
+        let events: Vec<_> = EventLogReader::open(&path).unwrap().map(Result::unwrap).collect();
+        assert_eq!(events, vec![event(1), event(2)]);
+    }
+
+    #[test]
+    fn test_torn_header_is_rewritten() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+        fs::write(&path, "{\"format\": \"event_log\", \"vers").unwrap();
+        assert!(EventLogReader::open(&path).is_err());
+
+        let mut writer = EventLogWriter::open(&path, header(), EventLogConfig::default()).unwrap();
+        writer.append(&event(1)).unwrap();
+        drop(writer);
+        let events: Vec<_> = EventLogReader::open(&path).unwrap().map(Result::unwrap).collect();
+        assert_eq!(events, vec![event(1)]);
+    }
+
+    #[test]
+    fn test_other_files_are_refused_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        for contents in ["notes\nmore notes", "no newline at all", "{\"not\": \"an event\"}\n{\"step"] {
+            let path = temp_dir.path().join("notes.txt");
+            fs::write(&path, contents).unwrap();
+            assert!(EventLogWriter::open(&path, header(), EventLogConfig::default()).is_err());
+            assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::causal::{CausalEventId, CausalGraph};
+use crate::hypergraph::{AtomId, Hypergraph};
+
+/// File format for graph export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// GraphML, read by networkx, Gephi and yEd
+    GraphMl,
+    /// Graphviz DOT
+    Dot,
+    /// GEXF 1.3, Gephi's native format
+    Gexf,
+}
+
+impl GraphFormat {
+    /// Returns the usual file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GraphFormat::GraphMl => "graphml",
+            GraphFormat::Dot => "dot",
+            GraphFormat::Gexf => "gexf",
+        }
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "graphml" => Ok(GraphFormat::GraphMl),
+            "dot" => Ok(GraphFormat::Dot),
+            "gexf" => Ok(GraphFormat::Gexf),
+            _ => Err(format!("Unknown graph format: {} (expected graphml, dot or gexf)", s)),
+        }
+    }
+}
+
+/// How hyperedges are turned into ordinary edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HyperedgeExpansion {
+    /// A node per atom and per relation, with an edge from each relation to each of its
+    /// atoms carrying the atom's position in the relation
+    #[default]
+    Incidence,
+    /// An edge between each consecutive pair of atoms of a relation, as `WolframModelPlot`
+    /// draws them. Unary relations have no edges.
+    OrderedPairs,
+}
+
+impl FromStr for HyperedgeExpansion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "incidence" => Ok(HyperedgeExpansion::Incidence),
+            "ordered_pairs" => Ok(HyperedgeExpansion::OrderedPairs),
+            _ => Err(format!("Unknown hyperedge expansion: {} (expected incidence or ordered_pairs)", s)),
+        }
+    }
+}
+
+/// Value of a node or edge attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AttributeValue {
+    Integer(u64),
+    Text(String),
+}
+
+impl AttributeValue {
+    fn as_text(&self) -> String {
+        match self {
+            AttributeValue::Integer(n) => n.to_string(),
+            AttributeValue::Text(text) => text.clone(),
+        }
+    }
+}
+
+type Attributes = Vec<(&'static str, AttributeValue)>;
+
+#[derive(Debug)]
+struct Node {
+    id: String,
+    label: String,
+    attributes: Attributes,
+}
+
+#[derive(Debug)]
+struct Edge {
+    source: String,
+    target: String,
+    attributes: Attributes,
+}
+
+/// Directed graph with attributes, written by the format-specific functions.
+#[derive(Debug, Default)]
+struct ExportGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl ExportGraph {
+    /// Returns every attribute name used by nodes or edges, with whether it is
+    /// always an integer, in order of first use.
+    fn attribute_keys<'a>(items: impl Iterator<Item = &'a Attributes>) -> Vec<(&'static str, bool)> {
+        let mut keys: Vec<(&'static str, bool)> = Vec::new();
+        for attributes in items {
+            for (name, value) in attributes {
+                let is_integer = matches!(value, AttributeValue::Integer(_));
+                match keys.iter_mut().find(|(key, _)| key == name) {
+                    Some((_, integer)) => *integer &= is_integer,
+                    None => keys.push((name, is_integer)),
+                }
+            }
+        }
+        keys
+    }
+
+    fn node_keys(&self) -> Vec<(&'static str, bool)> {
+        Self::attribute_keys(self.nodes.iter().map(|node| &node.attributes))
+    }
+
+    fn edge_keys(&self) -> Vec<(&'static str, bool)> {
+        Self::attribute_keys(self.edges.iter().map(|edge| &edge.attributes))
+    }
+}
+
+/// Exports a hypergraph in the given format, expanding hyperedges as requested.
+/// Atom and relation metadata, relation creation timestamps and arities are kept as attributes.
+pub fn export_hypergraph(hypergraph: &Hypergraph, format: GraphFormat, expansion: HyperedgeExpansion) -> String {
+    write_graph(&hypergraph_graph(hypergraph, expansion), format, "hypergraph")
+}
+
+/// Exports a causal graph in the given format, with a node per event and an edge
+/// for each relation an event consumed from an earlier one.
+pub fn export_causal_graph(causal_graph: &CausalGraph, format: GraphFormat) -> String {
+    let mut graph = ExportGraph::default();
+    let event_id = |id: CausalEventId| format!("e{}", id.value());
+    for (index, event) in causal_graph.events().iter().enumerate() {
+        graph.nodes.push(Node {
+            id: event_id(CausalEventId(causal_graph.first_event_id().value() + index)),
+            label: format!("Step {}", event.step_number()),
+            attributes: vec![
+                ("step_number", AttributeValue::Integer(event.step_number())),
+                ("rule_id", AttributeValue::Integer(event.rule_id().value())),
+            ],
+        });
+    }
+    for edge in causal_graph.edges() {
+        graph.edges.push(Edge {
+            source: event_id(edge.from),
+            target: event_id(edge.to),
+            attributes: vec![("relation_id", AttributeValue::Integer(edge.relation.value()))],
+        });
+    }
+    write_graph(&graph, format, "causal_graph")
+}
+
+fn hypergraph_graph(hypergraph: &Hypergraph, expansion: HyperedgeExpansion) -> ExportGraph {
+    let mut graph = ExportGraph::default();
+    let atom_id = |id: AtomId| format!("a{}", id.value());
+
+    for atom in hypergraph.get_all_atoms() {
+        let mut attributes = vec![("kind", AttributeValue::Text("atom".to_string()))];
+        if let Some(metadata) = atom.metadata() {
+            attributes.push(("metadata", AttributeValue::Text(metadata.to_string())));
+        }
+        graph.nodes.push(Node { id: atom_id(atom.id()), label: atom.id().value().to_string(), attributes });
+    }
+
+    for relation in hypergraph.get_all_relations() {
+        let mut attributes = vec![
+            ("relation_id", AttributeValue::Integer(relation.id().value())),
+            ("created_at", AttributeValue::Integer(relation.created_at())),
+            ("arity", AttributeValue::Integer(relation.arity() as u64)),
+        ];
+        if let Some(metadata) = relation.metadata() {
+            attributes.push(("metadata", AttributeValue::Text(metadata.to_string())));
+        }
+
+        match expansion {
+            HyperedgeExpansion::Incidence => {
+                let relation_node = format!("r{}", relation.id().value());
+                for (position, atom) in relation.atoms().iter().enumerate() {
+                    graph.edges.push(Edge {
+                        source: relation_node.clone(),
+                        target: atom_id(*atom),
+                        attributes: vec![("position", AttributeValue::Integer(position as u64))],
+                    });
+                }
+                let mut node_attributes = vec![("kind", AttributeValue::Text("relation".to_string()))];
+                node_attributes.extend(attributes);
+                graph.nodes.push(Node {
+                    id: relation_node,
+                    label: format!("R{}", relation.id().value()),
+                    attributes: node_attributes,
+                });
+            }
+            HyperedgeExpansion::OrderedPairs => {
+                for (position, pair) in relation.atoms().windows(2).enumerate() {
+                    let mut edge_attributes = attributes.clone();
+                    edge_attributes.push(("position", AttributeValue::Integer(position as u64)));
+                    graph.edges.push(Edge {
+                        source: atom_id(pair[0]),
+                        target: atom_id(pair[1]),
+                        attributes: edge_attributes,
+                    });
+                }
+            }
+        }
+    }
+    graph
+}
+
+fn write_graph(graph: &ExportGraph, format: GraphFormat, name: &str) -> String {
+    match format {
+        GraphFormat::GraphMl => write_graphml(graph, name),
+        GraphFormat::Dot => write_dot(graph, name),
+        GraphFormat::Gexf => write_gexf(graph),
+    }
+}
+
+fn write_graphml(graph: &ExportGraph, name: &str) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    for (domain, keys) in [("node", graph.node_keys()), ("edge", graph.edge_keys())] {
+        for (key, is_integer) in keys {
+            let _ = writeln!(
+                out,
+                "  <key id=\"{domain}_{key}\" for=\"{domain}\" attr.name=\"{key}\" attr.type=\"{}\"/>",
+                if is_integer { "long" } else { "string" }
+            );
+        }
+    }
+    let _ = writeln!(out, "  <graph id=\"{}\" edgedefault=\"directed\">", xml_escape(name));
+    for node in &graph.nodes {
+        let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&node.id));
+        let _ = writeln!(out, "      <data key=\"label\">{}</data>", xml_escape(&node.label));
+        for (key, value) in &node.attributes {
+            let _ = writeln!(out, "      <data key=\"node_{}\">{}</data>", key, xml_escape(&value.as_text()));
+        }
+        out.push_str("    </node>\n");
+    }
+    for (index, edge) in graph.edges.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">",
+            index,
+            xml_escape(&edge.source),
+            xml_escape(&edge.target)
+        );
+        for (key, value) in &edge.attributes {
+            let _ = writeln!(out, "      <data key=\"edge_{}\">{}</data>", key, xml_escape(&value.as_text()));
+        }
+        out.push_str("    </edge>\n");
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn write_dot(graph: &ExportGraph, name: &str) -> String {
+    let attribute_list = |label: Option<&str>, attributes: &Attributes| {
+        let mut parts: Vec<String> = label.map(|l| format!("label={}", dot_quote(l))).into_iter().collect();
+        parts.extend(attributes.iter().map(|(key, value)| match value {
+            AttributeValue::Integer(n) => format!("{}={}", key, n),
+            AttributeValue::Text(text) => format!("{}={}", key, dot_quote(text)),
+        }));
+        parts.join(", ")
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph {} {{", dot_quote(name));
+    for node in &graph.nodes {
+        let _ = writeln!(out, "  {} [{}];", dot_quote(&node.id), attribute_list(Some(&node.label), &node.attributes));
+    }
+    for edge in &graph.edges {
+        let _ = writeln!(
+            out,
+            "  {} -> {} [{}];",
+            dot_quote(&edge.source),
+            dot_quote(&edge.target),
+            attribute_list(None, &edge.attributes)
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn write_gexf(graph: &ExportGraph) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n");
+    out.push_str("  <graph mode=\"static\" defaultedgetype=\"directed\">\n");
+
+    let node_keys = graph.node_keys();
+    let edge_keys = graph.edge_keys();
+    for (class, keys) in [("node", &node_keys), ("edge", &edge_keys)] {
+        if keys.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "    <attributes class=\"{}\">", class);
+        for (index, (key, is_integer)) in keys.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>",
+                index,
+                key,
+                if *is_integer { "long" } else { "string" }
+            );
+        }
+        out.push_str("    </attributes>\n");
+    }
+
+    let write_values = |out: &mut String, keys: &[(&str, bool)], attributes: &Attributes| {
+        if attributes.is_empty() {
+            return;
+        }
+        out.push_str("        <attvalues>\n");
+        for (key, value) in attributes {
+            let index = keys.iter().position(|(name, _)| name == key).expect("key collected from attributes");
+            let _ = writeln!(out, "          <attvalue for=\"{}\" value=\"{}\"/>", index, xml_escape(&value.as_text()));
+        }
+        out.push_str("        </attvalues>\n");
+    };
+
+    out.push_str("    <nodes>\n");
+    for node in &graph.nodes {
+        let _ = writeln!(out, "      <node id=\"{}\" label=\"{}\">", xml_escape(&node.id), xml_escape(&node.label));
+        write_values(&mut out, &node_keys, &node.attributes);
+        out.push_str("      </node>\n");
+    }
+    out.push_str("    </nodes>\n    <edges>\n");
+    for (index, edge) in graph.edges.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+            index,
+            xml_escape(&edge.source),
+            xml_escape(&edge.target)
+        );
+        write_values(&mut out, &edge_keys, &edge.attributes);
+        out.push_str("      </edge>\n");
+    }
+    out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn dot_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::wolfram::parse_hypergraph;
+    use crate::simulation::SimulationManager;
+    use crate::rules::rule::RuleSet;
+
+    fn sample() -> Hypergraph {
+        parse_hypergraph(r#"{{1,2,3},{3,Symbol["A"]},{2}}"#).unwrap().to_hypergraph()
+    }
+
+    #[test]
+    fn test_incidence_expansion() {
+        let dot = export_hypergraph(&sample(), GraphFormat::Dot, HyperedgeExpansion::Incidence);
+        // 4 atoms and 3 relation nodes, one edge per atom occurrence
+        assert_eq!(dot.matches("kind=").count(), 7);
+        assert_eq!(dot.matches(" -> ").count(), 6);
+        assert!(dot.contains(r#""r0" -> "a3" [position=2];"#));
+        assert!(dot.contains(r#"metadata="Symbol[\"A\"]""#));
+    }
+
+    #[test]
+    fn test_ordered_pair_expansion() {
+        let graphml = export_hypergraph(&sample(), GraphFormat::GraphMl, HyperedgeExpansion::OrderedPairs);
+        assert_eq!(graphml.matches("<node ").count(), 4);
+        assert_eq!(graphml.matches("<edge ").count(), 3);
+        assert!(graphml.contains(r#"<key id="edge_arity" for="edge" attr.name="arity" attr.type="long"/>"#));
+        assert!(graphml.contains("<data key=\"node_metadata\">Symbol[&quot;A&quot;]</data>"));
+    }
+
+    #[test]
+    fn test_gexf_attributes() {
+        let gexf = export_hypergraph(&sample(), GraphFormat::Gexf, HyperedgeExpansion::Incidence);
+        assert!(gexf.contains(r#"<attributes class="node">"#));
+        assert!(gexf.contains(r#"<attribute id="0" title="kind" type="string"/>"#));
+        assert_eq!(gexf.matches("<edge ").count(), 6);
+    }
+
+    #[test]
+    fn test_causal_graph_export() {
+        let mut manager = SimulationManager::with_hypergraph_and_rules(sample(), RuleSet::create_basic_ruleset());
+        manager.step_multiple(3);
+        let dot = export_causal_graph(manager.causal_graph(), GraphFormat::Dot);
+        assert_eq!(dot.matches("step_number=").count(), 3);
+        assert_eq!(dot.matches(" -> ").count(), manager.causal_graph().edges().len());
+        assert_eq!("gexf".parse(), Ok(GraphFormat::Gexf));
+    }
+}
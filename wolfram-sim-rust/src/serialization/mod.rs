@@ -1,5 +1,19 @@
 pub mod persistence;
 pub mod examples;
+pub mod session;
+pub mod binary;
+pub mod event_log;
+pub mod versioning;
+pub mod wolfram;
+pub mod graph_export;
+pub mod storage;
+pub mod sqlite_store;
 
 pub use persistence::*;
-pub use examples::*; 
\ No newline at end of file
+pub use examples::*;
+pub use session::SimulationSession;
+pub use versioning::{SESSION_FORMAT, SESSION_FORMAT_VERSION};
+pub use event_log::{EventLogConfig, EventLogHeader, EventLogReader, EventLogWriter};
+pub use graph_export::{GraphFormat, HyperedgeExpansion};
+pub use storage::{snapshot_key, FilesystemStore, MemoryStore, SnapshotInfo, SnapshotQuery, SnapshotStore, StoreBackend};
+pub use sqlite_store::SqliteStore; 
\ No newline at end of file
@@ -1,9 +1,12 @@
-use std::fs;
-use std::path::{Path, PathBuf};
-use std::io::{self, Write};
+use std::path::Path;
+use std::io;
+use std::str::FromStr;
 use serde_json;
 
 use crate::simulation::HypergraphState;
+use super::{binary, versioning};
+use super::session::SimulationSession;
+use super::storage::{snapshot_key, FilesystemStore, SnapshotInfo, SnapshotQuery, SnapshotStore};
 
 /// Result type for persistence operations.
 pub type PersistenceResult<T> = Result<T, PersistenceError>;
@@ -25,36 +28,119 @@ pub enum PersistenceError {
     
     #[error("Invalid hypergraph data: {0}")]
     InvalidData(String),
+    
+    #[error("Unsupported format version {found}: this build reads versions up to {supported}")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    
+    #[error("Expected a {expected} file but found {found}")]
+    WrongFormat { expected: String, found: String },
+    
+    #[error("Failed to migrate data from format version {from}: {message}")]
+    Migration { from: u32, message: String },
+    
+    #[error("Snapshot not found: {0}")]
+    SnapshotNotFound(String),
+    
+    #[error("Snapshot already exists and overwrite is disabled: {0}")]
+    AlreadyExists(String),
+    
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
 }
 
 /// Configuration for save operations.
 #[derive(Debug, Clone)]
 pub struct SaveConfig {
-    /// Whether to create directories if they don't exist
-    pub create_directories: bool,
-    
-    /// Whether to overwrite existing files
+    /// Whether to overwrite existing snapshots
     pub overwrite_existing: bool,
     
     /// Whether to format JSON with pretty printing
     pub pretty_print: bool,
+    
+    /// Encoding of saved hypergraph states
+    pub format: SnapshotFormat,
+    
+    /// Tags stored with saved hypergraph states, for querying
+    pub tags: Vec<String>,
 }
 
 impl Default for SaveConfig {
     fn default() -> Self {
         SaveConfig {
-            create_directories: true,
             overwrite_existing: false,
             pretty_print: true,
+            format: SnapshotFormat::Json,
+            tags: Vec::new(),
         }
     }
 }
 
-/// Main persistence manager for hypergraph states.
+/// Encoding of a saved hypergraph state. Loading detects the encoding from the file contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotFormat {
+    /// Human-readable JSON
+    #[default]
+    Json,
+    
+    /// Compact varint-delta binary encoding
+    Binary,
+    
+    /// Binary encoding with a DEFLATE-compressed body
+    CompressedBinary,
+}
+
+impl SnapshotFormat {
+    /// Returns the file extension used for generated filenames.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Json => "json",
+            SnapshotFormat::Binary | SnapshotFormat::CompressedBinary => BINARY_EXTENSION,
+        }
+    }
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(SnapshotFormat::Json),
+            "binary" => Ok(SnapshotFormat::Binary),
+            "compressed_binary" => Ok(SnapshotFormat::CompressedBinary),
+            _ => Err(format!("Unknown snapshot format: {} (expected json, binary or compressed_binary)", s)),
+        }
+    }
+}
+
+/// File extension of binary hypergraph snapshots.
+pub const BINARY_EXTENSION: &str = "hgb";
+
+/// Encodes a hypergraph state in the given format.
+pub fn encode_snapshot(state: &HypergraphState, format: SnapshotFormat, pretty_print: bool) -> PersistenceResult<Vec<u8>> {
+    match format {
+        SnapshotFormat::Json => Ok(versioning::to_versioned_json(state, pretty_print)?.into_bytes()),
+        SnapshotFormat::Binary => binary::encode_state(state, false),
+        SnapshotFormat::CompressedBinary => binary::encode_state(state, true),
+    }
+}
+
+/// Decodes a hypergraph state written by `encode_snapshot`, detecting the format from the bytes.
+/// JSON written by older versions is migrated to the current format.
+pub fn decode_snapshot(bytes: &[u8]) -> PersistenceResult<HypergraphState> {
+    if binary::is_binary_snapshot(bytes) {
+        binary::decode_state(bytes)
+    } else {
+        versioning::from_versioned_json(bytes)
+    }
+}
+
+/// Main persistence manager for hypergraph states. Snapshots and sessions are kept
+/// in a `SnapshotStore` under keys; names given to it may also be file names or
+/// paths, of which only the file name without its extension is used as the key.
 #[derive(Debug)]
 pub struct PersistenceManager {
-    /// Default directory for saving hypergraphs
-    default_save_directory: PathBuf,
+    /// Store for snapshots and sessions
+    store: Box<dyn SnapshotStore>,
 }
 
 impl PersistenceManager {
@@ -63,140 +149,123 @@ impl PersistenceManager {
         Self::with_save_directory("saved_hypergraphs")
     }
     
-    /// Creates a new persistence manager with a custom save directory.
+    /// Creates a new persistence manager keeping its files in a custom save directory.
     pub fn with_save_directory<P: AsRef<Path>>(save_directory: P) -> Self {
-        PersistenceManager {
-            default_save_directory: save_directory.as_ref().to_path_buf(),
-        }
+        Self::with_store(Box::new(FilesystemStore::new(save_directory)))
     }
     
-    /// Saves a hypergraph state to a JSON file.
-    /// If no path is provided, saves to the default directory with a generated filename.
+    /// Creates a persistence manager that keeps snapshots and sessions in `store`.
+    pub fn with_store(store: Box<dyn SnapshotStore>) -> Self {
+        PersistenceManager { store }
+    }
+    
+    /// Saves a hypergraph state under `name`, returning what was stored.
+    /// If no name is provided, a key is generated from the step number and time.
     pub fn save_hypergraph_state(
-        &self,
+        &mut self,
         state: &HypergraphState,
-        path: Option<&Path>,
+        name: Option<&str>,
         config: Option<SaveConfig>,
-    ) -> PersistenceResult<PathBuf> {
+    ) -> PersistenceResult<SnapshotInfo> {
         let config = config.unwrap_or_default();
-        
-        // Determine the save path
-        let save_path = match path {
-            Some(p) => p.to_path_buf(),
-            None => {
-                // Generate a filename based on timestamp and step number
-                let filename = format!(
-                    "hypergraph_step_{}_{}_.json",
-                    state.step_number(),
-                    chrono::Utc::now().format("%Y%m%d_%H%M%S")
-                );
-                self.default_save_directory.join(filename)
-            }
-        };
-        
-        // Create parent directories if needed
-        if config.create_directories {
-            if let Some(parent) = save_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-        }
-        
-        // Check if file exists and handle overwrite policy
-        if save_path.exists() && !config.overwrite_existing {
-            return Err(PersistenceError::InvalidPath(format!(
-                "File already exists and overwrite is disabled: {}",
-                save_path.display()
-            )));
-        }
-        
-        // Serialize the state
-        let json_data = if config.pretty_print {
-            serde_json::to_string_pretty(state)?
-        } else {
-            serde_json::to_string(state)?
+        let key = match name {
+            Some(name) => snapshot_key(name),
+            None => format!(
+                "hypergraph_step_{}_{}",
+                state.step_number(),
+                chrono::Utc::now().format("%Y%m%d_%H%M%S")
+            ),
         };
-        
-        // Write to file
-        let mut file = fs::File::create(&save_path)?;
-        file.write_all(json_data.as_bytes())?;
-        file.flush()?;
-        
-        Ok(save_path)
+        self.store.put(&key, state, &config)
     }
     
-    /// Loads a hypergraph state from a JSON file.
-    pub fn load_hypergraph_state<P: AsRef<Path>>(
-        &self,
-        path: P,
-    ) -> PersistenceResult<HypergraphState> {
-        let path = path.as_ref();
-        
-        // Check if file exists
-        if !path.exists() {
-            return Err(PersistenceError::FileNotFound(path.display().to_string()));
-        }
-        
-        // Read file contents
-        let json_data = fs::read_to_string(path)?;
-        
-        // Deserialize
-        let state: HypergraphState = serde_json::from_str(&json_data)
-            .map_err(|e| PersistenceError::InvalidData(format!("Failed to parse JSON: {}", e)))?;
-        
-        // Basic validation
+    /// Loads and validates the hypergraph state saved under `name`.
+    /// JSON written by older versions is migrated to the current format.
+    pub fn load_hypergraph_state(&self, name: &str) -> PersistenceResult<HypergraphState> {
+        let state = self.store.get(&snapshot_key(name))?;
         self.validate_hypergraph_state(&state)?;
-        
         Ok(state)
     }
     
-    /// Lists all JSON files in the default save directory.
-    pub fn list_saved_hypergraphs(&self) -> PersistenceResult<Vec<PathBuf>> {
-        if !self.default_save_directory.exists() {
-            return Ok(Vec::new());
-        }
-        
-        let mut files = Vec::new();
-        
-        for entry in fs::read_dir(&self.default_save_directory)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() && 
-               path.extension().and_then(|s| s.to_str()) == Some("json") {
-                files.push(path);
-            }
-        }
-        
-        // Sort by modification time (newest first)
-        files.sort_by(|a, b| {
-            let a_time = a.metadata().and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
-            let b_time = b.metadata().and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
-            b_time.cmp(&a_time)
-        });
-        
-        Ok(files)
+    /// Parses and validates a hypergraph state saved as JSON, migrating older versions.
+    pub fn parse_hypergraph_state(&self, json_data: &[u8]) -> PersistenceResult<HypergraphState> {
+        let state = versioning::from_versioned_json(json_data)?;
+        self.validate_hypergraph_state(&state)?;
+        Ok(state)
     }
     
-    /// Deletes a saved hypergraph file.
-    pub fn delete_hypergraph_file<P: AsRef<Path>>(&self, path: P) -> PersistenceResult<()> {
-        let path = path.as_ref();
-        
-        if !path.exists() {
-            return Err(PersistenceError::FileNotFound(path.display().to_string()));
-        }
-        
-        fs::remove_file(path)?;
-        Ok(())
+    /// Saves a simulation session under `name`, returning its key.
+    /// If no name is provided, a key is generated from the step number and time.
+    pub fn save_session(
+        &mut self,
+        session: &SimulationSession,
+        name: Option<&str>,
+        config: Option<SaveConfig>,
+    ) -> PersistenceResult<String> {
+        let config = config.unwrap_or_default();
+        let key = match name {
+            Some(name) => snapshot_key(name),
+            None => format!(
+                "session_step_{}_{}",
+                session.current_state.step_number(),
+                chrono::Utc::now().format("%Y%m%d_%H%M%S")
+            ),
+        };
+        let json_data = versioning::session_to_versioned_json(session, config.pretty_print)?;
+        self.store.put_session(&key, json_data.as_bytes(), &config)?;
+        Ok(key)
     }
     
-    /// Gets the default save directory.
-    pub fn default_save_directory(&self) -> &Path {
-        &self.default_save_directory
+    /// Loads the simulation session saved under `name`.
+    pub fn load_session(&self, name: &str) -> PersistenceResult<SimulationSession> {
+        let contents = self.store.get_session(&snapshot_key(name))?;
+        let json_data = String::from_utf8(contents)
+            .map_err(|e| PersistenceError::InvalidData(format!("Session is not UTF-8: {}", e)))?;
+        self.parse_session(&json_data)
     }
     
-    /// Sets a new default save directory.
-    pub fn set_default_save_directory<P: AsRef<Path>>(&mut self, directory: P) {
-        self.default_save_directory = directory.as_ref().to_path_buf();
+    /// Parses and validates the JSON contents of a session file of any supported
+    /// version, migrating it to the current one.
+    pub fn parse_session(&self, json_data: &str) -> PersistenceResult<SimulationSession> {
+        let session = versioning::session_from_versioned_json(json_data.as_bytes())?;
+        self.validate_hypergraph_state(&session.initial_state)?;
+        self.validate_hypergraph_state(&session.current_state)?;
+        Ok(session)
+    }
+    
+    /// Lists every saved hypergraph state, newest first.
+    pub fn list_saved_hypergraphs(&self) -> PersistenceResult<Vec<SnapshotInfo>> {
+        self.store.list()
+    }
+    
+    /// Lists the saved hypergraph states matching `query`, newest first.
+    pub fn query_saved_hypergraphs(&self, query: &SnapshotQuery) -> PersistenceResult<Vec<SnapshotInfo>> {
+        self.store.query(query)
+    }
+    
+    /// Deletes the hypergraph state saved under `name`.
+    pub fn delete_hypergraph(&mut self, name: &str) -> PersistenceResult<()> {
+        self.store.delete(&snapshot_key(name))
+    }
+    
+    /// Describes where the state saved under `key` lives, such as its file path.
+    pub fn location(&self, key: &str) -> String {
+        self.store.location(key)
+    }
+    
+    /// Describes where the session saved under `key` lives, such as its file path.
+    pub fn session_location(&self, key: &str) -> String {
+        self.store.session_location(key)
+    }
+    
+    /// Gets the store for snapshots and sessions.
+    pub fn store(&self) -> &dyn SnapshotStore {
+        self.store.as_ref()
+    }
+    
+    /// Replaces the store for snapshots and sessions, returning the previous one.
+    pub fn set_store(&mut self, store: Box<dyn SnapshotStore>) -> Box<dyn SnapshotStore> {
+        std::mem::replace(&mut self.store, store)
     }
     
     /// Validates a hypergraph state for basic consistency.
@@ -251,15 +320,13 @@ impl Default for PersistenceManager {
 /// Convenience functions for quick save/load operations.
 impl PersistenceManager {
     /// Quick save function with default configuration.
-    pub fn quick_save(&self, state: &HypergraphState, filename: &str) -> PersistenceResult<PathBuf> {
-        let path = self.default_save_directory.join(filename);
-        self.save_hypergraph_state(state, Some(&path), None)
+    pub fn quick_save(&mut self, state: &HypergraphState, name: &str) -> PersistenceResult<SnapshotInfo> {
+        self.save_hypergraph_state(state, Some(name), None)
     }
     
     /// Quick load function.
-    pub fn quick_load(&self, filename: &str) -> PersistenceResult<HypergraphState> {
-        let path = self.default_save_directory.join(filename);
-        self.load_hypergraph_state(path)
+    pub fn quick_load(&self, name: &str) -> PersistenceResult<HypergraphState> {
+        self.load_hypergraph_state(name)
     }
 }
 
@@ -267,6 +334,7 @@ impl PersistenceManager {
 mod tests {
     use super::*;
     use crate::hypergraph::{Atom, Relation, AtomId, RelationId};
+    use crate::serialization::storage::MemoryStore;
     use tempfile::TempDir;
 
     fn create_test_state() -> HypergraphState {
@@ -286,20 +354,21 @@ mod tests {
     #[test]
     fn test_save_and_load_hypergraph_state() {
         let temp_dir = TempDir::new().unwrap();
-        let persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
+        let mut persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
         
         let original_state = create_test_state();
         
         // Save the state
-        let save_path = persistence_manager
+        let info = persistence_manager
             .save_hypergraph_state(&original_state, None, None)
             .unwrap();
         
-        assert!(save_path.exists());
+        assert_eq!(info.step_number, 5);
+        assert!(Path::new(&persistence_manager.location(&info.key)).exists());
         
         // Load the state
         let loaded_state = persistence_manager
-            .load_hypergraph_state(&save_path)
+            .load_hypergraph_state(&info.key)
             .unwrap();
         
         // Verify they are equal
@@ -309,56 +378,106 @@ mod tests {
     #[test]
     fn test_quick_save_and_load() {
         let temp_dir = TempDir::new().unwrap();
-        let persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
+        let mut persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
         
         let original_state = create_test_state();
         
         // Quick save
-        let save_path = persistence_manager
+        let info = persistence_manager
             .quick_save(&original_state, "test_hypergraph.json")
             .unwrap();
         
-        assert!(save_path.exists());
+        assert_eq!(info.key, "test_hypergraph");
+        assert!(temp_dir.path().join("test_hypergraph.json").exists());
         
-        // Quick load
+        // Quick load, by file name or by key
         let loaded_state = persistence_manager
             .quick_load("test_hypergraph.json")
             .unwrap();
         
         assert_eq!(original_state, loaded_state);
+        assert_eq!(persistence_manager.quick_load("test_hypergraph").unwrap(), original_state);
     }
     
     #[test]
-    fn test_list_saved_hypergraphs() {
+    fn test_load_unversioned_file() {
         let temp_dir = TempDir::new().unwrap();
         let persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
         
+        // Files written before the format header are bare states
+        let original_state = create_test_state();
+        std::fs::write(temp_dir.path().join("legacy.json"), serde_json::to_string(&original_state).unwrap()).unwrap();
+        
+        let loaded_state = persistence_manager.load_hypergraph_state("legacy.json").unwrap();
+        assert_eq!(loaded_state.atoms(), original_state.atoms());
+        assert_eq!(loaded_state.relations().len(), 2);
+    }
+    
+    #[test]
+    fn test_binary_formats_are_detected_on_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
+        let original_state = create_test_state();
+        
+        for format in [SnapshotFormat::Binary, SnapshotFormat::CompressedBinary] {
+            let config = SaveConfig { format, overwrite_existing: true, ..Default::default() };
+            let info = persistence_manager
+                .save_hypergraph_state(&original_state, Some("binary"), Some(config))
+                .unwrap();
+            assert!(persistence_manager.location(&info.key).ends_with(BINARY_EXTENSION));
+            assert_eq!(persistence_manager.load_hypergraph_state(&info.key).unwrap(), original_state);
+        }
+        
+        assert_eq!("compressed_binary".parse(), Ok(SnapshotFormat::CompressedBinary));
+        assert!("xml".parse::<SnapshotFormat>().is_err());
+    }
+    
+    #[test]
+    fn test_saves_use_the_configured_store() {
+        let mut persistence_manager = PersistenceManager::with_store(Box::new(MemoryStore::new()));
+        let state = create_test_state();
+        
+        let config = SaveConfig { tags: vec!["unit".to_string()], ..Default::default() };
+        persistence_manager.save_hypergraph_state(&state, Some("test"), Some(config)).unwrap();
+        assert_eq!(persistence_manager.load_hypergraph_state("test").unwrap(), state);
+        let query = SnapshotQuery { tags: vec!["unit".to_string()], ..Default::default() };
+        assert_eq!(persistence_manager.query_saved_hypergraphs(&query).unwrap().len(), 1);
+        
+        persistence_manager.delete_hypergraph("test").unwrap();
+        assert!(persistence_manager.list_saved_hypergraphs().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_list_saved_hypergraphs() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
+        
         // Initially empty
-        let files = persistence_manager.list_saved_hypergraphs().unwrap();
-        assert_eq!(files.len(), 0);
+        let saved = persistence_manager.list_saved_hypergraphs().unwrap();
+        assert_eq!(saved.len(), 0);
         
-        // Save some files
+        // Save some states
         let state = create_test_state();
         persistence_manager.quick_save(&state, "test1.json").unwrap();
         persistence_manager.quick_save(&state, "test2.json").unwrap();
         
-        // Should now list 2 files
-        let files = persistence_manager.list_saved_hypergraphs().unwrap();
-        assert_eq!(files.len(), 2);
+        // Should now list 2 states, described without loading them
+        let saved = persistence_manager.list_saved_hypergraphs().unwrap();
+        assert_eq!(saved.len(), 2);
+        assert!(saved.iter().all(|info| info.atom_count == 3 && info.relation_count == 2));
     }
     
     #[test]
     fn test_overwrite_protection() {
         let temp_dir = TempDir::new().unwrap();
-        let persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
+        let mut persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
         
         let state = create_test_state();
-        let path = temp_dir.path().join("test.json");
         
         // First save should succeed
         let result = persistence_manager.save_hypergraph_state(
             &state,
-            Some(&path),
+            Some("test"),
             Some(SaveConfig { overwrite_existing: false, ..Default::default() })
         );
         assert!(result.is_ok());
@@ -366,15 +485,15 @@ mod tests {
         // Second save should fail due to overwrite protection
         let result = persistence_manager.save_hypergraph_state(
             &state,
-            Some(&path),
+            Some("test"),
             Some(SaveConfig { overwrite_existing: false, ..Default::default() })
         );
-        assert!(result.is_err());
+        assert!(matches!(result, Err(PersistenceError::AlreadyExists(_))));
         
         // Third save should succeed with overwrite enabled
         let result = persistence_manager.save_hypergraph_state(
             &state,
-            Some(&path),
+            Some("test"),
             Some(SaveConfig { overwrite_existing: true, ..Default::default() })
         );
         assert!(result.is_ok());
@@ -383,7 +502,7 @@ mod tests {
     #[test]
     fn test_validation_catches_invalid_references() {
         let temp_dir = TempDir::new().unwrap();
-        let persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
+        let mut persistence_manager = PersistenceManager::with_save_directory(temp_dir.path());
         
         // Create invalid state where relation references non-existent atom
         let atoms = vec![
@@ -397,10 +516,10 @@ mod tests {
         let invalid_state = HypergraphState::new(atoms, relations, 0, 2, 1);
         
         // Save should work (we don't validate on save)
-        let path = persistence_manager.quick_save(&invalid_state, "invalid.json").unwrap();
+        persistence_manager.quick_save(&invalid_state, "invalid.json").unwrap();
         
         // Load should fail validation
-        let result = persistence_manager.load_hypergraph_state(&path);
+        let result = persistence_manager.load_hypergraph_state("invalid");
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), PersistenceError::InvalidData(_)));
     }
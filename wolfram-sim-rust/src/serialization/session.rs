@@ -0,0 +1,143 @@
+use serde::{Serialize, Deserialize};
+
+use crate::rules::rule::RuleSet;
+use crate::simulation::{
+    ContinuousSimulationConfig, EventSelectionStrategy, HypergraphState, SimulationEvent, SimulationManager,
+};
+
+/// Everything needed to resume or share a simulation run exactly: the rules and
+/// configuration, the state the run started from, the events applied since and
+/// the state they led to. Saved sessions carry a `FormatHeader`; see `versioning`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationSession {
+    /// Rules of the simulation
+    pub rule_set: RuleSet,
+
+    /// Configuration for continuous runs
+    pub config: ContinuousSimulationConfig,
+
+    /// Event selection strategy
+    pub strategy: EventSelectionStrategy,
+
+    /// Seed of the random number generator used for event selection
+    pub random_seed: u64,
+
+    /// State the recorded events start from
+    pub initial_state: HypergraphState,
+
+    /// State after the last recorded event
+    pub current_state: HypergraphState,
+
+    /// Events applied since the initial state, in step order
+    pub events: Vec<SimulationEvent>,
+}
+
+impl SimulationSession {
+    /// Captures the run recorded in the manager's timeline. If the timeline has
+    /// nothing recorded, the session starts from the current state.
+    pub fn capture(manager: &SimulationManager, config: ContinuousSimulationConfig) -> Result<Self, String> {
+        let current_state = manager.get_current_state();
+        let (initial_state, events) = match manager.timeline().first_step() {
+            Some(first_step) => (manager.state_at_step(first_step)?, manager.timeline().events().to_vec()),
+            None => (current_state.clone(), Vec::new()),
+        };
+
+        Ok(SimulationSession {
+            rule_set: manager.rule_set().clone(),
+            config,
+            strategy: manager.event_selection_strategy().clone(),
+            random_seed: manager.random_seed(),
+            initial_state,
+            current_state,
+            events,
+        })
+    }
+
+    /// Rebuilds the manager by replaying the recorded events from the initial state,
+    /// so undo, the causal graph and past states are available as in the original run.
+    pub fn restore(&self) -> Result<SimulationManager, String> {
+        let mut manager = SimulationManager::from_state(&self.initial_state, self.rule_set.clone())?;
+        manager.set_event_selection_strategy(self.strategy.clone());
+        for event in &self.events {
+            manager.replay_event(event)?;
+        }
+
+        let restored = manager.get_current_state();
+        if restored.step_number() != self.current_state.step_number()
+            || restored.atoms() != self.current_state.atoms()
+            || restored.relations() != self.current_state.relations()
+        {
+            return Err("The recorded events do not lead to the saved current state".to_string());
+        }
+        match self.current_state.rng() {
+            Some(rng) => manager.set_rng(rng.clone()),
+            None => manager.set_random_seed(self.random_seed),
+        }
+        Ok(manager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::versioning::{SESSION_FORMAT, SESSION_FORMAT_VERSION};
+    use crate::serialization::{PersistenceManager, PredefinedExamples};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn random_run() -> SimulationManager {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_event_selection_strategy(EventSelectionStrategy::uniform_random());
+        manager.set_random_seed(7);
+        manager.step_multiple(12);
+        manager
+    }
+
+    #[test]
+    fn test_restored_session_continues_identically() {
+        let mut original = random_run();
+        let session = SimulationSession::capture(&original, ContinuousSimulationConfig::default()).unwrap();
+        assert_eq!(session.events.len(), 12);
+        assert_eq!(session.initial_state.step_number(), 0);
+
+        let mut restored = session.restore().unwrap();
+        assert_eq!(restored.get_current_state(), original.get_current_state());
+        assert_eq!(restored.history().undo_count(), 12);
+
+        original.step_multiple(5);
+        restored.step_multiple(5);
+        assert_eq!(restored.get_current_state(), original.get_current_state());
+    }
+
+    #[test]
+    fn test_save_and_load_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut persistence = PersistenceManager::with_save_directory(temp_dir.path());
+        let session = SimulationSession::capture(&random_run(), ContinuousSimulationConfig::default()).unwrap();
+
+        let key = persistence.save_session(&session, None, None).unwrap();
+        assert_eq!(persistence.load_session(&key).unwrap(), session);
+        assert!(Path::new(&persistence.session_location(&key)).exists());
+
+        let newer = serde_json::json!({"format": SESSION_FORMAT, "version": SESSION_FORMAT_VERSION + 1, "data": session});
+        assert!(persistence.parse_session(&newer.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_version_1_sessions_are_migrated() {
+        let persistence = PersistenceManager::new();
+        let session = SimulationSession::capture(&random_run(), ContinuousSimulationConfig::default()).unwrap();
+
+        // Version 1 sessions were bare, with the version in a field of their own
+        let mut legacy = serde_json::to_value(&session).unwrap();
+        legacy["format_version"] = serde_json::json!(1);
+        assert_eq!(persistence.parse_session(&legacy.to_string()).unwrap(), session);
+    }
+
+    #[test]
+    fn test_inconsistent_session_is_rejected() {
+        let mut session = SimulationSession::capture(&random_run(), ContinuousSimulationConfig::default()).unwrap();
+        session.events.pop();
+        assert!(session.restore().is_err());
+    }
+}
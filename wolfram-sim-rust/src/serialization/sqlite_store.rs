@@ -0,0 +1,240 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, types::Value, Connection, OptionalExtension};
+
+use crate::simulation::HypergraphState;
+use super::persistence::{decode_snapshot, encode_snapshot, PersistenceError, PersistenceResult, SaveConfig};
+use super::storage::{SnapshotInfo, SnapshotQuery, SnapshotStore};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        key TEXT PRIMARY KEY,
+        step_number INTEGER NOT NULL,
+        atom_count INTEGER NOT NULL,
+        relation_count INTEGER NOT NULL,
+        saved_at INTEGER NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS snapshot_tags (
+        key TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (key, tag)
+    );
+    CREATE TABLE IF NOT EXISTS sessions (
+        key TEXT PRIMARY KEY,
+        saved_at INTEGER NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_by_step ON snapshots (step_number);
+    CREATE INDEX IF NOT EXISTS snapshots_by_atom_count ON snapshots (atom_count);
+    CREATE INDEX IF NOT EXISTS snapshots_by_saved_at ON snapshots (saved_at);
+    CREATE INDEX IF NOT EXISTS snapshot_tags_by_tag ON snapshot_tags (tag);
+";
+
+/// Stores snapshots as blobs in an SQLite database, with the step number, atom count
+/// and tags in indexed columns so listing and querying never decode a snapshot.
+/// Sessions are kept in a table of their own.
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> PersistenceResult<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a private database that lives in memory.
+    pub fn open_in_memory() -> PersistenceResult<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> PersistenceResult<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection })
+    }
+
+    /// Reads the tags of the snapshot stored under `key`.
+    fn tags(&self, key: &str) -> PersistenceResult<Vec<String>> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT tag FROM snapshot_tags WHERE key = ?1 ORDER BY tag")?;
+        let tags = statement.query_map([key], |row| row.get(0))?.collect::<Result<_, _>>()?;
+        Ok(tags)
+    }
+}
+
+impl SnapshotStore for SqliteStore {
+    fn put(&mut self, key: &str, state: &HypergraphState, config: &SaveConfig) -> PersistenceResult<SnapshotInfo> {
+        let info = SnapshotInfo::describe(key, state, &config.tags);
+        let data = encode_snapshot(state, config.format, config.pretty_print)?;
+
+        let transaction = self.connection.transaction()?;
+        let exists = transaction
+            .query_row("SELECT 1 FROM snapshots WHERE key = ?1", [key], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if exists && !config.overwrite_existing {
+            return Err(PersistenceError::AlreadyExists(key.to_string()));
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO snapshots (key, step_number, atom_count, relation_count, saved_at, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key,
+                info.step_number as i64,
+                info.atom_count as i64,
+                info.relation_count as i64,
+                info.saved_at.timestamp_micros(),
+                data,
+            ],
+        )?;
+        transaction.execute("DELETE FROM snapshot_tags WHERE key = ?1", [key])?;
+        for tag in &info.tags {
+            transaction.execute("INSERT INTO snapshot_tags (key, tag) VALUES (?1, ?2)", [key, tag])?;
+        }
+        transaction.commit()?;
+        Ok(info)
+    }
+
+    fn get(&self, key: &str) -> PersistenceResult<HypergraphState> {
+        let data: Option<Vec<u8>> = self
+            .connection
+            .query_row("SELECT data FROM snapshots WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?;
+        match data {
+            Some(data) => decode_snapshot(&data),
+            None => Err(PersistenceError::SnapshotNotFound(key.to_string())),
+        }
+    }
+
+    fn delete(&mut self, key: &str) -> PersistenceResult<()> {
+        let transaction = self.connection.transaction()?;
+        let deleted = transaction.execute("DELETE FROM snapshots WHERE key = ?1", [key])?;
+        transaction.execute("DELETE FROM snapshot_tags WHERE key = ?1", [key])?;
+        transaction.commit()?;
+        if deleted == 0 {
+            return Err(PersistenceError::SnapshotNotFound(key.to_string()));
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> PersistenceResult<Vec<SnapshotInfo>> {
+        self.query(&SnapshotQuery::default())
+    }
+
+    fn query(&self, query: &SnapshotQuery) -> PersistenceResult<Vec<SnapshotInfo>> {
+        let mut sql = String::from(
+            "SELECT key, step_number, atom_count, relation_count, saved_at FROM snapshots s WHERE 1 = 1",
+        );
+        let mut values: Vec<Value> = Vec::new();
+        let mut bound = |sql: &mut String, condition: &str, value: Value| {
+            values.push(value);
+            sql.push_str(&format!(" AND {} ?{}", condition, values.len()));
+        };
+        if let Some(min) = query.min_step {
+            bound(&mut sql, "step_number >=", Value::Integer(min as i64));
+        }
+        if let Some(max) = query.max_step {
+            bound(&mut sql, "step_number <=", Value::Integer(max as i64));
+        }
+        if let Some(min) = query.min_atoms {
+            bound(&mut sql, "atom_count >=", Value::Integer(min as i64));
+        }
+        if let Some(max) = query.max_atoms {
+            bound(&mut sql, "atom_count <=", Value::Integer(max as i64));
+        }
+        for tag in &query.tags {
+            bound(
+                &mut sql,
+                "EXISTS (SELECT 1 FROM snapshot_tags t WHERE t.key = s.key AND t.tag =",
+                Value::Text(tag.clone()),
+            );
+            sql.push(')');
+        }
+        sql.push_str(" ORDER BY saved_at DESC, key");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let mut statement = self.connection.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+
+        let mut snapshots = Vec::new();
+        for row in rows {
+            let (key, step_number, atom_count, relation_count, saved_at) = row?;
+            snapshots.push(SnapshotInfo {
+                tags: self.tags(&key)?,
+                key,
+                step_number: step_number as u64,
+                atom_count: atom_count as usize,
+                relation_count: relation_count as usize,
+                saved_at: DateTime::<Utc>::from_timestamp_micros(saved_at).unwrap_or_default(),
+            });
+        }
+        Ok(snapshots)
+    }
+
+    fn put_session(&mut self, key: &str, contents: &[u8], config: &SaveConfig) -> PersistenceResult<()> {
+        let verb = if config.overwrite_existing { "INSERT OR REPLACE" } else { "INSERT" };
+        let result = self.connection.execute(
+            &format!("{} INTO sessions (key, saved_at, data) VALUES (?1, ?2, ?3)", verb),
+            params![key, Utc::now().timestamp_micros(), contents],
+        );
+        match result {
+            Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == rusqlite::ErrorCode::ConstraintViolation => {
+                Err(PersistenceError::AlreadyExists(key.to_string()))
+            }
+            result => result.map(|_| ()).map_err(Into::into),
+        }
+    }
+
+    fn get_session(&self, key: &str) -> PersistenceResult<Vec<u8>> {
+        self.connection
+            .query_row("SELECT data FROM sessions WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| PersistenceError::SnapshotNotFound(key.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::storage::tests::exercise_store;
+    use crate::serialization::PredefinedExamples;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_store() {
+        exercise_store(&mut SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_snapshots_survive_reopening() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("snapshots.db");
+        let state = PredefinedExamples::triangle();
+
+        let mut store = SqliteStore::open(&path).unwrap();
+        let config = SaveConfig { tags: vec!["example".to_string()], ..Default::default() };
+        store.put("triangle", &state, &config).unwrap();
+        drop(store);
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get("triangle").unwrap(), state);
+        assert_eq!(store.list().unwrap()[0].tags, ["example"]);
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::simulation::HypergraphState;
+use super::persistence::{
+    decode_snapshot, encode_snapshot, PersistenceError, PersistenceResult, SaveConfig, BINARY_EXTENSION,
+};
+use super::sqlite_store::SqliteStore;
+
+/// Description of a stored snapshot, available without loading it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// Key the snapshot is stored under
+    pub key: String,
+
+    /// Step number of the saved state
+    pub step_number: u64,
+
+    /// Number of atoms in the saved state
+    pub atom_count: usize,
+
+    /// Number of relations in the saved state
+    pub relation_count: usize,
+
+    /// Tags given when saving, sorted and without duplicates
+    pub tags: Vec<String>,
+
+    /// When the snapshot was saved
+    pub saved_at: DateTime<Utc>,
+}
+
+impl SnapshotInfo {
+    /// Describes `state` saved now under `key` with `tags`.
+    pub fn describe(key: &str, state: &HypergraphState, tags: &[String]) -> Self {
+        let mut tags = tags.to_vec();
+        tags.sort();
+        tags.dedup();
+        SnapshotInfo {
+            key: key.to_string(),
+            step_number: state.step_number(),
+            atom_count: state.atoms().len(),
+            relation_count: state.relations().len(),
+            tags,
+            saved_at: Utc::now(),
+        }
+    }
+}
+
+/// Filter for listing snapshots. Every field that is set must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotQuery {
+    /// Smallest step number to include
+    pub min_step: Option<u64>,
+
+    /// Largest step number to include
+    pub max_step: Option<u64>,
+
+    /// Smallest atom count to include
+    pub min_atoms: Option<usize>,
+
+    /// Largest atom count to include
+    pub max_atoms: Option<usize>,
+
+    /// Tags that a snapshot must all have
+    pub tags: Vec<String>,
+
+    /// Maximum number of results
+    pub limit: Option<usize>,
+}
+
+impl SnapshotQuery {
+    /// Returns true if `info` passes every filter of the query, ignoring `limit`.
+    pub fn matches(&self, info: &SnapshotInfo) -> bool {
+        self.min_step.is_none_or(|min| info.step_number >= min)
+            && self.max_step.is_none_or(|max| info.step_number <= max)
+            && self.min_atoms.is_none_or(|min| info.atom_count >= min)
+            && self.max_atoms.is_none_or(|max| info.atom_count <= max)
+            && self.tags.iter().all(|tag| info.tags.contains(tag))
+    }
+}
+
+/// Storage for named hypergraph snapshots and simulation sessions.
+pub trait SnapshotStore: fmt::Debug + Send {
+    /// Stores `state` under `key` with the encoding, tags and overwrite policy of `config`.
+    fn put(&mut self, key: &str, state: &HypergraphState, config: &SaveConfig) -> PersistenceResult<SnapshotInfo>;
+
+    /// Loads the snapshot stored under `key`.
+    fn get(&self, key: &str) -> PersistenceResult<HypergraphState>;
+
+    /// Deletes the snapshot stored under `key`.
+    fn delete(&mut self, key: &str) -> PersistenceResult<()>;
+
+    /// Lists every stored snapshot, newest first.
+    fn list(&self) -> PersistenceResult<Vec<SnapshotInfo>>;
+
+    /// Lists the snapshots matching `query`, newest first.
+    fn query(&self, query: &SnapshotQuery) -> PersistenceResult<Vec<SnapshotInfo>> {
+        let matching = self.list()?.into_iter().filter(|info| query.matches(info));
+        Ok(matching.take(query.limit.unwrap_or(usize::MAX)).collect())
+    }
+
+    /// Stores an encoded session under `key`, following the overwrite policy of `config`.
+    fn put_session(&mut self, key: &str, contents: &[u8], config: &SaveConfig) -> PersistenceResult<()>;
+
+    /// Loads the encoded session stored under `key`.
+    fn get_session(&self, key: &str) -> PersistenceResult<Vec<u8>>;
+
+    /// Describes where the snapshot stored under `key` lives, for messages to users.
+    fn location(&self, key: &str) -> String {
+        key.to_string()
+    }
+
+    /// Describes where the session stored under `key` lives, for messages to users.
+    fn session_location(&self, key: &str) -> String {
+        key.to_string()
+    }
+}
+
+/// Returns the store key for a snapshot or session name. Names may be keys, file
+/// names or paths as returned by earlier versions; only the file name is kept, without
+/// a `.json` or binary snapshot extension.
+pub fn snapshot_key(name: &str) -> String {
+    let file_name = Path::new(name).file_name().and_then(|s| s.to_str()).unwrap_or(name);
+    [".json", ".hgb"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .unwrap_or(file_name)
+        .to_string()
+}
+
+/// Sorts snapshots newest first, then by key.
+fn sort_newest_first(snapshots: &mut [SnapshotInfo]) {
+    snapshots.sort_by(|a, b| b.saved_at.cmp(&a.saved_at).then_with(|| a.key.cmp(&b.key)));
+}
+
+/// Stores each snapshot as a file named after its key in a directory, with its
+/// `SnapshotInfo` in a `.meta` file next to it so listing never decodes a snapshot.
+/// Snapshot files without one, such as saves from earlier versions, are decoded to
+/// describe them. Sessions are kept in a `sessions` subdirectory.
+#[derive(Debug, Clone)]
+pub struct FilesystemStore {
+    directory: PathBuf,
+}
+
+impl FilesystemStore {
+    /// Creates a store that keeps snapshots in `directory`.
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        FilesystemStore {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Gets the directory snapshots are stored in.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the path of the existing snapshot file for `key`, in any format.
+    fn find(&self, key: &str) -> PersistenceResult<PathBuf> {
+        check_key(key)?;
+        ["json", BINARY_EXTENSION]
+            .iter()
+            .map(|extension| self.directory.join(format!("{}.{}", key, extension)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| PersistenceError::SnapshotNotFound(key.to_string()))
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{}.meta", key))
+    }
+
+    fn session_path(&self, key: &str) -> PersistenceResult<PathBuf> {
+        check_key(key)?;
+        Ok(self.directory.join("sessions").join(format!("{}.json", key)))
+    }
+
+    /// Describes the snapshot file at `path` from its `.meta` file, or by decoding it.
+    /// Returns `None` for files that are not snapshots.
+    fn describe_file(&self, key: &str, path: &Path) -> PersistenceResult<Option<SnapshotInfo>> {
+        if let Ok(meta) = fs::read(self.meta_path(key)) {
+            if let Ok(info) = serde_json::from_slice(&meta) {
+                return Ok(Some(info));
+            }
+        }
+        let Ok(state) = decode_snapshot(&fs::read(path)?) else { return Ok(None) };
+        let mut info = SnapshotInfo::describe(key, &state, &[]);
+        if let Ok(modified) = path.metadata().and_then(|m| m.modified()) {
+            info.saved_at = modified.into();
+        }
+        Ok(Some(info))
+    }
+}
+
+/// Rejects keys that are not plain file names.
+pub(crate) fn check_key(key: &str) -> PersistenceResult<()> {
+    if key.is_empty() || key.starts_with('.') || key.contains(['/', '\\']) {
+        return Err(PersistenceError::InvalidPath(format!("Invalid snapshot key: {:?}", key)));
+    }
+    Ok(())
+}
+
+impl SnapshotStore for FilesystemStore {
+    fn put(&mut self, key: &str, state: &HypergraphState, config: &SaveConfig) -> PersistenceResult<SnapshotInfo> {
+        check_key(key)?;
+        fs::create_dir_all(&self.directory)?;
+        if let Ok(previous) = self.find(key) {
+            if !config.overwrite_existing {
+                return Err(PersistenceError::AlreadyExists(key.to_string()));
+            }
+            fs::remove_file(previous)?;
+        }
+
+        let info = SnapshotInfo::describe(key, state, &config.tags);
+        let path = self.directory.join(format!("{}.{}", key, config.format.extension()));
+        fs::write(path, encode_snapshot(state, config.format, config.pretty_print)?)?;
+        fs::write(self.meta_path(key), serde_json::to_vec(&info)?)?;
+        Ok(info)
+    }
+
+    fn get(&self, key: &str) -> PersistenceResult<HypergraphState> {
+        decode_snapshot(&fs::read(self.find(key)?)?)
+    }
+
+    fn delete(&mut self, key: &str) -> PersistenceResult<()> {
+        fs::remove_file(self.find(key)?)?;
+        let _ = fs::remove_file(self.meta_path(key));
+        Ok(())
+    }
+
+    fn list(&self) -> PersistenceResult<Vec<SnapshotInfo>> {
+        if !self.directory.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|s| s.to_str());
+            if !path.is_file() || !(extension == Some("json") || extension == Some(BINARY_EXTENSION)) {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            if let Some(info) = self.describe_file(key, &path)? {
+                snapshots.push(info);
+            }
+        }
+        sort_newest_first(&mut snapshots);
+        Ok(snapshots)
+    }
+
+    fn put_session(&mut self, key: &str, contents: &[u8], config: &SaveConfig) -> PersistenceResult<()> {
+        let path = self.session_path(key)?;
+        if path.exists() && !config.overwrite_existing {
+            return Err(PersistenceError::AlreadyExists(key.to_string()));
+        }
+        fs::create_dir_all(path.parent().expect("session paths have a parent"))?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    fn get_session(&self, key: &str) -> PersistenceResult<Vec<u8>> {
+        let path = self.session_path(key)?;
+        if !path.is_file() {
+            return Err(PersistenceError::SnapshotNotFound(key.to_string()));
+        }
+        Ok(fs::read(path)?)
+    }
+
+    fn location(&self, key: &str) -> String {
+        match self.find(key) {
+            Ok(path) => path.display().to_string(),
+            Err(_) => key.to_string(),
+        }
+    }
+
+    fn session_location(&self, key: &str) -> String {
+        match self.session_path(key) {
+            Ok(path) => path.display().to_string(),
+            Err(_) => key.to_string(),
+        }
+    }
+}
+
+/// Keeps snapshots in memory, for tests and short-lived simulations.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    snapshots: BTreeMap<String, (SnapshotInfo, HypergraphState)>,
+    sessions: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of stored snapshots.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Returns true if no snapshots are stored.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+impl SnapshotStore for MemoryStore {
+    fn put(&mut self, key: &str, state: &HypergraphState, config: &SaveConfig) -> PersistenceResult<SnapshotInfo> {
+        if self.snapshots.contains_key(key) && !config.overwrite_existing {
+            return Err(PersistenceError::AlreadyExists(key.to_string()));
+        }
+        let info = SnapshotInfo::describe(key, state, &config.tags);
+        self.snapshots.insert(key.to_string(), (info.clone(), state.clone()));
+        Ok(info)
+    }
+
+    fn get(&self, key: &str) -> PersistenceResult<HypergraphState> {
+        self.snapshots
+            .get(key)
+            .map(|(_, state)| state.clone())
+            .ok_or_else(|| PersistenceError::SnapshotNotFound(key.to_string()))
+    }
+
+    fn delete(&mut self, key: &str) -> PersistenceResult<()> {
+        self.snapshots
+            .remove(key)
+            .map(|_| ())
+            .ok_or_else(|| PersistenceError::SnapshotNotFound(key.to_string()))
+    }
+
+    fn list(&self) -> PersistenceResult<Vec<SnapshotInfo>> {
+        let mut snapshots: Vec<SnapshotInfo> = self.snapshots.values().map(|(info, _)| info.clone()).collect();
+        sort_newest_first(&mut snapshots);
+        Ok(snapshots)
+    }
+
+    fn put_session(&mut self, key: &str, contents: &[u8], config: &SaveConfig) -> PersistenceResult<()> {
+        if self.sessions.contains_key(key) && !config.overwrite_existing {
+            return Err(PersistenceError::AlreadyExists(key.to_string()));
+        }
+        self.sessions.insert(key.to_string(), contents.to_vec());
+        Ok(())
+    }
+
+    fn get_session(&self, key: &str) -> PersistenceResult<Vec<u8>> {
+        self.sessions
+            .get(key)
+            .cloned()
+            .ok_or_else(|| PersistenceError::SnapshotNotFound(key.to_string()))
+    }
+}
+
+/// Which store a server keeps snapshots and sessions in, written as
+/// `filesystem:<directory>`, `sqlite:<database file>` or `memory`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreBackend {
+    /// A `FilesystemStore` in the given directory
+    Filesystem(PathBuf),
+
+    /// A `SqliteStore` in the given database file
+    Sqlite(PathBuf),
+
+    /// A `MemoryStore`, lost when the server stops
+    Memory,
+}
+
+impl StoreBackend {
+    /// Opens the store, creating its directory or database if needed.
+    pub fn open(&self) -> PersistenceResult<Box<dyn SnapshotStore>> {
+        Ok(match self {
+            StoreBackend::Filesystem(directory) => Box::new(FilesystemStore::new(directory)),
+            StoreBackend::Sqlite(path) => Box::new(SqliteStore::open(path)?),
+            StoreBackend::Memory => Box::new(MemoryStore::new()),
+        })
+    }
+}
+
+impl Default for StoreBackend {
+    fn default() -> Self {
+        StoreBackend::Filesystem(PathBuf::from("saved_hypergraphs"))
+    }
+}
+
+impl fmt::Display for StoreBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreBackend::Filesystem(directory) => write!(f, "filesystem:{}", directory.display()),
+            StoreBackend::Sqlite(path) => write!(f, "sqlite:{}", path.display()),
+            StoreBackend::Memory => write!(f, "memory"),
+        }
+    }
+}
+
+impl FromStr for StoreBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("filesystem", directory)) if !directory.is_empty() => Ok(StoreBackend::Filesystem(directory.into())),
+            Some(("sqlite", path)) if !path.is_empty() => Ok(StoreBackend::Sqlite(path.into())),
+            None if s == "memory" => Ok(StoreBackend::Memory),
+            _ => Err(format!(
+                "Unknown snapshot store: {} (expected filesystem:<directory>, sqlite:<file> or memory)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::rules::rule::RuleSet;
+    use crate::serialization::persistence::SnapshotFormat;
+    use crate::serialization::PredefinedExamples;
+    use crate::simulation::SimulationManager;
+    use tempfile::TempDir;
+
+    /// Checks the behaviour every store must share.
+    pub(crate) fn exercise_store(store: &mut dyn SnapshotStore) {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        let tags = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        let tagged = |names: &[&str]| SaveConfig { tags: tags(names), ..Default::default() };
+
+        let early = manager.get_current_state();
+        store.put("early", &early, &tagged(&["triangle"])).unwrap();
+        manager.step_multiple(10);
+        let late = manager.get_current_state();
+        let config = SaveConfig { format: SnapshotFormat::CompressedBinary, ..tagged(&["triangle", "long", "long"]) };
+        let info = store.put("late", &late, &config).unwrap();
+        assert_eq!(info.tags, tags(&["long", "triangle"]));
+        assert_eq!(info.atom_count, late.atoms().len());
+
+        assert_eq!(store.get("late").unwrap(), late);
+        assert_eq!(store.list().unwrap().len(), 2);
+
+        let query = SnapshotQuery { min_step: Some(1), ..Default::default() };
+        assert_eq!(store.query(&query).unwrap().iter().map(|i| i.key.as_str()).collect::<Vec<_>>(), ["late"]);
+        let query = SnapshotQuery { tags: tags(&["triangle"]), max_atoms: Some(early.atoms().len()), ..Default::default() };
+        assert_eq!(store.query(&query).unwrap()[0].key, "early");
+        let query = SnapshotQuery { tags: tags(&["triangle"]), limit: Some(1), ..Default::default() };
+        assert_eq!(store.query(&query).unwrap().len(), 1);
+
+        // Replacing a snapshot needs permission and keeps one entry under the key
+        assert!(matches!(store.put("early", &late, &SaveConfig::default()), Err(PersistenceError::AlreadyExists(_))));
+        store.put("early", &late, &SaveConfig { overwrite_existing: true, ..Default::default() }).unwrap();
+        assert_eq!(store.list().unwrap().len(), 2);
+        assert!(store.query(&SnapshotQuery { tags: tags(&["triangle"]), ..Default::default() }).unwrap().len() == 1);
+
+        store.delete("early").unwrap();
+        assert!(matches!(store.get("early"), Err(PersistenceError::SnapshotNotFound(_))));
+        assert!(store.delete("early").is_err());
+
+        // Sessions live apart from snapshots
+        store.put_session("late", b"{\"session\": true}", &SaveConfig::default()).unwrap();
+        assert_eq!(store.get_session("late").unwrap(), b"{\"session\": true}");
+        assert!(store.put_session("late", b"{}", &SaveConfig::default()).is_err());
+        assert!(store.get_session("early").is_err());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise_store(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn test_filesystem_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = FilesystemStore::new(temp_dir.path());
+        exercise_store(&mut store);
+        assert!(store.location("late").ends_with("late.hgb"));
+        assert!(store.put("../escape", &PredefinedExamples::triangle(), &SaveConfig::default()).is_err());
+
+        // Files saved without metadata are still listed
+        fs::write(temp_dir.path().join("legacy.json"), serde_json::to_vec(&PredefinedExamples::triangle()).unwrap()).unwrap();
+        fs::write(temp_dir.path().join("notes.json"), b"{}").unwrap();
+        let keys: Vec<String> = store.list().unwrap().into_iter().map(|info| info.key).collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&"legacy".to_string()));
+    }
+
+    #[test]
+    fn test_keys_and_backends() {
+        assert_eq!(snapshot_key("saved/hypergraph_step_3.json"), "hypergraph_step_3");
+        assert_eq!(snapshot_key("checkpoint.hgb"), "checkpoint");
+        assert_eq!(snapshot_key("plain"), "plain");
+
+        assert_eq!("sqlite:runs/snapshots.db".parse(), Ok(StoreBackend::Sqlite("runs/snapshots.db".into())));
+        assert_eq!("memory".parse(), Ok(StoreBackend::Memory));
+        assert!("s3:bucket".parse::<StoreBackend>().is_err());
+        let backend = StoreBackend::default();
+        assert_eq!(backend.to_string().parse(), Ok(backend));
+    }
+}
@@ -0,0 +1,248 @@
+use serde::{de::DeserializeOwned, Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::simulation::HypergraphState;
+use super::persistence::{PersistenceError, PersistenceResult};
+use super::event_log::EventLogHeader;
+use super::session::SimulationSession;
+
+/// Name recorded in the header of saved hypergraph states.
+pub const HYPERGRAPH_FORMAT: &str = "hypergraph_state";
+
+/// Version of the hypergraph state format written by this build.
+///
+/// - 1: a bare `HypergraphState` without a header. Relations may lack creation timestamps.
+/// - 2: the state wrapped in a `FormatHeader` envelope.
+pub const HYPERGRAPH_FORMAT_VERSION: u32 = 2;
+
+/// Name recorded in the header of saved simulation sessions.
+pub const SESSION_FORMAT: &str = "simulation_session";
+
+/// Version of the session format written by this build.
+///
+/// - 1: a bare `SimulationSession` with its own `format_version` field.
+/// - 2: the session wrapped in a `FormatHeader` envelope.
+pub const SESSION_FORMAT_VERSION: u32 = 2;
+
+/// Name recorded in the header line of event logs.
+pub const EVENT_LOG_FORMAT: &str = "event_log";
+
+/// Version of the event log format written by this build.
+///
+/// - 1: a header line with the run's initial state, rules and strategy, then one event per line.
+pub const EVENT_LOG_FORMAT_VERSION: u32 = 1;
+
+/// Identifies the contents and version of a saved file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatHeader {
+    /// Kind of data in the file
+    pub format: String,
+
+    /// Version of the format the data was written in
+    pub version: u32,
+}
+
+/// A migration rewrites data of one version into the next version.
+type Migration = fn(Value) -> PersistenceResult<Value>;
+
+/// Migrations of hypergraph state data, where entry `i` upgrades version `i + 1`.
+const HYPERGRAPH_MIGRATIONS: &[Migration] = &[migrate_v1_to_v2];
+
+/// Migrations of session data, where entry `i` upgrades version `i + 1`.
+const SESSION_MIGRATIONS: &[Migration] = &[migrate_session_v1_to_v2];
+
+/// Migrations of event log headers, where entry `i` upgrades version `i + 1`.
+const EVENT_LOG_MIGRATIONS: &[Migration] = &[];
+
+/// JSON layout of a saved file: the header followed by the data.
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    #[serde(flatten)]
+    header: FormatHeader,
+    data: &'a T,
+}
+
+/// Serializes a state with the current format header.
+pub fn to_versioned_json(state: &HypergraphState, pretty_print: bool) -> PersistenceResult<String> {
+    write_versioned(HYPERGRAPH_FORMAT, HYPERGRAPH_FORMAT_VERSION, state, pretty_print)
+}
+
+/// Parses a saved state of any supported version, migrating it to the current one.
+/// JSON without a header is read as version 1.
+pub fn from_versioned_json(json_data: &[u8]) -> PersistenceResult<HypergraphState> {
+    read_versioned(json_data, HYPERGRAPH_FORMAT, HYPERGRAPH_FORMAT_VERSION, HYPERGRAPH_MIGRATIONS, "hypergraph state")
+}
+
+/// Serializes a session with the current format header.
+pub fn session_to_versioned_json(session: &SimulationSession, pretty_print: bool) -> PersistenceResult<String> {
+    write_versioned(SESSION_FORMAT, SESSION_FORMAT_VERSION, session, pretty_print)
+}
+
+/// Parses a saved session of any supported version, migrating it to the current one.
+/// JSON without a header is read as version 1.
+pub fn session_from_versioned_json(json_data: &[u8]) -> PersistenceResult<SimulationSession> {
+    read_versioned(json_data, SESSION_FORMAT, SESSION_FORMAT_VERSION, SESSION_MIGRATIONS, "session")
+}
+
+/// Serializes an event log header on a single line with the current format header.
+pub fn event_log_header_to_versioned_json(header: &EventLogHeader) -> PersistenceResult<String> {
+    write_versioned(EVENT_LOG_FORMAT, EVENT_LOG_FORMAT_VERSION, header, false)
+}
+
+/// Parses the header line of an event log of any supported version.
+pub fn event_log_header_from_versioned_json(json_data: &[u8]) -> PersistenceResult<EventLogHeader> {
+    read_versioned(json_data, EVENT_LOG_FORMAT, EVENT_LOG_FORMAT_VERSION, EVENT_LOG_MIGRATIONS, "event log header")
+}
+
+/// Serializes `data` in an envelope with the given header.
+fn write_versioned<T: Serialize>(format: &str, version: u32, data: &T, pretty_print: bool) -> PersistenceResult<String> {
+    let envelope = Envelope { header: FormatHeader { format: format.to_string(), version }, data };
+    Ok(if pretty_print { serde_json::to_string_pretty(&envelope)? } else { serde_json::to_string(&envelope)? })
+}
+
+/// Parses data of `format` written in any version up to `current`, running the
+/// migrations it needs. `what` names the data in error messages.
+fn read_versioned<T: DeserializeOwned>(
+    json_data: &[u8],
+    format: &str,
+    current: u32,
+    migrations: &[Migration],
+    what: &str,
+) -> PersistenceResult<T> {
+    let value: Value = serde_json::from_slice(json_data)
+        .map_err(|e| PersistenceError::InvalidData(format!("Failed to parse JSON: {}", e)))?;
+    let (version, data) = split_header(value, format)?;
+    let data = migrate(data, version, current, migrations)?;
+    serde_json::from_value(data).map_err(|e| PersistenceError::InvalidData(format!("Invalid {}: {}", what, e)))
+}
+
+/// Separates the header from the data, checking the format name.
+/// Data without a header is version 1.
+fn split_header(mut value: Value, expected_format: &str) -> PersistenceResult<(u32, Value)> {
+    let Some(object) = value.as_object_mut() else {
+        return Err(PersistenceError::InvalidData("Expected a JSON object".to_string()));
+    };
+    if !object.contains_key("format") {
+        return Ok((1, value));
+    }
+
+    let header = FormatHeader {
+        format: object.remove("format").and_then(|f| f.as_str().map(str::to_string)).unwrap_or_default(),
+        version: object.remove("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+    };
+    if header.format != expected_format {
+        return Err(PersistenceError::WrongFormat { expected: expected_format.to_string(), found: header.format });
+    }
+    let data = object
+        .remove("data")
+        .ok_or_else(|| PersistenceError::InvalidData("Missing data after the format header".to_string()))?;
+    Ok((header.version, data))
+}
+
+/// Runs the migrations needed to bring `data` from `version` to `current`.
+fn migrate(mut data: Value, version: u32, current: u32, migrations: &[Migration]) -> PersistenceResult<Value> {
+    if version == 0 || version > current {
+        return Err(PersistenceError::UnsupportedVersion { found: version, supported: current });
+    }
+    for (index, migration) in migrations.iter().enumerate().skip(version as usize - 1) {
+        data = migration(data).map_err(|e| PersistenceError::Migration { from: index as u32 + 1, message: e.to_string() })?;
+    }
+    Ok(data)
+}
+
+/// Version 1 files may predate relation creation timestamps. Relations without one are
+/// stamped in ID order, which is the order they were created in, after any stamped ones.
+fn migrate_v1_to_v2(mut data: Value) -> PersistenceResult<Value> {
+    let relations = data
+        .get_mut("relations")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| PersistenceError::InvalidData("Missing relations".to_string()))?;
+
+    let mut next_timestamp = relations
+        .iter()
+        .filter_map(|relation| relation.get("created_at").and_then(Value::as_u64))
+        .max()
+        .map_or(0, |latest| latest + 1);
+    let mut unstamped: Vec<(u64, usize)> = relations
+        .iter()
+        .enumerate()
+        .filter(|(_, relation)| relation.get("created_at").is_none())
+        .map(|(index, relation)| (relation.get("id").and_then(Value::as_u64).unwrap_or(0), index))
+        .collect();
+    unstamped.sort();
+    for (_, index) in unstamped {
+        relations[index]["created_at"] = json!(next_timestamp);
+        next_timestamp += 1;
+    }
+
+    if data.get("next_timestamp").is_none() {
+        data["next_timestamp"] = json!(next_timestamp);
+    }
+    Ok(data)
+}
+
+/// Version 1 sessions carried their version in a `format_version` field, which the
+/// header replaces.
+fn migrate_session_v1_to_v2(mut data: Value) -> PersistenceResult<Value> {
+    let object = data
+        .as_object_mut()
+        .ok_or_else(|| PersistenceError::InvalidData("Expected a JSON object".to_string()))?;
+    match object.remove("format_version").and_then(|v| v.as_u64()) {
+        Some(1) | None => Ok(data),
+        Some(version) => Err(PersistenceError::UnsupportedVersion { found: version as u32, supported: SESSION_FORMAT_VERSION }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypergraph::{Atom, AtomId, Relation, RelationId};
+
+    #[test]
+    fn test_round_trip_writes_header() {
+        let mut relation = Relation::new(RelationId::new(0), vec![AtomId::new(0), AtomId::new(1)]);
+        relation.created_at = 3;
+        let state = HypergraphState::new(vec![Atom::new(AtomId::new(0)), Atom::new(AtomId::new(1))], vec![relation], 2, 2, 1)
+            .with_next_timestamp(4);
+
+        let json_data = to_versioned_json(&state, false).unwrap();
+        let value: Value = serde_json::from_str(&json_data).unwrap();
+        assert_eq!(value["format"], HYPERGRAPH_FORMAT);
+        assert_eq!(value["version"], HYPERGRAPH_FORMAT_VERSION);
+        assert_eq!(from_versioned_json(json_data.as_bytes()).unwrap(), state);
+    }
+
+    #[test]
+    fn test_version_1_files_are_migrated() {
+        let legacy = r#"{
+            "atoms": [{"id": 0, "metadata": null}, {"id": 1, "metadata": null}, {"id": 2, "metadata": null}],
+            "relations": [
+                {"id": 4, "atoms": [1, 2], "metadata": null},
+                {"id": 1, "atoms": [0, 1], "metadata": null}
+            ],
+            "step_number": 3,
+            "next_atom_id": 3,
+            "next_relation_id": 5
+        }"#;
+
+        let state = from_versioned_json(legacy.as_bytes()).unwrap();
+        assert_eq!(state.relations()[0].created_at(), 1);
+        assert_eq!(state.relations()[1].created_at(), 0);
+        assert_eq!(state.next_timestamp(), 2);
+    }
+
+    #[test]
+    fn test_unsupported_versions_and_formats_are_rejected() {
+        let newer = json!({"format": HYPERGRAPH_FORMAT, "version": HYPERGRAPH_FORMAT_VERSION + 1, "data": {}});
+        assert!(matches!(
+            from_versioned_json(newer.to_string().as_bytes()),
+            Err(PersistenceError::UnsupportedVersion { found, supported: HYPERGRAPH_FORMAT_VERSION }) if found == HYPERGRAPH_FORMAT_VERSION + 1
+        ));
+
+        let other = json!({"format": "simulation_session", "version": 1, "data": {}});
+        assert!(matches!(
+            from_versioned_json(other.to_string().as_bytes()),
+            Err(PersistenceError::WrongFormat { .. })
+        ));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use crate::hypergraph::{Atom, AtomId, Hypergraph};
+use crate::rules::parser::{parse_expression, Expr, ExprKind};
+use crate::rules::{RuleParseError, RuleParseResult};
+use crate::simulation::HypergraphState;
+
+/// Parses a hypergraph written as a Wolfram Language nested list, e.g. `{{1,2},{2,3},{3,1}}`.
+/// A pasted `WolframModel[rules, init, ...]` call is read as its initial state `init`.
+///
+/// Integer atoms keep their number as ID. Any other atom, such as `x`, `"A"` or
+/// `Symbol["A"]`, becomes a new atom numbered after the largest integer, with the
+/// expression as its metadata so that `to_wolfram` writes it back unchanged.
+pub fn parse_hypergraph(input: &str) -> RuleParseResult<HypergraphState> {
+    let expr = parse_expression(input)?;
+    let relations = match &expr.kind {
+        ExprKind::Apply(head, args) if head == "WolframModel" => match args.get(1) {
+            Some(init) => init,
+            None => return Err(RuleParseError::at("WolframModel[...] needs an initial state", &expr)),
+        },
+        _ => &expr,
+    };
+    let relations = match &relations.kind {
+        ExprKind::List(relations) => relations,
+        _ => {
+            return Err(RuleParseError::at(
+                format!("expected a list of relations but found {}", relations.describe()),
+                relations,
+            ))
+        }
+    };
+
+    // Symbolic atoms are numbered after every integer atom
+    let mut next_symbolic = 0;
+    for relation in relations {
+        let ExprKind::List(elements) = &relation.kind else {
+            return Err(RuleParseError::at(
+                format!("expected a relation such as {{1,2}} but found {}", relation.describe()),
+                relation,
+            ));
+        };
+        for element in elements {
+            match &element.kind {
+                ExprKind::Integer(n) => {
+                    let after = n.checked_add(1).ok_or_else(|| {
+                        RuleParseError::at(format!("atom {} leaves no ID for further atoms", n), element)
+                    })?;
+                    next_symbolic = next_symbolic.max(after);
+                }
+                ExprKind::List(_) | ExprKind::Rule(_, _) => {
+                    return Err(RuleParseError::at(
+                        format!("expected an atom but found {}", element.describe()),
+                        element,
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut hypergraph = Hypergraph::new();
+    let mut symbolic: HashMap<String, AtomId> = HashMap::new();
+    for relation in relations {
+        let ExprKind::List(elements) = &relation.kind else { unreachable!("checked above") };
+        let mut atoms = Vec::with_capacity(elements.len());
+        for element in elements {
+            let id = match &element.kind {
+                ExprKind::Integer(n) => {
+                    let id = AtomId::new(*n);
+                    if !hypergraph.contains_atom(id) {
+                        hypergraph.add_atom(Atom::new(id));
+                    }
+                    id
+                }
+                _ => {
+                    let text = format_expr(element);
+                    match symbolic.get(&text) {
+                        Some(&id) => id,
+                        None => {
+                            let id = AtomId::new(next_symbolic);
+                            next_symbolic = next_symbolic.checked_add(1).ok_or_else(|| {
+                                RuleParseError::at(format!("no atom ID is left for {}", text), element)
+                            })?;
+                            hypergraph.add_atom(Atom::with_metadata(id, text.clone()));
+                            symbolic.insert(text, id);
+                            id
+                        }
+                    }
+                }
+            };
+            atoms.push(id);
+        }
+        hypergraph.create_relation(atoms);
+    }
+    hypergraph.set_next_atom_id(next_symbolic);
+
+    Ok(HypergraphState::new(
+        hypergraph.get_all_atoms(),
+        hypergraph.get_all_relations(),
+        0,
+        hypergraph.next_atom_id(),
+        hypergraph.next_relation_id(),
+    )
+    .with_next_timestamp(hypergraph.next_timestamp()))
+}
+
+/// Writes a hypergraph state as a Wolfram Language nested list, relations in creation order.
+/// Atoms are written as their ID, or as their metadata if it is a Wolfram Language atom
+/// such as `Symbol["A"]`; other metadata is written as a string.
+pub fn to_wolfram(state: &HypergraphState) -> String {
+    let names: HashMap<AtomId, String> = state
+        .atoms()
+        .iter()
+        .filter_map(|atom| atom.metadata().map(|metadata| (atom.id(), atom_name(metadata))))
+        .collect();
+
+    let relations: Vec<String> = state
+        .relations()
+        .iter()
+        .map(|relation| {
+            let atoms: Vec<String> = relation
+                .atoms()
+                .iter()
+                .map(|id| names.get(id).cloned().unwrap_or_else(|| id.value().to_string()))
+                .collect();
+            format!("{{{}}}", atoms.join(", "))
+        })
+        .collect();
+    format!("{{{}}}", relations.join(", "))
+}
+
+/// Writes a hypergraph as a Wolfram Language nested list; see `to_wolfram`.
+pub fn hypergraph_to_wolfram(hypergraph: &Hypergraph) -> String {
+    to_wolfram(&HypergraphState::new(
+        hypergraph.get_all_atoms(),
+        hypergraph.get_all_relations(),
+        0,
+        hypergraph.next_atom_id(),
+        hypergraph.next_relation_id(),
+    ))
+}
+
+/// Returns the text an atom with the given metadata is written as.
+fn atom_name(metadata: &str) -> String {
+    match parse_expression(metadata).map(|expr| expr.kind) {
+        Ok(ExprKind::Symbol(_) | ExprKind::Str(_) | ExprKind::Apply(_, _)) => metadata.to_string(),
+        _ => format_expr(&Expr { kind: ExprKind::Str(metadata.to_string()), line: 0, column: 0 }),
+    }
+}
+
+/// Formats an expression in Wolfram Language input form.
+fn format_expr(expr: &Expr) -> String {
+    let join = |items: &[Expr]| items.iter().map(format_expr).collect::<Vec<_>>().join(", ");
+    match &expr.kind {
+        ExprKind::List(items) => format!("{{{}}}", join(items)),
+        ExprKind::Symbol(name) => name.clone(),
+        ExprKind::Integer(n) => n.to_string(),
+        ExprKind::Str(text) => format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")),
+        ExprKind::Apply(head, args) => format!("{}[{}]", head, join(args)),
+        ExprKind::Rule(lhs, rhs) => format!("{} -> {}", format_expr(lhs), format_expr(rhs)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_integer_hypergraph() {
+        let state = parse_hypergraph("{{1,2},{2,3},{3,1}}").unwrap();
+        assert_eq!(state.atoms().len(), 3);
+        assert_eq!(state.relations().len(), 3);
+        assert_eq!(state.relations()[1].atoms(), &[AtomId::new(2), AtomId::new(3)]);
+        assert_eq!(state.next_atom_id(), 4);
+        assert_eq!(to_wolfram(&state), "{{1, 2}, {2, 3}, {3, 1}}");
+    }
+
+    #[test]
+    fn test_symbolic_atoms_round_trip() {
+        let input = r#"{{Symbol["A"], 1}, {1, x, "quoted \"name\""}, {Symbol["A"]}}"#;
+        let state = parse_hypergraph(input).unwrap();
+        assert_eq!(state.atoms().len(), 4);
+        let a = state.atoms().iter().find(|atom| atom.metadata() == Some("Symbol[\"A\"]")).unwrap();
+        assert_eq!(a.id(), AtomId::new(2));
+        assert_eq!(to_wolfram(&state), input);
+    }
+
+    #[test]
+    fn test_wolfram_model_call_and_errors() {
+        let state = parse_hypergraph("WolframModel[{{x,y}}->{{x,z},{z,y}}, {{0,0},{0,0}}, 10]").unwrap();
+        assert_eq!(state.relations().len(), 2);
+        assert_eq!(state.atoms().len(), 1);
+
+        assert!(parse_hypergraph("{1,2}").is_err());
+        assert!(parse_hypergraph("{{1,{2}}}").is_err());
+        let error = parse_hypergraph("{{1,2},\n {2,").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        // The largest IDs leave nothing for the next atom
+        let error = parse_hypergraph("{{1,18446744073709551615}}").unwrap_err();
+        assert_eq!(error.column, 5);
+        assert!(parse_hypergraph("{{18446744073709551614,a}}").is_err());
+        assert!(parse_hypergraph("{{18446744073709551613,a}}").is_ok());
+    }
+
+    #[test]
+    fn test_plain_metadata_is_quoted() {
+        let mut state = parse_hypergraph("{{1,2}}").unwrap();
+        state.atoms[0].set_metadata(Some("two words".to_string()));
+        assert_eq!(to_wolfram(&state), "{{\"two words\", 2}}");
+    }
+}
@@ -118,6 +118,16 @@ pub struct HypergraphState {
     
     /// The next relation ID to be assigned (for proper reconstruction)
     pub next_relation_id: u64,
+    
+    /// State of the random number generator used for event selection, if recorded,
+    /// so that a stochastic run can be continued exactly
+    #[serde(default)]
+    pub rng: Option<super::random::SimulationRng>,
+    
+    /// The creation timestamp the next created relation will get. Older states
+    /// without it restart the clock after their newest relation.
+    #[serde(default)]
+    pub next_timestamp: u64,
 }
 
 impl HypergraphState {
@@ -135,9 +145,23 @@ impl HypergraphState {
             step_number,
             next_atom_id,
             next_relation_id,
+            rng: None,
+            next_timestamp: 0,
         }
     }
     
+    /// Records the random number generator state with this state.
+    pub fn with_rng(mut self, rng: super::random::SimulationRng) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+    
+    /// Records the relation creation clock with this state.
+    pub fn with_next_timestamp(mut self, next_timestamp: u64) -> Self {
+        self.next_timestamp = next_timestamp;
+        self
+    }
+    
     /// Returns the atoms in this state.
     pub fn atoms(&self) -> &[crate::hypergraph::Atom] {
         &self.atoms
@@ -162,6 +186,42 @@ impl HypergraphState {
     pub fn next_relation_id(&self) -> u64 {
         self.next_relation_id
     }
+    
+    /// Returns the recorded random number generator state, if any.
+    pub fn rng(&self) -> Option<&super::random::SimulationRng> {
+        self.rng.as_ref()
+    }
+    
+    /// Returns the creation timestamp the next created relation will get.
+    pub fn next_timestamp(&self) -> u64 {
+        self.next_timestamp
+    }
+    
+    /// Rebuilds the hypergraph described by this state, including its ID counters
+    /// and relation creation clock.
+    pub fn to_hypergraph(&self) -> crate::hypergraph::Hypergraph {
+        let mut hypergraph = crate::hypergraph::Hypergraph::new();
+        hypergraph.set_next_atom_id(self.next_atom_id);
+        hypergraph.set_next_relation_id(self.next_relation_id);
+        for atom in &self.atoms {
+            hypergraph.add_atom(atom.clone());
+        }
+        for relation in &self.relations {
+            hypergraph.add_relation(relation.clone());
+        }
+        // Adding relations moves the clock past the newest one; the recorded clock may be later
+        hypergraph.set_next_timestamp(hypergraph.next_timestamp().max(self.next_timestamp));
+        hypergraph
+    }
+    
+    /// Returns the canonical form of this state's hypergraph.
+    /// Two states have the same canonical form when they are equal up to relabelling of atoms.
+    pub fn canonical_form(&self) -> crate::hypergraph::CanonicalForm {
+        crate::hypergraph::CanonicalForm::from_parts(
+            self.atoms.iter().map(|atom| atom.id()),
+            self.relations.iter().map(|relation| relation.atoms().to_vec()),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -242,4 +302,24 @@ mod tests {
         assert_eq!(state.next_atom_id(), 3);
         assert_eq!(state.next_relation_id(), 2);
     }
+    
+    #[test]
+    fn test_hypergraph_state_canonical_form() {
+        let atoms = vec![
+            crate::hypergraph::Atom::new(AtomId::new(1)),
+            crate::hypergraph::Atom::new(AtomId::new(2)),
+        ];
+        let forward = HypergraphState::new(
+            atoms.clone(),
+            vec![crate::hypergraph::Relation::new(RelationId::new(0), vec![AtomId::new(1), AtomId::new(2)])],
+            0, 3, 1,
+        );
+        let backward = HypergraphState::new(
+            atoms,
+            vec![crate::hypergraph::Relation::new(RelationId::new(5), vec![AtomId::new(2), AtomId::new(1)])],
+            7, 3, 6,
+        );
+        
+        assert_eq!(forward.canonical_form(), backward.canonical_form());
+    }
 } 
\ No newline at end of file
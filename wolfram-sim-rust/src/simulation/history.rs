@@ -0,0 +1,226 @@
+use serde::{Serialize, Deserialize};
+
+use crate::evolution::RewriteResult;
+use crate::hypergraph::{Atom, Hypergraph, Relation};
+use super::event::SimulationEvent;
+use super::random::SimulationRng;
+
+/// Simulation counters at a point in the history, restored when undoing or redoing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryMarker {
+    /// Step number of the simulation
+    pub step_number: u64,
+
+    /// Number of completed generations
+    pub generation: u64,
+
+    /// Next atom ID of the hypergraph
+    pub next_atom_id: u64,
+
+    /// Next relation ID of the hypergraph
+    pub next_relation_id: u64,
+
+    /// Next relation creation timestamp of the hypergraph
+    pub next_timestamp: u64,
+
+    /// Random number generator used for event selection
+    pub rng: SimulationRng,
+}
+
+/// An applied event together with everything needed to reverse or replay it exactly:
+/// the full contents of the relations it removed, the atoms and relations it created,
+/// and the counters before and after it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvertibleEvent {
+    /// The event as reported to clients
+    pub event: SimulationEvent,
+
+    /// Relations removed by the event, as they were before removal
+    pub removed_relations: Vec<Relation>,
+
+    /// Atoms created by the event
+    pub created_atoms: Vec<Atom>,
+
+    /// Relations created by the event
+    pub created_relations: Vec<Relation>,
+
+    /// Counters before the event was applied
+    pub before: HistoryMarker,
+
+    /// Counters after the event was applied
+    pub after: HistoryMarker,
+}
+
+impl InvertibleEvent {
+    /// Builds the log entry for a successful rewrite. `removed_relations` must be the
+    /// contents of the relations in `result.removed_relations`, captured before the
+    /// rewrite; created atoms and relations are read from the rewritten `hypergraph`.
+    pub fn from_rewrite(
+        event: SimulationEvent,
+        result: &RewriteResult,
+        removed_relations: Vec<Relation>,
+        hypergraph: &Hypergraph,
+        before: HistoryMarker,
+        after: HistoryMarker,
+    ) -> Self {
+        InvertibleEvent {
+            event,
+            removed_relations,
+            created_atoms: result.new_atoms.iter().filter_map(|id| hypergraph.get_atom(*id)).cloned().collect(),
+            created_relations: result
+                .new_relations
+                .iter()
+                .filter_map(|id| hypergraph.get_relation(*id))
+                .cloned()
+                .collect(),
+            before,
+            after,
+        }
+    }
+
+    /// Reverses the event on a hypergraph in the state right after it was applied.
+    pub fn undo(&self, hypergraph: &mut Hypergraph) {
+        for relation in &self.created_relations {
+            hypergraph.remove_relation(relation.id());
+        }
+        for atom in &self.created_atoms {
+            hypergraph.remove_atom(atom.id());
+        }
+        for relation in &self.removed_relations {
+            hypergraph.add_relation(relation.clone());
+        }
+        Self::restore_counters(hypergraph, &self.before);
+    }
+
+    /// Re-applies the event on a hypergraph in the state right before it was applied.
+    pub fn redo(&self, hypergraph: &mut Hypergraph) {
+        for relation in &self.removed_relations {
+            hypergraph.remove_relation(relation.id());
+        }
+        for atom in &self.created_atoms {
+            hypergraph.add_atom(atom.clone());
+        }
+        for relation in &self.created_relations {
+            hypergraph.add_relation(relation.clone());
+        }
+        Self::restore_counters(hypergraph, &self.after);
+    }
+
+    /// The rewrite performed by this event, for updating match indices on redo.
+    pub fn rewrite(&self) -> RewriteResult {
+        RewriteResult::success(
+            self.created_atoms.iter().map(Atom::id).collect(),
+            self.created_relations.iter().map(Relation::id).collect(),
+            self.removed_relations.iter().map(Relation::id).collect(),
+        )
+    }
+
+    /// The rewrite that reverses this event, for updating match indices on undo.
+    pub fn inverse_rewrite(&self) -> RewriteResult {
+        RewriteResult::success(
+            Vec::new(),
+            self.removed_relations.iter().map(Relation::id).collect(),
+            self.created_relations.iter().map(Relation::id).collect(),
+        )
+    }
+
+    fn restore_counters(hypergraph: &mut Hypergraph, marker: &HistoryMarker) {
+        hypergraph.set_next_atom_id(marker.next_atom_id);
+        hypergraph.set_next_relation_id(marker.next_relation_id);
+        hypergraph.set_next_timestamp(marker.next_timestamp);
+    }
+}
+
+/// Returns how many of the oldest of `len` events to forget to respect `max_events`.
+/// Nothing is forgotten until the limit is exceeded; then events are forgotten in a
+/// batch down to seven eighths of the limit, so forgetting does not happen every step.
+pub(crate) fn events_to_forget(len: usize, max_events: Option<usize>) -> usize {
+    match max_events {
+        Some(max) if len > max => len - (max - max / 8),
+        _ => 0,
+    }
+}
+
+/// The undo and redo stacks of a simulation. With a `max_events` limit, the oldest
+/// applied events are forgotten, so at most that many steps can be undone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventHistory {
+    /// Applied events, oldest first
+    applied: Vec<InvertibleEvent>,
+
+    /// Undone events, most recently undone last
+    undone: Vec<InvertibleEvent>,
+
+    /// Largest number of events kept, or `None` to keep every event
+    max_events: Option<usize>,
+}
+
+impl EventHistory {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the largest number of events kept, if limited.
+    pub fn max_events(&self) -> Option<usize> {
+        self.max_events
+    }
+
+    /// Limits the number of events kept, forgetting the oldest applied events if needed.
+    pub fn set_max_events(&mut self, max_events: Option<usize>) {
+        self.max_events = max_events;
+        self.forget_oldest();
+    }
+
+    /// Records a newly applied event. This discards anything that could be redone.
+    pub fn push(&mut self, entry: InvertibleEvent) {
+        self.undone.clear();
+        self.applied.push(entry);
+        self.forget_oldest();
+    }
+
+    fn forget_oldest(&mut self) {
+        let forget = events_to_forget(self.applied.len(), self.max_events);
+        self.applied.drain(..forget);
+    }
+
+    /// Moves the most recent applied event to the redo stack and returns it.
+    pub fn undo(&mut self) -> Option<&InvertibleEvent> {
+        let entry = self.applied.pop()?;
+        self.undone.push(entry);
+        self.undone.last()
+    }
+
+    /// Moves the most recently undone event back to the applied events and returns it.
+    pub fn redo(&mut self) -> Option<&InvertibleEvent> {
+        let entry = self.undone.pop()?;
+        self.applied.push(entry);
+        self.applied.last()
+    }
+
+    /// Returns the most recent applied event for amending, e.g. its counters.
+    pub fn last_applied_mut(&mut self) -> Option<&mut InvertibleEvent> {
+        self.applied.last_mut()
+    }
+
+    /// Returns the applied events, oldest first.
+    pub fn applied(&self) -> &[InvertibleEvent] {
+        &self.applied
+    }
+
+    /// Returns the number of events that can be undone.
+    pub fn undo_count(&self) -> usize {
+        self.applied.len()
+    }
+
+    /// Returns the number of events that can be redone.
+    pub fn redo_count(&self) -> usize {
+        self.undone.len()
+    }
+
+    /// Forgets all applied and undone events.
+    pub fn clear(&mut self) {
+        self.applied.clear();
+        self.undone.clear();
+    }
+}
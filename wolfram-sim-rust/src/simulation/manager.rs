@@ -1,11 +1,24 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Instant;
 use serde::{Serialize, Deserialize};
 
-use crate::hypergraph::{Hypergraph, AtomId, RelationId};
-use crate::rules::{Rule, rule::RuleSet, RuleId};
-use crate::matching::find_pattern_matches;
+use crate::hypergraph::{Hypergraph, Relation};
+use crate::rules::rule::RuleSet;
+use crate::matching::{MatchIndex, PatternMatch};
 use crate::evolution::apply_rule;
+use crate::causal::CausalGraph;
 use super::event::{SimulationEvent, HypergraphState};
+use super::multiway::{MultiwayConfig, MultiwaySystem};
+use super::ordering::{compare_events, EventOrdering, OrderedEvent};
+use super::random::SimulationRng;
+use super::history::{EventHistory, HistoryMarker, InvertibleEvent};
+use super::timeline::{recorded_match, Timeline};
+use super::metrics::{MetricSample, MetricsRecorder, StepContext};
+use crate::serialization::event_log::EventLogWriter;
+use crate::serialization::persistence::PersistenceResult;
+
+/// Number of events the causal graph, undo log and timeline each keep unless configured otherwise.
+pub const DEFAULT_EVENT_RETENTION: usize = 100_000;
 
 /// Result of a simulation step operation.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -21,6 +34,9 @@ pub struct StepResult {
     
     /// Optional message describing the result
     pub message: Option<String>,
+    
+    /// Problems that did not stop the step, such as a failed checkpoint or event log write
+    pub warnings: Vec<String>,
 }
 
 impl StepResult {
@@ -31,6 +47,7 @@ impl StepResult {
             event: Some(event),
             hypergraph_state,
             message: None,
+            warnings: Vec::new(),
         }
     }
     
@@ -41,6 +58,7 @@ impl StepResult {
             event: None,
             hypergraph_state,
             message: Some("No applicable rules found".to_string()),
+            warnings: Vec::new(),
         }
     }
     
@@ -51,6 +69,28 @@ impl StepResult {
     }
 }
 
+/// Result of a generation step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationResult {
+    /// Whether any rule was applied
+    pub success: bool,
+    
+    /// Number of generations completed, including this one if it succeeded
+    pub generation: u64,
+    
+    /// Events applied in this generation, in the order they were applied
+    pub events: Vec<SimulationEvent>,
+    
+    /// Current state of the hypergraph after the generation
+    pub hypergraph_state: HypergraphState,
+    
+    /// Optional message describing the result
+    pub message: Option<String>,
+    
+    /// Problems that did not stop the generation, such as a failed checkpoint or event log write
+    pub warnings: Vec<String>,
+}
+
 /// Configuration for continuous simulation.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ContinuousSimulationConfig {
@@ -75,7 +115,7 @@ impl Default for ContinuousSimulationConfig {
 }
 
 /// Result of a continuous simulation run.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ContinuousSimulationResult {
     /// Total number of steps executed
     pub steps_executed: u64,
@@ -88,6 +128,13 @@ pub struct ContinuousSimulationResult {
     
     /// Reason why the simulation stopped
     pub stop_reason: StopReason,
+    
+    /// Metric samples collected during the run, if a recorder is attached, up to the
+    /// recorder's `max_samples`
+    pub metrics: Vec<MetricSample>,
+    
+    /// Problems that did not stop the run, such as a failed checkpoint or event log write
+    pub warnings: Vec<String>,
 }
 
 /// Reasons why a continuous simulation might stop.
@@ -116,72 +163,150 @@ pub struct SimulationManager {
     /// Current step number
     step_number: u64,
     
+    /// Number of generations completed since the current state was loaded
+    generation: u64,
+    
     /// Event selection strategy (for MVP, we use deterministic "first match")
     event_selection_strategy: EventSelectionStrategy,
+    
+    /// Random number generator for event orderings that break ties at random
+    rng: SimulationRng,
+    
+    /// Causal graph of the events applied since the current state was loaded
+    causal_graph: CausalGraph,
+
+    /// Largest number of events the causal graph, undo log and timeline each keep
+    event_retention: Option<usize>,
+    
+    /// Matches of every rule, kept up to date across steps. Built lazily and
+    /// discarded whenever the hypergraph or rule set is changed from outside `step`.
+    match_index: Option<MatchIndex>,
+    
+    /// Invertible log of applied events, for undo and redo
+    history: EventHistory,
+    
+    /// Checkpoints and event log for reconstructing past states. Like the undo log,
+    /// it starts over whenever the state or rule set is changed from outside `step`.
+    timeline: Timeline,
+    
+    /// Durable log every applied event is appended to, if attached
+    event_log: Option<EventLogWriter>,
+    
+    /// Collectors run after every step, if attached
+    metrics: Option<MetricsRecorder>,
+    
+    /// Problems recorded since they were last taken, such as failed checkpoints
+    /// or event log writes, which only make the run less recoverable
+    warnings: Vec<String>,
 }
 
 /// Strategy for selecting which rule to apply when multiple matches are available.
-/// For MVP, we implement a simple deterministic strategy.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Within a rule, matches are tried in order of their matched relation IDs.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub enum EventSelectionStrategy {
     /// Apply the first rule that has at least one match
+    #[default]
     FirstRuleFirstMatch,
     
     /// Apply the rule with the most matches available
     MostMatches,
+    
+    /// Order all matches by a priority list of SetReplace ordering functions
+    Ordered(Vec<EventOrdering>),
+    
+    /// Pick a match at random using the manager's seeded generator. Each match is
+    /// weighted by its rule's entry in `rule_weights`; rules without an entry weigh 1,
+    /// so an empty list picks uniformly among all matches.
+    Random {
+        /// Relative weight of each rule, by position in the rule set
+        rule_weights: Vec<f64>,
+    },
 }
 
-impl Default for EventSelectionStrategy {
-    fn default() -> Self {
-        EventSelectionStrategy::FirstRuleFirstMatch
+impl EventSelectionStrategy {
+    /// The event ordering `WolframModel` uses by default.
+    pub fn wolfram_standard() -> Self {
+        EventSelectionStrategy::Ordered(EventOrdering::standard())
+    }
+    
+    /// Uniform random selection among all matches.
+    pub fn uniform_random() -> Self {
+        EventSelectionStrategy::Random { rule_weights: Vec::new() }
+    }
+    
+    /// Returns the weight of the rule at `rule_index` under random selection.
+    /// Negative and non-finite weights count as zero.
+    fn rule_weight(rule_weights: &[f64], rule_index: usize) -> f64 {
+        let weight = rule_weights.get(rule_index).copied().unwrap_or(1.0);
+        if weight.is_finite() && weight > 0.0 { weight } else { 0.0 }
     }
 }
 
 impl SimulationManager {
     /// Creates a new simulation manager with an empty hypergraph and basic rule set.
     pub fn new() -> Self {
-        SimulationManager {
+        let mut manager = SimulationManager {
             hypergraph: Hypergraph::new(),
             rule_set: RuleSet::create_basic_ruleset(),
             step_number: 0,
+            generation: 0,
             event_selection_strategy: EventSelectionStrategy::default(),
-        }
+            rng: SimulationRng::default(),
+            causal_graph: CausalGraph::new(),
+            event_retention: None,
+            match_index: None,
+            history: EventHistory::new(),
+            timeline: Timeline::default(),
+            event_log: None,
+            metrics: None,
+            warnings: Vec::new(),
+        };
+        manager.set_event_retention(Some(DEFAULT_EVENT_RETENTION));
+        manager
     }
     
     /// Creates a new simulation manager with a custom hypergraph and rule set.
     pub fn with_hypergraph_and_rules(hypergraph: Hypergraph, rule_set: RuleSet) -> Self {
-        SimulationManager {
+        let mut manager = SimulationManager {
             hypergraph,
             rule_set,
             step_number: 0,
+            generation: 0,
             event_selection_strategy: EventSelectionStrategy::default(),
-        }
+            rng: SimulationRng::default(),
+            causal_graph: CausalGraph::new(),
+            event_retention: None,
+            match_index: None,
+            history: EventHistory::new(),
+            timeline: Timeline::default(),
+            event_log: None,
+            metrics: None,
+            warnings: Vec::new(),
+        };
+        manager.set_event_retention(Some(DEFAULT_EVENT_RETENTION));
+        manager
     }
     
     /// Creates a new simulation manager from a saved hypergraph state.
     pub fn from_state(state: &HypergraphState, rule_set: RuleSet) -> Result<Self, String> {
-        let mut hypergraph = Hypergraph::new();
-        
-        // Restore the ID counters
-        hypergraph.set_next_atom_id(state.next_atom_id());
-        hypergraph.set_next_relation_id(state.next_relation_id());
-        
-        // Add all atoms
-        for atom in state.atoms() {
-            hypergraph.add_atom(atom.clone());
-        }
-        
-        // Add all relations
-        for relation in state.relations() {
-            hypergraph.add_relation(relation.clone());
-        }
-        
-        Ok(SimulationManager {
-            hypergraph,
+        let mut manager = SimulationManager {
+            hypergraph: state.to_hypergraph(),
             rule_set,
             step_number: state.step_number(),
+            generation: 0,
             event_selection_strategy: EventSelectionStrategy::default(),
-        })
+            rng: state.rng().cloned().unwrap_or_default(),
+            causal_graph: CausalGraph::new(),
+            event_retention: None,
+            match_index: None,
+            history: EventHistory::new(),
+            timeline: Timeline::default(),
+            event_log: None,
+            metrics: None,
+            warnings: Vec::new(),
+        };
+        manager.set_event_retention(Some(DEFAULT_EVENT_RETENTION));
+        Ok(manager)
     }
     
     /// Returns the current hypergraph state.
@@ -193,6 +318,8 @@ impl SimulationManager {
             self.hypergraph.next_atom_id(),
             self.hypergraph.next_relation_id(),
         )
+        .with_rng(self.rng.clone())
+        .with_next_timestamp(self.hypergraph.next_timestamp())
     }
     
     /// Returns a reference to the current hypergraph.
@@ -202,6 +329,10 @@ impl SimulationManager {
     
     /// Returns a mutable reference to the current hypergraph.
     pub fn hypergraph_mut(&mut self) -> &mut Hypergraph {
+        // Outside edits invalidate the index and the undo log
+        self.match_index = None;
+        self.history.clear();
+        self.timeline.clear();
         &mut self.hypergraph
     }
     
@@ -210,40 +341,184 @@ impl SimulationManager {
         self.step_number
     }
     
+    /// Returns the number of generations completed with `step_generation`.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    
     /// Returns a reference to the rule set.
     pub fn rule_set(&self) -> &RuleSet {
         &self.rule_set
     }
     
+    /// Returns the causal graph of the events applied so far.
+    pub fn causal_graph(&self) -> &CausalGraph {
+        &self.causal_graph
+    }
+    
+    /// Replaces the rule set used for subsequent steps.
+    pub fn set_rule_set(&mut self, rule_set: RuleSet) {
+        self.rule_set = rule_set;
+        self.match_index = None;
+        // Logged events can only be replayed with the rules that produced them
+        self.timeline.clear();
+    }
+    
     /// Sets the event selection strategy.
     pub fn set_event_selection_strategy(&mut self, strategy: EventSelectionStrategy) {
         self.event_selection_strategy = strategy;
     }
     
+    /// Returns the event selection strategy.
+    pub fn event_selection_strategy(&self) -> &EventSelectionStrategy {
+        &self.event_selection_strategy
+    }
+    
+    /// Reseeds the random number generator used for random event selection.
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.rng = SimulationRng::new(seed);
+    }
+    
+    /// Restores the random number generator, e.g. when resuming a saved run.
+    pub fn set_rng(&mut self, rng: SimulationRng) {
+        self.rng = rng;
+    }
+    
+    /// Returns the seed of the random number generator used for random event selection.
+    pub fn random_seed(&self) -> u64 {
+        self.rng.seed()
+    }
+    
     /// Executes a single simulation step.
     /// This implements the core simulation loop logic: match, select, apply.
     pub fn step(&mut self) -> StepResult {
-        // Find all possible matches for all rules
-        let mut all_matches = Vec::new();
+        let started = Instant::now();
+        let before = self.history_marker();
+        self.start_timeline();
         
-        for rule in self.rule_set.iter() {
-            let matches = find_pattern_matches(rule.pattern(), &self.hypergraph);
-            if !matches.is_empty() {
-                all_matches.push((rule, matches));
-            }
-        }
+        // Find all possible matches for all rules, reusing the index from earlier steps
+        let match_index = self
+            .match_index
+            .get_or_insert_with(|| MatchIndex::new(&self.rule_set, &self.hypergraph));
         
         // If no matches found, simulation cannot proceed
-        if all_matches.is_empty() {
-            return StepResult::no_rules_applicable(self.get_current_state());
+        if match_index.is_empty() {
+            let mut result = StepResult::no_rules_applicable(self.get_current_state());
+            result.warnings = self.take_warnings();
+            return result;
         }
         
         // Select which rule and match to apply based on strategy
-        let (selected_rule, selected_match) = self.select_event(&all_matches);
+        let (rule_index, selected_match) =
+            Self::select_event(&self.event_selection_strategy, match_index, &self.hypergraph, &mut self.rng);
         
         // Apply the selected rule
+        let event = self.apply_event(rule_index, &selected_match, &before);
+        
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record_step(&StepContext {
+                step_number: self.step_number,
+                hypergraph: &self.hypergraph,
+                event: &event,
+                duration: started.elapsed(),
+            });
+        }
+        
+        let current_state = self.get_current_state();
+        
+        let mut result = StepResult::success(event, current_state);
+        result.warnings = self.take_warnings();
+        result
+    }
+    
+    /// Executes a single generation: a maximal set of pairwise disjoint matches,
+    /// chosen greedily in the order given by the event selection strategy, all applied
+    /// to the current state. Matches created during the generation wait for the next one.
+    pub fn step_generation(&mut self) -> GenerationResult {
+        self.start_timeline();
+        let match_index = self
+            .match_index
+            .get_or_insert_with(|| MatchIndex::new(&self.rule_set, &self.hypergraph));
+        
+        if match_index.is_empty() {
+            return GenerationResult {
+                success: false,
+                generation: self.generation,
+                events: Vec::new(),
+                hypergraph_state: self.get_current_state(),
+                message: Some("No applicable rules found".to_string()),
+                warnings: self.take_warnings(),
+            };
+        }
+        
+        let rng_before = self.rng.clone();
+        let selected =
+            Self::select_generation(&self.event_selection_strategy, match_index, &self.hypergraph, &mut self.rng);
+        
+        // The matches are disjoint, so applying one never invalidates another.
+        // Undoing any event of the generation rewinds the generator to before the selection.
+        let mut events = Vec::with_capacity(selected.len());
+        for (rule_index, selected_match) in selected {
+            let before = HistoryMarker { rng: rng_before.clone(), ..self.history_marker() };
+            events.push(self.apply_event(rule_index, &selected_match, &before));
+        }
+        self.generation += 1;
+        if let Some(last) = self.history.last_applied_mut() {
+            // Redoing the last event of the generation completes it
+            last.after.generation = self.generation;
+        }
+        
+        GenerationResult {
+            success: true,
+            generation: self.generation,
+            events,
+            hypergraph_state: self.get_current_state(),
+            message: None,
+            warnings: self.take_warnings(),
+        }
+    }
+    
+    /// Executes multiple generations, stopping early if no rule applies.
+    pub fn step_generations(&mut self, num_generations: u64) -> Vec<GenerationResult> {
+        let mut results = Vec::new();
+        
+        for _ in 0..num_generations {
+            let result = self.step_generation();
+            let should_continue = result.success;
+            results.push(result);
+            
+            if !should_continue {
+                break;
+            }
+        }
+        
+        results
+    }
+    
+    /// Applies one match of the rule at `rule_index` and records the resulting event,
+    /// logging it for undo with the counters from `before`.
+    fn apply_event(&mut self, rule_index: usize, selected_match: &PatternMatch, before: &HistoryMarker) -> SimulationEvent {
+        let selected_rule = self.rule_set.rule_at(rule_index).expect("indexed rule exists");
+        let removed_relations: Vec<Relation> = selected_match
+            .matched_relations
+            .iter()
+            .filter_map(|id| self.hypergraph.get_relation(*id))
+            .cloned()
+            .collect();
         let rewrite_result = apply_rule(&mut self.hypergraph, selected_rule, selected_match);
         
+        // Bring the index up to date; a failed rewrite may have left partial changes
+        // that cannot be undone
+        if rewrite_result.success {
+            if let Some(match_index) = self.match_index.as_mut() {
+                match_index.update(&self.rule_set, &self.hypergraph, &rewrite_result);
+            }
+        } else {
+            self.match_index = None;
+            self.history.clear();
+            self.timeline.clear();
+        }
+        
         // Increment step number
         self.step_number += 1;
         
@@ -251,15 +526,234 @@ impl SimulationManager {
         let event = SimulationEvent::with_description(
             self.step_number,
             selected_rule.id(),
-            rewrite_result.new_atoms,
-            rewrite_result.new_relations,
-            rewrite_result.removed_relations,
+            rewrite_result.new_atoms.clone(),
+            rewrite_result.new_relations.clone(),
+            rewrite_result.removed_relations.clone(),
             format!("Applied rule {} at step {}", selected_rule.id().value(), self.step_number),
         );
         
-        let current_state = self.get_current_state();
+        self.causal_graph.add_event(event.clone());
         
-        StepResult::success(event, current_state)
+        if rewrite_result.success {
+            let after = self.history_marker();
+            self.history.push(InvertibleEvent::from_rewrite(
+                event.clone(),
+                &rewrite_result,
+                removed_relations,
+                &self.hypergraph,
+                before.clone(),
+                after,
+            ));
+            self.record_in_timeline(&event);
+            self.append_to_event_log(&event);
+        }
+        
+        event
+    }
+    
+    /// Reapplies a recorded event to the current state, as when resuming a saved run.
+    /// The event must have been recorded at this point of a run with the current rules;
+    /// it is logged for undo, the causal graph and the timeline like a regular step.
+    pub fn replay_event(&mut self, event: &SimulationEvent) -> Result<(), String> {
+        let rule_index = self
+            .rule_set
+            .iter()
+            .position(|rule| rule.id() == event.rule_id())
+            .ok_or_else(|| format!("Step {} applied unknown rule {}", event.step_number(), event.rule_id().value()))?;
+        let rule = self.rule_set.rule_at(rule_index).expect("indexed rule exists");
+        let pattern_match = recorded_match(rule, event, &self.hypergraph)?;
+        
+        let before = self.history_marker();
+        self.start_timeline();
+        let replayed = self.apply_event(rule_index, &pattern_match, &before);
+        if replayed.step_number != event.step_number
+            || replayed.atoms_created != event.atoms_created
+            || replayed.relations_created != event.relations_created
+            || replayed.relations_removed != event.relations_removed
+        {
+            return Err(format!("Replaying step {} did not reproduce the recorded event", event.step_number()));
+        }
+        Ok(())
+    }
+    
+    /// Undoes up to `num_steps` of the most recent events, restoring the hypergraph,
+    /// counters and random number generator exactly. Returns the undone events,
+    /// most recent first.
+    pub fn undo(&mut self, num_steps: u64) -> Vec<SimulationEvent> {
+        let mut undone = Vec::new();
+        for _ in 0..num_steps {
+            let Some(entry) = self.history.undo() else {
+                break;
+            };
+            entry.undo(&mut self.hypergraph);
+            self.step_number = entry.before.step_number;
+            self.generation = entry.before.generation;
+            self.rng = entry.before.rng.clone();
+            self.causal_graph.pop_event();
+            if let Some(match_index) = self.match_index.as_mut() {
+                match_index.update(&self.rule_set, &self.hypergraph, &entry.inverse_rewrite());
+            }
+            undone.push(entry.event.clone());
+        }
+        self.timeline.truncate(self.step_number);
+        undone
+    }
+    
+    /// Redoes up to `num_steps` of the most recently undone events. Returns the
+    /// redone events in the order they were reapplied.
+    pub fn redo(&mut self, num_steps: u64) -> Vec<SimulationEvent> {
+        let mut redone = Vec::new();
+        for _ in 0..num_steps {
+            let Some(entry) = self.history.redo() else {
+                break;
+            };
+            entry.redo(&mut self.hypergraph);
+            self.step_number = entry.after.step_number;
+            self.generation = entry.after.generation;
+            self.rng = entry.after.rng.clone();
+            self.causal_graph.add_event(entry.event.clone());
+            if let Some(match_index) = self.match_index.as_mut() {
+                match_index.update(&self.rule_set, &self.hypergraph, &entry.rewrite());
+            }
+            let event = entry.event.clone();
+            self.record_in_timeline(&event);
+            self.append_to_event_log(&event);
+            redone.push(event);
+        }
+        redone
+    }
+    
+    /// Returns the undo and redo history.
+    pub fn history(&self) -> &EventHistory {
+        &self.history
+    }
+    
+    /// Returns the checkpoints and event log of the current run.
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+    
+    /// Replaces the timeline, e.g. to change the checkpoint interval or keep
+    /// checkpoints on disk. Recording starts over from the next step, within the
+    /// manager's event retention.
+    pub fn set_timeline(&mut self, mut timeline: Timeline) {
+        self.timeline.clear();
+        timeline.clear();
+        timeline.set_max_events(self.event_retention);
+        self.timeline = timeline;
+    }
+
+    /// Returns the largest number of events the causal graph, undo log and timeline each keep.
+    pub fn event_retention(&self) -> Option<usize> {
+        self.event_retention
+    }
+
+    /// Limits the events the causal graph, undo log and timeline each keep, or lifts
+    /// the limit with `None`. Beyond it the oldest events are forgotten: they can no
+    /// longer be undone, drop out of the causal graph, and their steps can no longer
+    /// be reconstructed.
+    pub fn set_event_retention(&mut self, max_events: Option<usize>) {
+        self.event_retention = max_events;
+        self.causal_graph.set_max_events(max_events);
+        self.history.set_max_events(max_events);
+        self.timeline.set_max_events(max_events);
+    }
+    
+    /// Reconstructs the state at a past step of the current run from the nearest
+    /// checkpoint and the event log. The current step returns the current state.
+    pub fn state_at_step(&self, step_number: u64) -> Result<HypergraphState, String> {
+        if step_number == self.step_number {
+            return Ok(self.get_current_state());
+        }
+        self.timeline.state_at(step_number, &self.rule_set)
+    }
+    
+    /// Attaches a durable log that every subsequently applied or redone event is
+    /// appended to, replacing and returning the previous one. Pass `None` to detach.
+    pub fn set_event_log(&mut self, event_log: Option<EventLogWriter>) -> Option<EventLogWriter> {
+        std::mem::replace(&mut self.event_log, event_log)
+    }
+    
+    /// Returns the attached event log, if any.
+    pub fn event_log(&self) -> Option<&EventLogWriter> {
+        self.event_log.as_ref()
+    }
+    
+    /// Attaches a metrics recorder that is run after every step, or detaches it with `None`.
+    /// Returns the previously attached recorder.
+    pub fn set_metrics_recorder(&mut self, metrics: Option<MetricsRecorder>) -> Option<MetricsRecorder> {
+        std::mem::replace(&mut self.metrics, metrics)
+    }
+    
+    /// Returns the attached metrics recorder.
+    pub fn metrics_recorder(&self) -> Option<&MetricsRecorder> {
+        self.metrics.as_ref()
+    }
+    
+    /// Forces the events appended to the attached log to disk.
+    pub fn sync_event_log(&mut self) -> PersistenceResult<()> {
+        self.event_log.as_mut().map_or(Ok(()), EventLogWriter::sync)
+    }
+    
+    /// Returns and clears the problems recorded since the last call. Steps, generations
+    /// and runs report theirs in their results; undo, redo and replay leave them here.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+    
+    /// Appends an event to the attached log. A log that cannot be written is detached,
+    /// so later events are not written after a gap, and a warning is recorded.
+    fn append_to_event_log(&mut self, event: &SimulationEvent) {
+        if let Some(Err(e)) = self.event_log.as_mut().map(|log| log.append(event)) {
+            self.warnings.push(format!(
+                "Failed to append step {} to the event log, which was detached: {}",
+                event.step_number(),
+                e
+            ));
+            self.event_log = None;
+        }
+    }
+    
+    /// Checkpoints the current state if the timeline has nothing recorded yet.
+    fn start_timeline(&mut self) {
+        if self.timeline.is_empty() {
+            self.checkpoint();
+        }
+    }
+    
+    /// Logs an applied event and checkpoints the state after it when one is due.
+    fn record_in_timeline(&mut self, event: &SimulationEvent) {
+        if self.timeline.is_empty() {
+            // Nothing to replay the event from
+            return;
+        }
+        if let Err(e) = self.timeline.record_event(event.clone(), &self.rule_set) {
+            // The emptied timeline starts over from the checkpoint below
+            self.warnings.push(format!("Timeline restarted at step {}: {}", event.step_number(), e));
+        }
+        if self.timeline.is_checkpoint_due(self.step_number) {
+            self.checkpoint();
+        }
+    }
+    
+    /// Stores a checkpoint of the current state. A checkpoint that cannot be written
+    /// only makes reconstruction slower, so failures are recorded as warnings and skipped.
+    fn checkpoint(&mut self) {
+        if let Err(e) = self.timeline.record_checkpoint(&self.get_current_state()) {
+            self.warnings.push(format!("Failed to checkpoint step {}: {}", self.step_number, e));
+        }
+    }
+    
+    /// Captures the current counters for the undo log.
+    fn history_marker(&self) -> HistoryMarker {
+        HistoryMarker {
+            step_number: self.step_number,
+            generation: self.generation,
+            next_atom_id: self.hypergraph.next_atom_id(),
+            next_relation_id: self.hypergraph.next_relation_id(),
+            next_timestamp: self.hypergraph.next_timestamp(),
+            rng: self.rng.clone(),
+        }
     }
     
     /// Executes multiple simulation steps.
@@ -280,8 +774,25 @@ impl SimulationManager {
     }
     
     /// Runs the simulation continuously until a stopping condition is met.
+    /// Events appended to an attached event log are synced to disk before returning.
     pub fn run_continuous(&mut self, config: ContinuousSimulationConfig) -> ContinuousSimulationResult {
+        let taken_before = self.metrics.as_ref().map_or(0, MetricsRecorder::samples_taken);
+        let mut result = self.run_until_stopped(config);
+        if let Err(e) = self.sync_event_log() {
+            result.warnings.push(format!("Failed to sync the event log: {}", e));
+        }
+        if let Some(metrics) = &self.metrics {
+            let samples = &metrics.series().samples;
+            let taken = (metrics.samples_taken() - taken_before).min(samples.len() as u64) as usize;
+            result.metrics = samples[samples.len() - taken..].to_vec();
+        }
+        result
+    }
+    
+    /// The loop of `run_continuous`.
+    fn run_until_stopped(&mut self, config: ContinuousSimulationConfig) -> ContinuousSimulationResult {
         let mut events = Vec::new();
+        let mut warnings = Vec::new();
         let mut steps_executed = 0;
         
         loop {
@@ -293,6 +804,8 @@ impl SimulationManager {
                         events,
                         final_state: self.get_current_state(),
                         stop_reason: StopReason::MaxStepsReached,
+                        metrics: Vec::new(),
+                        warnings,
                     };
                 }
             }
@@ -300,6 +813,7 @@ impl SimulationManager {
             // Execute one step
             let step_result = self.step();
             steps_executed += 1;
+            warnings.extend(step_result.warnings);
             
             if step_result.success {
                 if let Some(event) = step_result.event {
@@ -313,6 +827,8 @@ impl SimulationManager {
                         events,
                         final_state: step_result.hypergraph_state,
                         stop_reason: StopReason::FixedPointReached,
+                        metrics: Vec::new(),
+                        warnings,
                     };
                 }
                 // If not stopping on fixed point, we still can't proceed
@@ -325,61 +841,176 @@ impl SimulationManager {
             events,
             final_state: self.get_current_state(),
             stop_reason: StopReason::FixedPointReached,
+            metrics: Vec::new(),
+            warnings,
         }
     }
     
+    /// Explores the multiway evolution of the current state.
+    /// Unlike `step`, every applicable match is applied to its own copy of the
+    /// hypergraph; the manager's own state is left unchanged.
+    pub fn explore_multiway(&self, config: MultiwayConfig) -> MultiwaySystem {
+        let mut system = MultiwaySystem::new(self.hypergraph.clone(), self.rule_set.clone(), config);
+        system.evolve();
+        system
+    }
+    
     /// Resets the simulation to an empty state.
     pub fn reset(&mut self) {
         self.hypergraph.clear();
         self.step_number = 0;
+        self.generation = 0;
+        self.causal_graph.clear();
+        self.match_index = None;
+        self.history.clear();
+        self.timeline.clear();
     }
     
     /// Loads a new hypergraph state, replacing the current one.
     pub fn load_state(&mut self, state: &HypergraphState) -> Result<(), String> {
-        // Create a new hypergraph and populate it
-        let mut new_hypergraph = Hypergraph::new();
-        
-        // Restore the ID counters
-        new_hypergraph.set_next_atom_id(state.next_atom_id());
-        new_hypergraph.set_next_relation_id(state.next_relation_id());
-        
-        // Add all atoms
-        for atom in state.atoms() {
-            new_hypergraph.add_atom(atom.clone());
-        }
-        
-        // Add all relations
-        for relation in state.relations() {
-            new_hypergraph.add_relation(relation.clone());
-        }
-        
         // Replace current state
-        self.hypergraph = new_hypergraph;
+        self.hypergraph = state.to_hypergraph();
         self.step_number = state.step_number();
+        self.generation = 0;
+        self.causal_graph.clear();
+        self.match_index = None;
+        self.history.clear();
+        self.timeline.clear();
+        if let Some(rng) = state.rng() {
+            self.rng = rng.clone();
+        }
         
         Ok(())
     }
     
-    /// Selects which event (rule + match) to apply based on the current strategy.
-    fn select_event<'a>(
-        &self,
-        all_matches: &'a [(&Rule, Vec<crate::matching::PatternMatch>)],
-    ) -> (&'a Rule, &'a crate::matching::PatternMatch) {
-        match self.event_selection_strategy {
+    /// Selects which event (rule index + match) to apply based on the given strategy.
+    /// The index must contain at least one match.
+    fn select_event(
+        strategy: &EventSelectionStrategy,
+        match_index: &MatchIndex,
+        hypergraph: &Hypergraph,
+        rng: &mut SimulationRng,
+    ) -> (usize, PatternMatch) {
+        let rule_count = match_index.rule_count();
+        let rule_index = match strategy {
             EventSelectionStrategy::FirstRuleFirstMatch => {
-                // Simply take the first rule that has matches and its first match
-                let (rule, matches) = &all_matches[0];
-                (rule, &matches[0])
+                // Simply take the first rule that has matches
+                (0..rule_count).find(|&index| match_index.match_count(index) > 0)
             }
             EventSelectionStrategy::MostMatches => {
                 // Find the rule with the most matches
-                let (rule, matches) = all_matches
-                    .iter()
-                    .max_by_key(|(_, matches)| matches.len())
-                    .unwrap();
-                (rule, &matches[0])
+                (0..rule_count).max_by_key(|&index| match_index.match_count(index))
+            }
+            EventSelectionStrategy::Ordered(orderings) => {
+                // Only the best candidate is kept, so a step does not sort every match
+                let best = Self::ordered_candidates(orderings, match_index, hypergraph, rng)
+                    .min_by(|a, b| compare_events(orderings, a, b))
+                    .expect("match index is not empty");
+                return (best.rule_index, best.pattern_match.clone());
+            }
+            EventSelectionStrategy::Random { rule_weights } => {
+                // Choose a rule with probability proportional to weight times match count,
+                // then a match of that rule uniformly
+                let totals: Vec<f64> = (0..rule_count)
+                    .map(|index| {
+                        EventSelectionStrategy::rule_weight(rule_weights, index) * match_index.match_count(index) as f64
+                    })
+                    .collect();
+                let mut target = rng.next_f64() * totals.iter().sum::<f64>();
+                let rule_index = (0..rule_count)
+                    .filter(|&index| totals[index] > 0.0)
+                    .find(|&index| {
+                        target -= totals[index];
+                        target < 0.0
+                    })
+                    // Rounding can leave the target just past the last rule
+                    .or_else(|| (0..rule_count).rev().find(|&index| totals[index] > 0.0));
+                if let Some(rule_index) = rule_index {
+                    let position = rng.below(match_index.match_count(rule_index) as u64) as usize;
+                    let selected_match = match_index.match_at(rule_index, position).expect("position is in range");
+                    return (rule_index, selected_match.clone());
+                }
+                // Every rule with matches has zero weight
+                (0..rule_count).find(|&index| match_index.match_count(index) > 0)
+            }
+        }
+        .expect("match index is not empty");
+        let selected_match = match_index.matches(rule_index).next().expect("rule has a match").clone();
+        (rule_index, selected_match)
+    }
+    
+    /// Selects a maximal set of pairwise disjoint matches, taking matches greedily
+    /// in the order given by the strategy.
+    fn select_generation(
+        strategy: &EventSelectionStrategy,
+        match_index: &MatchIndex,
+        hypergraph: &Hypergraph,
+        rng: &mut SimulationRng,
+    ) -> Vec<(usize, PatternMatch)> {
+        let candidates: Vec<(usize, &PatternMatch)> = match strategy {
+            EventSelectionStrategy::Ordered(orderings) => {
+                let mut candidates: Vec<OrderedEvent> =
+                    Self::ordered_candidates(orderings, match_index, hypergraph, rng).collect();
+                candidates.sort_by(|a, b| compare_events(orderings, a, b));
+                candidates.into_iter().map(|event| (event.rule_index, event.pattern_match)).collect()
+            }
+            EventSelectionStrategy::Random { rule_weights } => {
+                // Weighted random permutation: sort by u^(1/w) descending (Efraimidis-Spirakis).
+                // Matches of zero-weight rules are only used if no other rule matches.
+                let mut keyed: Vec<(f64, usize, &PatternMatch)> = (0..match_index.rule_count())
+                    .flat_map(|rule_index| match_index.matches(rule_index).map(move |m| (rule_index, m)))
+                    .map(|(rule_index, m)| {
+                        let weight = EventSelectionStrategy::rule_weight(rule_weights, rule_index);
+                        let key = if weight > 0.0 { rng.next_f64().powf(1.0 / weight) } else { -1.0 };
+                        (key, rule_index, m)
+                    })
+                    .collect();
+                if keyed.iter().any(|(key, _, _)| *key >= 0.0) {
+                    keyed.retain(|(key, _, _)| *key >= 0.0);
+                }
+                keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+                keyed.into_iter().map(|(_, rule_index, m)| (rule_index, m)).collect()
+            }
+            _ => {
+                let mut rule_order: Vec<usize> = (0..match_index.rule_count()).collect();
+                if *strategy == EventSelectionStrategy::MostMatches {
+                    // Stable, so ties keep rule order
+                    rule_order.sort_by_key(|&index| std::cmp::Reverse(match_index.match_count(index)));
+                }
+                rule_order
+                    .into_iter()
+                    .flat_map(|rule_index| match_index.matches(rule_index).map(move |m| (rule_index, m)))
+                    .collect()
+            }
+        };
+        
+        let mut used = HashSet::new();
+        let mut selected = Vec::new();
+        for (rule_index, pattern_match) in candidates {
+            if pattern_match.matched_relations.iter().any(|relation| used.contains(relation)) {
+                continue;
             }
+            used.extend(pattern_match.matched_relations.iter().copied());
+            selected.push((rule_index, pattern_match.clone()));
         }
+        selected
+    }
+    
+    /// Prepares each indexed match for comparison under the given orderings, as it is
+    /// iterated. Random keys are only drawn when the orderings use them.
+    fn ordered_candidates<'a>(
+        orderings: &[EventOrdering],
+        match_index: &'a MatchIndex,
+        hypergraph: &'a Hypergraph,
+        rng: &'a mut SimulationRng,
+    ) -> impl Iterator<Item = OrderedEvent<'a>> + 'a {
+        let random = orderings.contains(&EventOrdering::Random);
+        (0..match_index.rule_count())
+            .flat_map(|rule_index| match_index.matches(rule_index).map(move |m| (rule_index, m)))
+            .map(move |(rule_index, m)| {
+                let random_key = if random { rng.next_u64() } else { 0 };
+                OrderedEvent::new(rule_index, m, hypergraph, random_key)
+            })
     }
 }
 
@@ -392,8 +1023,6 @@ impl Default for SimulationManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hypergraph::{Atom, Relation};
-    use crate::rules::Rule;
 
     #[test]
     fn test_simulation_manager_creation() {
@@ -471,6 +1100,241 @@ mod tests {
         assert_eq!(manager.step_number(), 3);
     }
     
+    #[test]
+    fn test_external_edits_invalidate_match_index() {
+        let mut manager = SimulationManager::new();
+        let _atom_a = manager.hypergraph_mut().create_atom();
+        
+        // Builds an empty index
+        assert!(!manager.step().success);
+        
+        // Relations added from outside must be picked up by the next step
+        let atom_b = manager.hypergraph_mut().create_atom();
+        let atom_c = manager.hypergraph_mut().create_atom();
+        manager.hypergraph_mut().create_relation(vec![atom_b, atom_c]);
+        assert!(manager.step().success);
+    }
+    
+    #[test]
+    fn test_ordered_strategies() {
+        // a -> b -> c, with a -> b the older edge
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<_> = (0..3).map(|_| hypergraph.create_atom()).collect();
+        hypergraph.create_relation(vec![atoms[0], atoms[1]]);
+        let second = hypergraph.create_relation(vec![atoms[1], atoms[2]]);
+        let rule_set = RuleSet::create_basic_ruleset();
+        
+        // The standard ordering keeps rewriting the least recent edge
+        let mut manager = SimulationManager::with_hypergraph_and_rules(hypergraph.clone(), rule_set.clone());
+        manager.set_event_selection_strategy(EventSelectionStrategy::wolfram_standard());
+        manager.step();
+        let event = manager.step().event.unwrap();
+        assert_eq!(event.relations_removed(), &[second]);
+        
+        // NewestEdge rewrites the edge created by the previous event
+        let mut manager = SimulationManager::with_hypergraph_and_rules(hypergraph, rule_set);
+        manager.set_event_selection_strategy(EventSelectionStrategy::Ordered(vec![EventOrdering::NewestEdge]));
+        let first = manager.step().event.unwrap();
+        let event = manager.step().event.unwrap();
+        assert_eq!(event.relations_removed(), &first.relations_created()[1..]);
+    }
+    
+    #[test]
+    fn test_random_selection_is_reproducible() {
+        let run = |seed: u64| {
+            let mut manager = SimulationManager::new();
+            let atom_a = manager.hypergraph_mut().create_atom();
+            let atom_b = manager.hypergraph_mut().create_atom();
+            manager.hypergraph_mut().create_relation(vec![atom_a, atom_b]);
+            manager.set_event_selection_strategy(EventSelectionStrategy::uniform_random());
+            manager.set_random_seed(seed);
+            manager.step_multiple(20);
+            manager
+        };
+        
+        let a = run(7);
+        assert_eq!(a.get_current_state(), run(7).get_current_state());
+        assert_ne!(a.causal_graph(), run(8).causal_graph());
+        
+        // The generator state travels with the saved state, so a reloaded run continues identically
+        let mut continued = run(7);
+        let mut reloaded = SimulationManager::from_state(&continued.get_current_state(), RuleSet::create_basic_ruleset()).unwrap();
+        reloaded.set_event_selection_strategy(EventSelectionStrategy::uniform_random());
+        assert_eq!(reloaded.random_seed(), 7);
+        continued.step_multiple(5);
+        reloaded.step_multiple(5);
+        assert_eq!(continued.get_current_state(), reloaded.get_current_state());
+    }
+    
+    #[test]
+    fn test_random_rule_weights() {
+        // Two rules always match; a zero weight disables the first
+        let rule_set = crate::rules::parse_rule_set("{{{x,y}} -> {{x,y},{y,z}}, {{x,y}} -> {{y,x}}}").unwrap();
+        let mut hypergraph = Hypergraph::new();
+        let atom_a = hypergraph.create_atom();
+        let atom_b = hypergraph.create_atom();
+        hypergraph.create_relation(vec![atom_a, atom_b]);
+        let mut manager = SimulationManager::with_hypergraph_and_rules(hypergraph, rule_set);
+        manager.set_event_selection_strategy(EventSelectionStrategy::Random { rule_weights: vec![0.0, 1.0] });
+        
+        let results = manager.step_multiple(10);
+        assert!(results.iter().all(|r| r.event.as_ref().unwrap().rule_id().value() == 1));
+        assert_eq!(manager.hypergraph().relation_count(), 1);
+        
+        let result = manager.step_generation();
+        assert_eq!(result.events.len(), 1);
+        assert_eq!(result.events[0].rule_id().value(), 1);
+    }
+    
+    #[test]
+    fn test_undo_and_redo_restore_exact_state() {
+        let mut manager = SimulationManager::new();
+        let atom_a = manager.hypergraph_mut().create_atom();
+        let atom_b = manager.hypergraph_mut().create_atom();
+        manager.hypergraph_mut().create_relation(vec![atom_a, atom_b]);
+        manager.set_event_selection_strategy(EventSelectionStrategy::uniform_random());
+        manager.set_random_seed(3);
+        
+        let snapshot = |manager: &SimulationManager| {
+            (manager.hypergraph().clone(), manager.get_current_state(), manager.causal_graph().clone())
+        };
+        let initial = snapshot(&manager);
+        manager.step_multiple(5);
+        let after_five = snapshot(&manager);
+        manager.step_multiple(3);
+        let after_eight = snapshot(&manager);
+        
+        let undone = manager.undo(3);
+        assert_eq!(undone.iter().map(|e| e.step_number()).collect::<Vec<_>>(), vec![8, 7, 6]);
+        assert_eq!(snapshot(&manager), after_five);
+        
+        // Undo stops at the start of the history
+        assert_eq!(manager.undo(10).len(), 5);
+        assert_eq!(snapshot(&manager), initial);
+        
+        assert_eq!(manager.redo(10).len(), 8);
+        assert_eq!(snapshot(&manager), after_eight);
+        
+        // The restored generator continues the run exactly as before
+        manager.undo(1);
+        let replayed = manager.step().event.unwrap();
+        assert_eq!(snapshot(&manager), after_eight);
+        assert_eq!(replayed.step_number(), 8);
+        
+        // A new step discards the redo stack
+        manager.undo(2);
+        manager.step();
+        assert!(manager.redo(1).is_empty());
+    }
+    
+    #[test]
+    fn test_undo_restores_indices_of_new_arity() {
+        let rule_set = crate::rules::parse_rule_set("{{x,y}} -> {{x,y,z}}").unwrap();
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<_> = (0..2).map(|_| hypergraph.create_atom()).collect();
+        hypergraph.create_relation(vec![atoms[0], atoms[1]]);
+        let mut manager = SimulationManager::with_hypergraph_and_rules(hypergraph, rule_set);
+        let before = manager.hypergraph().clone();
+        
+        manager.step();
+        assert_eq!(manager.hypergraph().arity_count(3), 1);
+        manager.undo(1);
+        assert!(manager.hypergraph() == &before);
+    }
+    
+    #[test]
+    fn test_undo_generations() {
+        let mut manager = SimulationManager::new();
+        let atoms: Vec<_> = (0..3).map(|_| manager.hypergraph_mut().create_atom()).collect();
+        manager.hypergraph_mut().create_relation(vec![atoms[0], atoms[1]]);
+        manager.hypergraph_mut().create_relation(vec![atoms[1], atoms[2]]);
+        let initial = manager.get_current_state();
+        
+        manager.step_generations(2);
+        let final_state = manager.get_current_state();
+        assert_eq!(manager.history().undo_count(), 6);
+        
+        // Undoing part of a generation rewinds the generation counter
+        manager.undo(1);
+        assert_eq!(manager.generation(), 1);
+        manager.undo(5);
+        assert_eq!(manager.generation(), 0);
+        assert_eq!(manager.get_current_state(), initial);
+        
+        manager.redo(6);
+        assert_eq!(manager.generation(), 2);
+        assert_eq!(manager.get_current_state(), final_state);
+    }
+    
+    #[test]
+    fn test_event_retention_bounds_stored_events() {
+        let mut manager = SimulationManager::from_state(&crate::serialization::PredefinedExamples::single_edge(), RuleSet::create_basic_ruleset()).unwrap();
+        assert_eq!(manager.event_retention(), Some(DEFAULT_EVENT_RETENTION));
+        manager.set_event_retention(Some(16));
+        manager.set_timeline(Timeline::new(4));
+        
+        manager.step_multiple(100);
+        let before = manager.get_current_state();
+        assert!(manager.history().undo_count() <= 16);
+        assert!(manager.causal_graph().event_count() <= 16);
+        assert!(manager.timeline().events().len() <= 16);
+        assert_eq!(manager.causal_graph().find_by_step(100).map(|id| id.value()), Some(99));
+        assert!(manager.state_at_step(90).is_ok());
+        assert!(manager.state_at_step(50).is_err());
+        
+        // Undo stops at the oldest event kept
+        let kept = manager.history().undo_count();
+        assert_eq!(manager.undo(100).len(), kept);
+        assert_eq!(manager.step_number(), 100 - kept as u64);
+        assert_eq!(manager.redo(100).len(), kept);
+        assert_eq!(manager.get_current_state(), before);
+    }
+    
+    #[test]
+    fn test_generation_step() {
+        let mut manager = SimulationManager::new();
+        
+        // A path of three edges: every edge splits in the first generation
+        let atoms: Vec<_> = (0..4).map(|_| manager.hypergraph_mut().create_atom()).collect();
+        for pair in atoms.windows(2) {
+            manager.hypergraph_mut().create_relation(pair.to_vec());
+        }
+        
+        let result = manager.step_generation();
+        assert!(result.success);
+        assert_eq!(result.generation, 1);
+        assert_eq!(result.events.len(), 3);
+        assert_eq!(manager.step_number(), 3);
+        assert_eq!(manager.hypergraph().relation_count(), 6);
+        
+        // Each generation doubles the edges
+        let results = manager.step_generations(2);
+        assert_eq!(results.iter().map(|r| r.events.len()).collect::<Vec<_>>(), vec![6, 12]);
+        assert_eq!(manager.generation(), 3);
+        assert_eq!(manager.hypergraph().relation_count(), 24);
+    }
+    
+    #[test]
+    fn test_generation_uses_disjoint_matches() {
+        // {{x,y},{y,z}} on a path of three edges overlaps: only one match fits per generation
+        let rule_set = crate::rules::parse_rule_set("{{x,y},{y,z}} -> {{x,z}}").unwrap();
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<_> = (0..4).map(|_| hypergraph.create_atom()).collect();
+        for pair in atoms.windows(2) {
+            hypergraph.create_relation(pair.to_vec());
+        }
+        let mut manager = SimulationManager::with_hypergraph_and_rules(hypergraph, rule_set);
+        
+        let result = manager.step_generation();
+        assert_eq!(result.events.len(), 1);
+        assert_eq!(manager.hypergraph().relation_count(), 2);
+        
+        manager.step_generation();
+        let result = manager.step_generation();
+        assert!(!result.success);
+        assert_eq!(result.generation, 2);
+    }
+    
     #[test]
     fn test_continuous_simulation() {
         let mut manager = SimulationManager::new();
@@ -495,6 +1359,51 @@ mod tests {
         assert_eq!(manager.step_number(), 5);
     }
     
+    #[test]
+    fn test_event_log_recovers_continuous_run() {
+        use crate::serialization::{EventLogConfig, EventLogHeader, EventLogReader, EventLogWriter, PredefinedExamples};
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("events.jsonl");
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_event_selection_strategy(EventSelectionStrategy::uniform_random());
+        manager.set_random_seed(11);
+        let header = EventLogHeader::capture(&manager);
+        manager.set_event_log(Some(EventLogWriter::open(&path, header, EventLogConfig::default()).unwrap()));
+        
+        manager.run_continuous(ContinuousSimulationConfig { max_steps: Some(20), ..Default::default() });
+        manager.undo(5);
+        manager.run_continuous(ContinuousSimulationConfig { max_steps: Some(15), ..Default::default() });
+        
+        // The log alone is enough to rebuild the final state after the run
+        let recovered = EventLogReader::recover(&path).unwrap();
+        assert_eq!(recovered.step_number(), 30);
+        assert_eq!(recovered.event_selection_strategy(), &EventSelectionStrategy::uniform_random());
+        assert_eq!(recovered.hypergraph(), manager.hypergraph());
+    }
+    
+    #[test]
+    fn test_failed_checkpoints_are_reported_as_warnings() {
+        use crate::serialization::{PersistenceManager, PredefinedExamples};
+        
+        // A store whose directory is a file cannot save anything
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let blocked = temp_dir.path().join("not_a_directory");
+        std::fs::write(&blocked, "").unwrap();
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_timeline(Timeline::with_persistence(2, PersistenceManager::with_save_directory(&blocked)));
+        
+        let result = manager.step();
+        assert!(result.success);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].starts_with("Failed to checkpoint step 0"));
+        assert!(manager.take_warnings().is_empty());
+        
+        // Without a first checkpoint, every step tries again
+        let run = manager.run_continuous(ContinuousSimulationConfig { max_steps: Some(4), ..Default::default() });
+        assert_eq!(run.warnings.len(), 4);
+    }
+    
     #[test]
     fn test_get_and_load_state() {
         let mut manager = SimulationManager::new();
@@ -525,6 +1434,46 @@ mod tests {
         assert_eq!(manager.hypergraph().relation_count(), 2);
     }
     
+    #[test]
+    fn test_causal_graph_updates_each_step() {
+        let mut manager = SimulationManager::new();
+        let atom_a = manager.hypergraph_mut().create_atom();
+        let atom_b = manager.hypergraph_mut().create_atom();
+        manager.hypergraph_mut().create_relation(vec![atom_a, atom_b]);
+        
+        manager.step_multiple(3);
+        
+        // The first event consumes an initial relation; every later one consumes
+        // a relation created by an earlier event
+        let graph = manager.causal_graph();
+        assert_eq!(graph.event_count(), 3);
+        assert_eq!(graph.edges().len(), 2);
+        assert!(graph.parents(crate::causal::CausalEventId(0)).is_empty());
+        
+        manager.reset();
+        assert!(manager.causal_graph().is_empty());
+    }
+    
+    #[test]
+    fn test_explore_multiway() {
+        let mut manager = SimulationManager::new();
+        let atom_a = manager.hypergraph_mut().create_atom();
+        let atom_b = manager.hypergraph_mut().create_atom();
+        let atom_c = manager.hypergraph_mut().create_atom();
+        manager.hypergraph_mut().create_relation(vec![atom_a, atom_b]);
+        manager.hypergraph_mut().create_relation(vec![atom_b, atom_c]);
+        
+        let config = MultiwayConfig { max_depth: 2, ..Default::default() };
+        let system = manager.explore_multiway(config);
+        
+        // Both edges branch from the root (into isomorphic states), and the manager itself is untouched
+        assert_eq!(system.successors(system.root()).count(), 2);
+        assert_eq!(system.states_at_depth(1).count(), 1);
+        assert_eq!(system.depth(), 2);
+        assert_eq!(manager.step_number(), 0);
+        assert_eq!(manager.hypergraph().relation_count(), 2);
+    }
+    
     #[test]
     fn test_event_selection_strategies() {
         let mut manager = SimulationManager::new();
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+
+use crate::hypergraph::Hypergraph;
+use super::event::SimulationEvent;
+
+/// Number of samples a recorder keeps unless configured otherwise.
+pub const DEFAULT_MAX_SAMPLES: usize = 10_000;
+
+/// Metric values by name. Distributions are flattened into one value per bucket,
+/// such as `degree.3` for the number of atoms in three relations.
+pub type MetricValues = BTreeMap<String, f64>;
+
+/// What a collector sees after a step.
+#[derive(Debug, Clone, Copy)]
+pub struct StepContext<'a> {
+    /// Step number after the step
+    pub step_number: u64,
+
+    /// The hypergraph after the step
+    pub hypergraph: &'a Hypergraph,
+
+    /// The event the step applied
+    pub event: &'a SimulationEvent,
+
+    /// Wall time the step took
+    pub duration: Duration,
+}
+
+/// Computes metrics of a running simulation.
+pub trait MetricsCollector: fmt::Debug + Send {
+    /// Called after every step, for collectors that accumulate between samples.
+    fn observe(&mut self, _context: &StepContext<'_>) {}
+
+    /// Adds this collector's values to a sample taken after the step in `context`.
+    fn collect(&mut self, context: &StepContext<'_>, values: &mut MetricValues);
+}
+
+/// Metric values collected after one step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricSample {
+    /// Step number the values were collected at
+    pub step_number: u64,
+
+    /// Collected values by name
+    pub values: MetricValues,
+}
+
+/// Time series of metric samples in step order.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MetricSeries {
+    /// Samples in the order they were collected
+    pub samples: Vec<MetricSample>,
+}
+
+impl MetricSeries {
+    /// Returns every metric name in the series, sorted.
+    pub fn metric_names(&self) -> Vec<&str> {
+        let names: BTreeSet<&str> = self.samples.iter().flat_map(|s| s.values.keys().map(String::as_str)).collect();
+        names.into_iter().collect()
+    }
+
+    /// Writes the series as CSV with a `step_number` column followed by one column per
+    /// metric. Metrics missing from a sample, such as an unused degree, are left empty.
+    pub fn to_csv(&self) -> String {
+        let names = self.metric_names();
+        let mut csv = String::from("step_number");
+        for name in &names {
+            csv.push(',');
+            csv.push_str(name);
+        }
+        csv.push('\n');
+        for sample in &self.samples {
+            csv.push_str(&sample.step_number.to_string());
+            for name in &names {
+                csv.push(',');
+                if let Some(value) = sample.values.get(*name) {
+                    csv.push_str(&value.to_string());
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Writes the series as JSON.
+    pub fn to_json(&self, pretty_print: bool) -> serde_json::Result<String> {
+        if pretty_print {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        }
+    }
+}
+
+/// Runs a set of collectors after each step and keeps a sample every `interval` steps.
+/// Only the latest `max_samples` samples are kept; older ones are dropped.
+#[derive(Debug)]
+pub struct MetricsRecorder {
+    collectors: Vec<Box<dyn MetricsCollector>>,
+    interval: u64,
+    max_samples: usize,
+    /// Samples taken since the recorder was created, including dropped ones
+    samples_taken: u64,
+    series: MetricSeries,
+}
+
+impl MetricsRecorder {
+    /// Creates a recorder without collectors that samples every `interval` steps (0 = every step).
+    pub fn new(interval: u64) -> Self {
+        MetricsRecorder {
+            collectors: Vec::new(),
+            interval: interval.max(1),
+            max_samples: DEFAULT_MAX_SAMPLES,
+            samples_taken: 0,
+            series: MetricSeries::default(),
+        }
+    }
+
+    /// Keeps at most `max_samples` samples (at least one), dropping the oldest.
+    pub fn with_max_samples(mut self, max_samples: usize) -> Self {
+        self.max_samples = max_samples.max(1);
+        self
+    }
+
+    /// Creates a recorder with every built-in collector.
+    pub fn with_default_collectors(interval: u64) -> Self {
+        let mut recorder = Self::new(interval);
+        recorder.add_collector(Box::new(CountCollector));
+        recorder.add_collector(Box::new(DegreeDistributionCollector));
+        recorder.add_collector(Box::new(ArityHistogramCollector));
+        recorder.add_collector(Box::new(RuleUsageCollector::default()));
+        recorder.add_collector(Box::new(StepTimeCollector::default()));
+        recorder
+    }
+
+    /// Adds a collector.
+    pub fn add_collector(&mut self, collector: Box<dyn MetricsCollector>) {
+        self.collectors.push(collector);
+    }
+
+    /// Returns the number of steps between samples.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Returns the largest number of samples kept.
+    pub fn max_samples(&self) -> usize {
+        self.max_samples
+    }
+
+    /// Returns the number of samples taken, including those since dropped.
+    pub fn samples_taken(&self) -> u64 {
+        self.samples_taken
+    }
+
+    /// Returns the number of samples dropped to stay within `max_samples`.
+    pub fn dropped_samples(&self) -> u64 {
+        self.samples_taken - self.series.samples.len() as u64
+    }
+
+    /// Returns the samples kept, oldest first.
+    pub fn series(&self) -> &MetricSeries {
+        &self.series
+    }
+
+    /// Returns the most recent sample.
+    pub fn last_sample(&self) -> Option<&MetricSample> {
+        self.series.samples.last()
+    }
+
+    /// Passes a step to every collector, taking a sample if one is due.
+    pub fn record_step(&mut self, context: &StepContext<'_>) {
+        for collector in &mut self.collectors {
+            collector.observe(context);
+        }
+        if !context.step_number.is_multiple_of(self.interval) {
+            return;
+        }
+
+        let mut values = MetricValues::new();
+        for collector in &mut self.collectors {
+            collector.collect(context, &mut values);
+        }
+        self.series.samples.push(MetricSample { step_number: context.step_number, values });
+        self.samples_taken += 1;
+        if self.series.samples.len() > self.max_samples {
+            // Drop a batch at a time, so the shift is not paid on every sample
+            let excess = self.series.samples.len() - self.max_samples;
+            let batch = (self.max_samples / 8).max(excess).min(self.series.samples.len() - 1);
+            self.series.samples.drain(..batch);
+        }
+    }
+}
+
+/// Collects `atom_count` and `relation_count`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountCollector;
+
+impl MetricsCollector for CountCollector {
+    fn collect(&mut self, context: &StepContext<'_>, values: &mut MetricValues) {
+        values.insert("atom_count".to_string(), context.hypergraph.atom_count() as f64);
+        values.insert("relation_count".to_string(), context.hypergraph.relation_count() as f64);
+    }
+}
+
+/// Collects `degree.{d}`, the number of atoms in `d` relations, and `max_degree`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DegreeDistributionCollector;
+
+impl MetricsCollector for DegreeDistributionCollector {
+    fn collect(&mut self, context: &StepContext<'_>, values: &mut MetricValues) {
+        let mut max_degree = 0;
+        for &atom in context.hypergraph.atom_ids() {
+            let degree = context.hypergraph.atom_degree(atom);
+            max_degree = max_degree.max(degree);
+            *values.entry(format!("degree.{}", degree)).or_default() += 1.0;
+        }
+        values.insert("max_degree".to_string(), max_degree as f64);
+    }
+}
+
+/// Collects `arity.{k}`, the number of relations with `k` atoms.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArityHistogramCollector;
+
+impl MetricsCollector for ArityHistogramCollector {
+    fn collect(&mut self, context: &StepContext<'_>, values: &mut MetricValues) {
+        for relation in context.hypergraph.relations() {
+            *values.entry(format!("arity.{}", relation.arity())).or_default() += 1.0;
+        }
+    }
+}
+
+/// Collects `rule_usage.{id}`, how many steps since the recorder was attached applied each rule.
+#[derive(Debug, Clone, Default)]
+pub struct RuleUsageCollector {
+    counts: BTreeMap<u64, u64>,
+}
+
+impl MetricsCollector for RuleUsageCollector {
+    fn observe(&mut self, context: &StepContext<'_>) {
+        *self.counts.entry(context.event.rule_id().value()).or_default() += 1;
+    }
+
+    fn collect(&mut self, _context: &StepContext<'_>, values: &mut MetricValues) {
+        for (rule_id, count) in &self.counts {
+            values.insert(format!("rule_usage.{}", rule_id), *count as f64);
+        }
+    }
+}
+
+/// Collects `step_time_ms`, the mean wall time of the steps since the previous sample.
+#[derive(Debug, Clone, Default)]
+pub struct StepTimeCollector {
+    total: Duration,
+    steps: u32,
+}
+
+impl MetricsCollector for StepTimeCollector {
+    fn observe(&mut self, context: &StepContext<'_>) {
+        self.total += context.duration;
+        self.steps += 1;
+    }
+
+    fn collect(&mut self, _context: &StepContext<'_>, values: &mut MetricValues) {
+        let mean = self.total.as_secs_f64() * 1000.0 / self.steps.max(1) as f64;
+        values.insert("step_time_ms".to_string(), mean);
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::rule::RuleSet;
+    use crate::serialization::PredefinedExamples;
+    use crate::simulation::{ContinuousSimulationConfig, SimulationManager};
+
+    #[test]
+    fn test_run_continuous_collects_samples() {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_metrics_recorder(Some(MetricsRecorder::with_default_collectors(5)));
+
+        let config = ContinuousSimulationConfig { max_steps: Some(12), ..Default::default() };
+        let result = manager.run_continuous(config);
+        assert_eq!(result.metrics.iter().map(|s| s.step_number).collect::<Vec<_>>(), vec![5, 10]);
+
+        let sample = &result.metrics[1];
+        assert_eq!(sample.values["atom_count"], 13.0);
+        assert_eq!(sample.values["relation_count"], 13.0);
+        assert_eq!(sample.values["arity.2"], 13.0);
+        assert_eq!(sample.values["degree.2"], 13.0);
+        assert_eq!(sample.values["rule_usage.0"], 10.0);
+        assert!(sample.values["step_time_ms"] >= 0.0);
+    }
+
+    #[test]
+    fn test_csv_and_json_export() {
+        let mut series = MetricSeries::default();
+        series.samples.push(MetricSample {
+            step_number: 1,
+            values: MetricValues::from([("atom_count".to_string(), 4.0), ("degree.1".to_string(), 2.0)]),
+        });
+        series.samples.push(MetricSample {
+            step_number: 2,
+            values: MetricValues::from([("atom_count".to_string(), 5.5)]),
+        });
+
+        assert_eq!(series.to_csv(), "step_number,atom_count,degree.1\n1,4,2\n2,5.5,\n");
+        let json = series.to_json(false).unwrap();
+        assert_eq!(serde_json::from_str::<MetricSeries>(&json).unwrap(), series);
+    }
+
+    #[test]
+    fn test_retained_samples_are_capped() {
+        let mut manager = SimulationManager::from_state(&PredefinedExamples::triangle(), RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_metrics_recorder(Some(MetricsRecorder::new(1).with_max_samples(8)));
+
+        let config = ContinuousSimulationConfig { max_steps: Some(30), ..Default::default() };
+        let result = manager.run_continuous(config);
+        let recorder = manager.metrics_recorder().unwrap();
+        assert!(recorder.series().samples.len() <= 8);
+        assert_eq!(recorder.samples_taken(), 30);
+        assert_eq!(recorder.dropped_samples(), 30 - recorder.series().samples.len() as u64);
+        assert_eq!(recorder.last_sample().unwrap().step_number, 30);
+
+        // The run's result holds the samples that were kept
+        assert_eq!(result.metrics, recorder.series().samples);
+    }
+}
@@ -1,5 +1,17 @@
 pub mod manager;
 pub mod event;
+pub mod multiway;
+pub mod ordering;
+pub mod random;
+pub mod history;
+pub mod timeline;
+pub mod metrics;
 
 pub use manager::*;
-pub use event::*; 
\ No newline at end of file
+pub use event::*;
+pub use multiway::*;
+pub use ordering::{EventOrdering, OrderedEvent, compare_events};
+pub use random::SimulationRng;
+pub use history::{EventHistory, HistoryMarker, InvertibleEvent};
+pub use timeline::{Timeline, CHECKPOINT_KEY_PREFIX, DEFAULT_MAX_CHECKPOINTS};
+pub use metrics::{DEFAULT_MAX_SAMPLES, MetricSample, MetricSeries, MetricValues, MetricsCollector, MetricsRecorder, StepContext};
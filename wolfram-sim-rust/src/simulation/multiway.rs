@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use crate::hypergraph::{canonical_labeling, AtomId, CanonicalForm, Hypergraph, RelationId};
+use crate::rules::rule::RuleSet;
+use crate::matching::find_pattern_matches;
+use crate::evolution::{apply_rule, RewriteResult};
+use super::event::{SimulationEvent, HypergraphState};
+
+/// Identifies a state in a multiway system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MultiwayStateId(pub usize);
+
+impl MultiwayStateId {
+    /// Returns the inner value of the MultiwayStateId.
+    pub fn value(&self) -> usize {
+        self.0
+    }
+}
+
+/// Configuration for multiway exploration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiwayConfig {
+    /// Maximum number of generations to explore from the initial state
+    pub max_depth: u64,
+
+    /// Maximum number of new states created in a single generation (None for unlimited)
+    pub max_breadth: Option<usize>,
+
+    /// Merge states that are equal up to relabelling of atoms into a single node
+    pub merge_identical_states: bool,
+}
+
+impl Default for MultiwayConfig {
+    fn default() -> Self {
+        MultiwayConfig {
+            max_depth: 5,
+            max_breadth: Some(1000), // Default limit to prevent combinatorial explosion
+            merge_identical_states: true,
+        }
+    }
+}
+
+/// A node in the multiway graph: one possible hypergraph state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiwayState {
+    /// The ID of this state
+    pub id: MultiwayStateId,
+
+    /// The hypergraph at this state
+    pub hypergraph: Hypergraph,
+
+    /// Generation at which this state was first reached
+    pub depth: u64,
+}
+
+impl MultiwayState {
+    /// Returns the ID of this state.
+    pub fn id(&self) -> MultiwayStateId {
+        self.id
+    }
+
+    /// Returns the hypergraph at this state.
+    pub fn hypergraph(&self) -> &Hypergraph {
+        &self.hypergraph
+    }
+
+    /// Returns the generation at which this state was first reached.
+    pub fn depth(&self) -> u64 {
+        self.depth
+    }
+
+    /// Returns this state as a transmittable hypergraph state, using the depth as step number.
+    pub fn to_hypergraph_state(&self) -> HypergraphState {
+        HypergraphState::new(
+            self.hypergraph.get_all_atoms(),
+            self.hypergraph.get_all_relations(),
+            self.depth,
+            self.hypergraph.next_atom_id(),
+            self.hypergraph.next_relation_id(),
+        )
+    }
+}
+
+/// An edge in the multiway graph, labelled by the event that produced the target state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiwayEdge {
+    /// The state the event was applied to
+    pub from: MultiwayStateId,
+
+    /// The state produced by the event
+    pub to: MultiwayStateId,
+
+    /// The event that transformed `from` into `to`. Removed relations are those of
+    /// `from`, and created atoms and relations those of `to`; when the event's result
+    /// merged into an existing state, they are translated through the isomorphism.
+    pub event: SimulationEvent,
+}
+
+/// Reasons why multiway exploration might stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiwayStopReason {
+    /// Reached the configured maximum depth
+    MaxDepthReached,
+
+    /// No state in the frontier has any applicable rule
+    FixedPointReached,
+
+    /// Every state produced by the last generation merged into an earlier state
+    AllStatesMerged,
+
+    /// The breadth limit dropped every new state of the last generation
+    BreadthLimitReached,
+}
+
+/// A multiway system: every applicable match is applied to a copy of each state,
+/// building a graph of states whose edges are the events between them.
+#[derive(Debug, Clone)]
+pub struct MultiwaySystem {
+    /// The rules applied at every state
+    rule_set: RuleSet,
+
+    /// Exploration limits
+    config: MultiwayConfig,
+
+    /// All states discovered so far, indexed by their IDs
+    states: Vec<MultiwayState>,
+
+    /// All edges discovered so far
+    edges: Vec<MultiwayEdge>,
+
+    /// Index from canonical form to state ID, used to merge isomorphic states
+    state_index: HashMap<CanonicalForm, MultiwayStateId>,
+
+    /// States reached in the latest generation that have not been expanded yet
+    frontier: Vec<MultiwayStateId>,
+
+    /// Number of generations explored so far
+    depth: u64,
+
+    /// Whether any generation was cut short by the breadth limit
+    truncated: bool,
+
+    /// Why the latest generation left the frontier empty, if it did
+    exhausted: Option<MultiwayStopReason>,
+}
+
+impl MultiwaySystem {
+    /// Creates a new multiway system rooted at the given hypergraph.
+    pub fn new(initial: Hypergraph, rule_set: RuleSet, config: MultiwayConfig) -> Self {
+        let mut system = MultiwaySystem {
+            rule_set,
+            config,
+            states: Vec::new(),
+            edges: Vec::new(),
+            state_index: HashMap::new(),
+            frontier: Vec::new(),
+            depth: 0,
+            truncated: false,
+            exhausted: None,
+        };
+        let root = system.add_state(initial, 0);
+        system.frontier.push(root);
+        system
+    }
+
+    fn add_state(&mut self, hypergraph: Hypergraph, depth: u64) -> MultiwayStateId {
+        let id = MultiwayStateId(self.states.len());
+        if self.config.merge_identical_states {
+            self.state_index.insert(hypergraph.canonical_form(), id);
+        }
+        self.states.push(MultiwayState { id, hypergraph, depth });
+        id
+    }
+
+    /// Expands the frontier by one generation.
+    /// Returns the number of new states created.
+    pub fn step(&mut self) -> usize {
+        let frontier = std::mem::take(&mut self.frontier);
+        // Taken out for the duration of the step so new states can be added while iterating rules
+        let rule_set = std::mem::take(&mut self.rule_set);
+        let next_depth = self.depth + 1;
+        let mut new_states = 0;
+        let mut applied = false;
+        let mut dropped = false;
+
+        for parent_id in frontier {
+            for rule in rule_set.iter() {
+                let parent = &self.states[parent_id.0].hypergraph;
+                let matches = find_pattern_matches(rule.pattern(), parent);
+
+                for pattern_match in &matches {
+                    let mut child = self.states[parent_id.0].hypergraph.clone();
+                    let mut rewrite_result = apply_rule(&mut child, rule, pattern_match);
+                    if !rewrite_result.success {
+                        continue;
+                    }
+                    applied = true;
+
+                    let existing = if self.config.merge_identical_states {
+                        self.state_index.get(&child.canonical_form()).copied()
+                    } else {
+                        None
+                    };
+
+                    let child_id = match existing {
+                        Some(id) => {
+                            translate_created(&child, &self.states[id.0].hypergraph, &mut rewrite_result);
+                            id
+                        }
+                        None => {
+                            if self.config.max_breadth.is_some_and(|max| new_states >= max) {
+                                self.truncated = true;
+                                dropped = true;
+                                continue;
+                            }
+                            new_states += 1;
+                            let id = self.add_state(child, next_depth);
+                            self.frontier.push(id);
+                            id
+                        }
+                    };
+
+                    let event = SimulationEvent::with_description(
+                        next_depth,
+                        rule.id(),
+                        rewrite_result.new_atoms,
+                        rewrite_result.new_relations,
+                        rewrite_result.removed_relations,
+                        format!(
+                            "Applied rule {} to state {} producing state {}",
+                            rule.id().value(),
+                            parent_id.value(),
+                            child_id.value()
+                        ),
+                    );
+                    self.edges.push(MultiwayEdge { from: parent_id, to: child_id, event });
+                }
+            }
+        }
+
+        self.rule_set = rule_set;
+        self.depth = next_depth;
+        self.exhausted = self.frontier.is_empty().then_some(if !applied {
+            MultiwayStopReason::FixedPointReached
+        } else if dropped {
+            MultiwayStopReason::BreadthLimitReached
+        } else {
+            MultiwayStopReason::AllStatesMerged
+        });
+        new_states
+    }
+
+    /// Explores generations until the maximum depth is reached or no new states appear,
+    /// returning why the frontier ran out in the latter case.
+    pub fn evolve(&mut self) -> MultiwayStopReason {
+        while self.depth < self.config.max_depth {
+            if let Some(reason) = self.exhausted {
+                return reason;
+            }
+            self.step();
+        }
+        MultiwayStopReason::MaxDepthReached
+    }
+
+    /// Returns the ID of the initial state.
+    pub fn root(&self) -> MultiwayStateId {
+        MultiwayStateId(0)
+    }
+
+    /// Returns a state by its ID.
+    pub fn state(&self, id: MultiwayStateId) -> Option<&MultiwayState> {
+        self.states.get(id.0)
+    }
+
+    /// Returns all states discovered so far.
+    pub fn states(&self) -> &[MultiwayState] {
+        &self.states
+    }
+
+    /// Returns all edges discovered so far.
+    pub fn edges(&self) -> &[MultiwayEdge] {
+        &self.edges
+    }
+
+    /// Returns the number of states discovered so far.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns the edges leaving the given state.
+    pub fn successors(&self, id: MultiwayStateId) -> impl Iterator<Item = &MultiwayEdge> {
+        self.edges.iter().filter(move |edge| edge.from == id)
+    }
+
+    /// Returns the edges entering the given state.
+    pub fn predecessors(&self, id: MultiwayStateId) -> impl Iterator<Item = &MultiwayEdge> {
+        self.edges.iter().filter(move |edge| edge.to == id)
+    }
+
+    /// Returns the states reached at the given generation.
+    pub fn states_at_depth(&self, depth: u64) -> impl Iterator<Item = &MultiwayState> {
+        self.states.iter().filter(move |state| state.depth == depth)
+    }
+
+    /// Returns the number of generations explored so far.
+    pub fn depth(&self) -> u64 {
+        self.depth
+    }
+
+    /// Returns true if the breadth limit caused any states to be dropped.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns the exploration configuration.
+    pub fn config(&self) -> &MultiwayConfig {
+        &self.config
+    }
+}
+
+/// Translates the atoms and relations a rewrite created in `child` into those of the
+/// isomorphic `target`, by matching the atoms' canonical labels.
+fn translate_created(child: &Hypergraph, target: &Hypergraph, result: &mut RewriteResult) {
+    let child_labels = canonical_labeling(child);
+    let target_atoms: HashMap<u64, AtomId> = canonical_labeling(target).into_iter().map(|(atom, label)| (label, atom)).collect();
+    let translate = |atom: &AtomId| target_atoms[&child_labels[atom]];
+
+    let mut target_relations: HashMap<Vec<AtomId>, Vec<RelationId>> = HashMap::new();
+    for relation in target.relations() {
+        target_relations.entry(relation.atoms().to_vec()).or_default().push(relation.id());
+    }
+    result.new_atoms = result.new_atoms.iter().map(translate).collect();
+    result.new_relations = result
+        .new_relations
+        .iter()
+        .filter_map(|id| child.get_relation(*id))
+        .filter_map(|relation| {
+            let atoms: Vec<AtomId> = relation.atoms().iter().map(translate).collect();
+            // Identical relations are interchangeable; each is used once
+            target_relations.get_mut(&atoms).and_then(Vec::pop)
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{parse_rule_set, RuleId};
+
+    fn single_edge() -> Hypergraph {
+        let mut hypergraph = Hypergraph::new();
+        let a = hypergraph.create_atom();
+        let b = hypergraph.create_atom();
+        hypergraph.create_relation(vec![a, b]);
+        hypergraph
+    }
+
+    fn path(length: usize) -> Hypergraph {
+        let mut hypergraph = Hypergraph::new();
+        let mut previous = hypergraph.create_atom();
+        for _ in 0..length {
+            let next = hypergraph.create_atom();
+            hypergraph.create_relation(vec![previous, next]);
+            previous = next;
+        }
+        hypergraph
+    }
+
+    #[test]
+    fn test_branches_on_every_match() {
+        let config = MultiwayConfig { merge_identical_states: false, ..Default::default() };
+        let mut system = MultiwaySystem::new(path(2), RuleSet::create_basic_ruleset(), config);
+        let created = system.step();
+
+        // Two edges can be split, giving two states
+        assert_eq!(created, 2);
+        assert_eq!(system.state_count(), 3);
+        assert_eq!(system.successors(system.root()).count(), 2);
+        for edge in system.edges() {
+            assert_eq!(edge.event.rule_id(), RuleId::new(0));
+            assert_eq!(edge.event.relations_removed().len(), 1);
+            assert_eq!(system.state(edge.to).unwrap().depth(), 1);
+        }
+    }
+
+    #[test]
+    fn test_merges_identical_states() {
+        // Reversing a single edge yields a state isomorphic to the original
+        let rule_set = parse_rule_set("{{x,y}} -> {{y,x}}").unwrap();
+        let config = MultiwayConfig { max_depth: 4, ..Default::default() };
+        let mut system = MultiwaySystem::new(single_edge(), rule_set, config.clone());
+        assert_eq!(system.evolve(), MultiwayStopReason::AllStatesMerged);
+
+        assert_eq!(system.state_count(), 1);
+        assert_eq!(system.edges().len(), 1);
+        assert_eq!(system.predecessors(system.root()).count(), 1);
+
+        // The reversed edge was created as relation 1 of a copy; in the root it is relation 0
+        let event = &system.edges()[0].event;
+        assert_eq!(event.relations_created(), &[RelationId::new(0)]);
+        assert_eq!(event.relations_removed(), &[RelationId::new(0)]);
+
+        let unmerged_config = MultiwayConfig { merge_identical_states: false, ..config };
+        let mut unmerged = MultiwaySystem::new(single_edge(), parse_rule_set("{{x,y}} -> {{y,x}}").unwrap(), unmerged_config);
+        unmerged.evolve();
+        assert_eq!(unmerged.state_count(), 5);
+    }
+
+    #[test]
+    fn test_merges_commuting_events() {
+        // A -> B with B -> C and B -> D: splitting B -> C and B -> D gives isomorphic states,
+        // and splitting A -> B and B -> C in either order meets again at depth 2
+        let mut hypergraph = Hypergraph::new();
+        let atoms: Vec<_> = (0..4).map(|_| hypergraph.create_atom()).collect();
+        hypergraph.create_relation(vec![atoms[0], atoms[1]]);
+        hypergraph.create_relation(vec![atoms[1], atoms[2]]);
+        hypergraph.create_relation(vec![atoms[1], atoms[3]]);
+
+        let config = MultiwayConfig { max_depth: 2, ..Default::default() };
+        let mut system = MultiwaySystem::new(hypergraph, RuleSet::create_basic_ruleset(), config);
+        system.evolve();
+
+        assert_eq!(system.states_at_depth(1).count(), 2);
+        assert!(system.states_at_depth(2).any(|state| {
+            let parents: std::collections::HashSet<_> = system.predecessors(state.id()).map(|edge| edge.from).collect();
+            parents.len() == 2
+        }));
+
+        // Events into merged states name atoms and relations of the state they point to
+        for edge in system.edges() {
+            let from = system.state(edge.from).unwrap().hypergraph();
+            let to = system.state(edge.to).unwrap().hypergraph();
+            assert!(edge.event.relations_removed().iter().all(|id| from.contains_relation(*id)));
+            assert!(edge.event.atoms_created().iter().all(|id| to.contains_atom(*id)));
+            assert_eq!(edge.event.relations_created().len(), 2);
+            assert!(edge.event.relations_created().iter().all(|id| to.contains_relation(*id)));
+        }
+    }
+
+    #[test]
+    fn test_depth_and_breadth_limits() {
+        let config = MultiwayConfig { max_depth: 3, max_breadth: Some(2), merge_identical_states: false };
+        let mut system = MultiwaySystem::new(path(3), RuleSet::create_basic_ruleset(), config);
+
+        assert_eq!(system.evolve(), MultiwayStopReason::MaxDepthReached);
+        assert_eq!(system.depth(), 3);
+        assert!(system.is_truncated());
+        for depth in 1..=3 {
+            assert!(system.states_at_depth(depth).count() <= 2);
+        }
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let rule_set = parse_rule_set("{{x,y,z}} -> {{x,y}}").unwrap();
+        let mut system = MultiwaySystem::new(single_edge(), rule_set, MultiwayConfig::default());
+
+        assert_eq!(system.evolve(), MultiwayStopReason::FixedPointReached);
+        assert_eq!(system.state_count(), 1);
+        assert!(system.edges().is_empty());
+    }
+
+    #[test]
+    fn test_breadth_limit_dropping_every_state() {
+        let config = MultiwayConfig { max_breadth: Some(0), ..Default::default() };
+        let mut system = MultiwaySystem::new(path(2), RuleSet::create_basic_ruleset(), config);
+
+        assert_eq!(system.evolve(), MultiwayStopReason::BreadthLimitReached);
+        assert!(system.is_truncated());
+        assert_eq!(system.state_count(), 1);
+    }
+}
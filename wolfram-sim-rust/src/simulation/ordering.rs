@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+use crate::hypergraph::{Hypergraph, RelationId};
+use crate::matching::PatternMatch;
+
+/// An event-ordering function, as in SetReplace's `"EventOrderingFunction"` option.
+/// Orderings are applied as a priority list: each one only breaks the ties left by
+/// the ones before it. Edge age is given by relation creation timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventOrdering {
+    /// Prefer events whose matched relations, sorted oldest first, are oldest
+    OldestEdge,
+
+    /// Prefer events whose matched relations, sorted oldest first, are newest
+    LeastOldEdge,
+
+    /// Prefer events whose matched relations, sorted newest first, are oldest
+    LeastRecentEdge,
+
+    /// Prefer events whose matched relations, sorted newest first, are newest
+    NewestEdge,
+
+    /// Prefer events whose matched relations, in pattern order, are oldest
+    RuleOrdering,
+
+    /// Prefer events whose matched relations, in pattern order, are newest
+    ReverseRuleOrdering,
+
+    /// Prefer events of rules earlier in the rule set
+    RuleIndex,
+
+    /// Prefer events of rules later in the rule set
+    ReverseRuleIndex,
+
+    /// Break remaining ties at random
+    Random,
+}
+
+impl EventOrdering {
+    /// All orderings, in the order SetReplace documents them.
+    pub const ALL: [EventOrdering; 9] = [
+        EventOrdering::OldestEdge,
+        EventOrdering::LeastOldEdge,
+        EventOrdering::LeastRecentEdge,
+        EventOrdering::NewestEdge,
+        EventOrdering::RuleOrdering,
+        EventOrdering::ReverseRuleOrdering,
+        EventOrdering::RuleIndex,
+        EventOrdering::ReverseRuleIndex,
+        EventOrdering::Random,
+    ];
+
+    /// The ordering `WolframModel` uses by default.
+    pub fn standard() -> Vec<EventOrdering> {
+        vec![EventOrdering::LeastRecentEdge, EventOrdering::RuleOrdering, EventOrdering::RuleIndex]
+    }
+
+    /// Returns the SetReplace name of this ordering.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventOrdering::OldestEdge => "OldestEdge",
+            EventOrdering::LeastOldEdge => "LeastOldEdge",
+            EventOrdering::LeastRecentEdge => "LeastRecentEdge",
+            EventOrdering::NewestEdge => "NewestEdge",
+            EventOrdering::RuleOrdering => "RuleOrdering",
+            EventOrdering::ReverseRuleOrdering => "ReverseRuleOrdering",
+            EventOrdering::RuleIndex => "RuleIndex",
+            EventOrdering::ReverseRuleIndex => "ReverseRuleIndex",
+            EventOrdering::Random => "Random",
+        }
+    }
+}
+
+impl fmt::Display for EventOrdering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EventOrdering {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EventOrdering::ALL
+            .into_iter()
+            .find(|ordering| ordering.name() == s)
+            .ok_or_else(|| format!("Unknown event ordering '{}'", s))
+    }
+}
+
+/// A candidate event with the keys the orderings compare, computed once so that
+/// comparisons do not allocate.
+#[derive(Debug, Clone)]
+pub struct OrderedEvent<'a> {
+    /// Position of the rule in the rule set
+    pub rule_index: usize,
+
+    /// The match to apply
+    pub pattern_match: &'a PatternMatch,
+
+    /// Age of each matched relation in pattern order, as (creation timestamp, ID)
+    ages: Vec<(u64, RelationId)>,
+
+    /// Ages sorted oldest first
+    sorted_ages: Vec<(u64, RelationId)>,
+
+    /// Ages sorted newest first
+    reverse_sorted_ages: Vec<(u64, RelationId)>,
+
+    /// Tie-breaking key for `EventOrdering::Random`
+    random_key: u64,
+}
+
+impl<'a> OrderedEvent<'a> {
+    /// Prepares a candidate event for ordering. `random_key` is only compared by
+    /// `EventOrdering::Random`.
+    pub fn new(rule_index: usize, pattern_match: &'a PatternMatch, hypergraph: &Hypergraph, random_key: u64) -> Self {
+        let ages: Vec<(u64, RelationId)> = pattern_match
+            .matched_relations
+            .iter()
+            .map(|id| (hypergraph.get_relation(*id).map_or(0, |relation| relation.created_at()), *id))
+            .collect();
+        let mut sorted_ages = ages.clone();
+        sorted_ages.sort();
+        let reverse_sorted_ages = sorted_ages.iter().rev().copied().collect();
+        OrderedEvent { rule_index, pattern_match, ages, sorted_ages, reverse_sorted_ages, random_key }
+    }
+}
+
+/// Compares two candidate events under a priority list of orderings.
+/// `Ordering::Less` means `a` should be applied first. Events that tie under every
+/// ordering are ordered by rule index and then matched relations, so the result is
+/// always deterministic.
+pub fn compare_events(orderings: &[EventOrdering], a: &OrderedEvent, b: &OrderedEvent) -> Ordering {
+    orderings
+        .iter()
+        .map(|ordering| match ordering {
+            EventOrdering::OldestEdge => a.sorted_ages.cmp(&b.sorted_ages),
+            EventOrdering::LeastOldEdge => b.sorted_ages.cmp(&a.sorted_ages),
+            EventOrdering::LeastRecentEdge => a.reverse_sorted_ages.cmp(&b.reverse_sorted_ages),
+            EventOrdering::NewestEdge => b.reverse_sorted_ages.cmp(&a.reverse_sorted_ages),
+            EventOrdering::RuleOrdering => a.ages.cmp(&b.ages),
+            EventOrdering::ReverseRuleOrdering => b.ages.cmp(&a.ages),
+            EventOrdering::RuleIndex => a.rule_index.cmp(&b.rule_index),
+            EventOrdering::ReverseRuleIndex => b.rule_index.cmp(&a.rule_index),
+            EventOrdering::Random => a.random_key.cmp(&b.random_key),
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| {
+            (a.rule_index, &a.pattern_match.matched_relations).cmp(&(b.rule_index, &b.pattern_match.matched_relations))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::pattern::Binding;
+
+    /// Relations 0..4 created in order; returns the graph and a match over the given relations.
+    fn setup() -> Hypergraph {
+        let mut hypergraph = Hypergraph::new();
+        let atom = hypergraph.create_atom();
+        for _ in 0..4 {
+            hypergraph.create_relation(vec![atom]);
+        }
+        hypergraph
+    }
+
+    fn matched(relations: &[u64]) -> PatternMatch {
+        PatternMatch::new(Binding::new(), relations.iter().copied().map(RelationId::new).collect())
+    }
+
+    fn best(orderings: &[EventOrdering], candidates: &[(usize, PatternMatch)]) -> usize {
+        let hypergraph = setup();
+        let events: Vec<OrderedEvent> = candidates
+            .iter()
+            .map(|(rule_index, m)| OrderedEvent::new(*rule_index, m, &hypergraph, 0))
+            .collect();
+        (0..events.len())
+            .min_by(|&i, &j| compare_events(orderings, &events[i], &events[j]))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_edge_age_orderings() {
+        // {0,3} has the oldest edge; {1,2} has the least recent newest edge
+        let candidates = [(0, matched(&[0, 3])), (0, matched(&[2, 1]))];
+        assert_eq!(best(&[EventOrdering::OldestEdge], &candidates), 0);
+        assert_eq!(best(&[EventOrdering::LeastOldEdge], &candidates), 1);
+        assert_eq!(best(&[EventOrdering::LeastRecentEdge], &candidates), 1);
+        assert_eq!(best(&[EventOrdering::NewestEdge], &candidates), 0);
+        // In pattern order, {0,3} starts older than {2,1}
+        assert_eq!(best(&[EventOrdering::RuleOrdering], &candidates), 0);
+        assert_eq!(best(&[EventOrdering::ReverseRuleOrdering], &candidates), 1);
+    }
+
+    #[test]
+    fn test_priority_list() {
+        // Same edges in different pattern order, for two rules
+        let candidates = [(1, matched(&[0, 1])), (0, matched(&[1, 0])), (0, matched(&[0, 1]))];
+        assert_eq!(best(&[EventOrdering::RuleIndex], &candidates), 2);
+        assert_eq!(best(&[EventOrdering::LeastRecentEdge, EventOrdering::ReverseRuleIndex], &candidates), 0);
+        assert_eq!(best(&EventOrdering::standard(), &candidates), 2);
+        assert_eq!(best(&[EventOrdering::ReverseRuleOrdering, EventOrdering::RuleIndex], &candidates), 1);
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for ordering in EventOrdering::ALL {
+            assert_eq!(ordering.to_string().parse::<EventOrdering>(), Ok(ordering));
+        }
+        assert!("Oldest".parse::<EventOrdering>().is_err());
+    }
+}
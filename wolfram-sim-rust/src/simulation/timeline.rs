@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+
+use crate::evolution::apply_rule;
+use crate::hypergraph::Hypergraph;
+use crate::matching::PatternMatch;
+use crate::rules::{pattern::{Binding, PatternElement}, rule::RuleSet, Rule};
+use crate::serialization::persistence::{PersistenceManager, PersistenceResult, SaveConfig, SnapshotFormat};
+use super::event::{HypergraphState, SimulationEvent};
+use super::history::events_to_forget;
+
+/// Largest number of checkpoints a timeline keeps unless configured otherwise.
+pub const DEFAULT_MAX_CHECKPOINTS: usize = 32;
+
+/// Start of the keys checkpoints are saved under, followed by the step number.
+pub const CHECKPOINT_KEY_PREFIX: &str = "checkpoint_step_";
+
+/// A stored snapshot of the simulation state.
+#[derive(Debug)]
+enum Checkpoint {
+    /// Kept in memory
+    Memory(Box<HypergraphState>),
+    /// Saved through the persistence layer under a key
+    Stored(String),
+}
+
+/// Record of a run that can reconstruct the state at any step it covers, from a full
+/// state checkpointed at the start and a log of the events since. A past state is
+/// rebuilt by loading the nearest earlier checkpoint and replaying the events after it.
+///
+/// The default timeline keeps only the first checkpoint. Periodic checkpoints every
+/// `checkpoint_interval` steps, which make reconstruction faster, are opt-in: `new` keeps
+/// them in memory and `with_persistence` saves them in a store. At most `max_checkpoints`
+/// are kept; beyond that they are thinned out so that the gaps between them grow with
+/// their age, and recent steps stay quickest to rebuild.
+///
+/// With a `max_events` limit, the oldest events are forgotten and the first checkpoint
+/// moves forward, to a later checkpoint or to a state rebuilt by replaying the forgotten
+/// events, so the log holds at most `max_events` events.
+#[derive(Debug)]
+pub struct Timeline {
+    /// Number of steps between periodic checkpoints, if any
+    checkpoint_interval: Option<u64>,
+
+    /// Largest number of checkpoints kept
+    max_checkpoints: usize,
+
+    /// Checkpoints by step number
+    checkpoints: BTreeMap<u64, Checkpoint>,
+
+    /// Events after the first checkpoint, in step order
+    events: Vec<SimulationEvent>,
+
+    /// Where checkpoints are saved, if they are not kept in memory
+    persistence: Option<PersistenceManager>,
+
+    /// Largest number of events logged, or `None` to keep every event
+    max_events: Option<usize>,
+}
+
+impl Timeline {
+    /// Creates a timeline keeping a checkpoint every `checkpoint_interval` steps in memory.
+    pub fn new(checkpoint_interval: u64) -> Self {
+        Timeline {
+            checkpoint_interval: Some(checkpoint_interval.max(1)),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a timeline saving a checkpoint every `checkpoint_interval` steps in the
+    /// store of `persistence`.
+    pub fn with_persistence(checkpoint_interval: u64, persistence: PersistenceManager) -> Self {
+        Timeline {
+            persistence: Some(persistence),
+            ..Self::new(checkpoint_interval)
+        }
+    }
+
+    /// Keeps at most `max_checkpoints` checkpoints (at least two).
+    pub fn with_max_checkpoints(mut self, max_checkpoints: usize) -> Self {
+        self.max_checkpoints = max_checkpoints.max(2);
+        self
+    }
+
+    /// Returns the number of steps between periodic checkpoints, if any.
+    pub fn checkpoint_interval(&self) -> Option<u64> {
+        self.checkpoint_interval
+    }
+
+    /// Returns the largest number of checkpoints kept.
+    pub fn max_checkpoints(&self) -> usize {
+        self.max_checkpoints
+    }
+
+    /// Returns the largest number of events logged, if limited.
+    pub fn max_events(&self) -> Option<usize> {
+        self.max_events
+    }
+
+    /// Limits the number of events logged. Events beyond the limit are forgotten when
+    /// the next event is recorded.
+    pub fn set_max_events(&mut self, max_events: Option<usize>) {
+        self.max_events = max_events;
+    }
+
+    /// Returns true if nothing has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+
+    /// Returns the earliest step that can be reconstructed.
+    pub fn first_step(&self) -> Option<u64> {
+        self.checkpoints.keys().next().copied()
+    }
+
+    /// Returns the latest step that can be reconstructed.
+    pub fn last_step(&self) -> Option<u64> {
+        self.events.last().map(|event| event.step_number()).or_else(|| self.first_step())
+    }
+
+    /// Returns the step numbers of all checkpoints.
+    pub fn checkpoint_steps(&self) -> impl Iterator<Item = u64> + '_ {
+        self.checkpoints.keys().copied()
+    }
+
+    /// Returns the logged events, in step order.
+    pub fn events(&self) -> &[SimulationEvent] {
+        &self.events
+    }
+
+    /// Returns true if a checkpoint is due at `step_number`.
+    pub fn is_checkpoint_due(&self, step_number: u64) -> bool {
+        self.is_empty() || self.checkpoint_interval.is_some_and(|interval| step_number.is_multiple_of(interval))
+    }
+
+    /// Stores a checkpoint of `state`, replacing any earlier one for the same step.
+    pub fn record_checkpoint(&mut self, state: &HypergraphState) -> PersistenceResult<()> {
+        let checkpoint = match self.persistence.as_mut() {
+            Some(persistence) => {
+                let key = format!("{}{}", CHECKPOINT_KEY_PREFIX, state.step_number());
+                let config = SaveConfig {
+                    overwrite_existing: true,
+                    format: SnapshotFormat::CompressedBinary,
+                    ..SaveConfig::default()
+                };
+                Checkpoint::Stored(persistence.save_hypergraph_state(state, Some(&key), Some(config))?.key)
+            }
+            None => Checkpoint::Memory(Box::new(state.clone())),
+        };
+        self.checkpoints.insert(state.step_number(), checkpoint);
+        self.thin_checkpoints();
+        Ok(())
+    }
+
+    /// Drops checkpoints until at most `max_checkpoints` remain. The first and latest are
+    /// always kept; otherwise the one dropped leaves the smallest gap relative to its age,
+    /// so the number of checkpoints grows only logarithmically with the steps covered.
+    fn thin_checkpoints(&mut self) {
+        while self.checkpoints.len() > self.max_checkpoints {
+            let latest = self.last_step().unwrap_or(0);
+            let steps: Vec<u64> = self.checkpoints.keys().copied().collect();
+            let gap_per_age = |window: &[u64]| (window[2] - window[0]) as f64 / (latest - window[1] + 1) as f64;
+            let Some(step) = steps
+                .windows(3)
+                .min_by(|a, b| gap_per_age(a).total_cmp(&gap_per_age(b)))
+                .map(|window| window[1])
+            else {
+                return;
+            };
+            if let Some(checkpoint) = self.checkpoints.remove(&step) {
+                self.discard(checkpoint);
+            }
+        }
+    }
+
+    /// Appends an event to the log, forgetting the oldest events if there are more than
+    /// `max_events`. Events must be recorded in step order, after a checkpoint of the
+    /// state they were applied to, and `rule_set` must hold the rules that applied them.
+    ///
+    /// Moving the first checkpoint forward may require saving a rebuilt state. If that
+    /// fails, the timeline starts over and the error is returned.
+    pub fn record_event(&mut self, event: SimulationEvent, rule_set: &RuleSet) -> Result<(), String> {
+        self.events.push(event);
+        self.forget_oldest(rule_set).inspect_err(|_| self.clear())
+    }
+
+    /// Moves the first checkpoint past the events due to be forgotten: to the earliest
+    /// checkpoint after them, or else to a new one rebuilt by replaying them.
+    fn forget_oldest(&mut self, rule_set: &RuleSet) -> Result<(), String> {
+        let forget = events_to_forget(self.events.len(), self.max_events);
+        if forget == 0 {
+            return Ok(());
+        }
+        let last_forgotten = self.events[forget - 1].step_number();
+        let first = match self.checkpoints.range(last_forgotten..).next() {
+            Some((&step, _)) => step,
+            None => {
+                let state = self.state_at(last_forgotten, rule_set)?;
+                self.record_checkpoint(&state)
+                    .map_err(|e| format!("Failed to checkpoint step {}: {}", last_forgotten, e))?;
+                last_forgotten
+            }
+        };
+
+        let kept = self.checkpoints.split_off(&first);
+        for (_, checkpoint) in std::mem::replace(&mut self.checkpoints, kept) {
+            self.discard(checkpoint);
+        }
+        let forget = self.events.partition_point(|event| event.step_number() <= first);
+        self.events.drain(..forget);
+        Ok(())
+    }
+
+    /// Forgets everything after `step_number`, e.g. after an undo.
+    pub fn truncate(&mut self, step_number: u64) {
+        let keep = self.events.partition_point(|event| event.step_number() <= step_number);
+        self.events.truncate(keep);
+        for (_, checkpoint) in self.checkpoints.split_off(&(step_number + 1)) {
+            self.discard(checkpoint);
+        }
+    }
+
+    /// Forgets all checkpoints and events.
+    pub fn clear(&mut self) {
+        for (_, checkpoint) in std::mem::take(&mut self.checkpoints) {
+            self.discard(checkpoint);
+        }
+        self.events.clear();
+    }
+
+    /// Reconstructs the state at `step_number` by replaying the logged events from the
+    /// nearest earlier checkpoint with the rules of `rule_set`. Replayed states do not
+    /// carry the random number generator; only checkpoints do.
+    pub fn state_at(&self, step_number: u64, rule_set: &RuleSet) -> Result<HypergraphState, String> {
+        let (first, last) = match (self.first_step(), self.last_step()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err("No steps have been recorded".to_string()),
+        };
+        if step_number < first || step_number > last {
+            return Err(format!("Step {} is outside the recorded range {}..={}", step_number, first, last));
+        }
+
+        let (&checkpoint_step, checkpoint) = self
+            .checkpoints
+            .range(..=step_number)
+            .next_back()
+            .expect("the first checkpoint precedes every recorded step");
+        let checkpoint = self.load(checkpoint)?;
+        if checkpoint_step == step_number {
+            return Ok(checkpoint);
+        }
+
+        let mut hypergraph = checkpoint.to_hypergraph();
+        let start = self.events.partition_point(|event| event.step_number() <= checkpoint_step);
+        let end = self.events.partition_point(|event| event.step_number() <= step_number);
+        for event in &self.events[start..end] {
+            let rule = rule_set
+                .get_rule(event.rule_id())
+                .ok_or_else(|| format!("Step {} applied unknown rule {}", event.step_number(), event.rule_id().value()))?;
+            let pattern_match = recorded_match(rule, event, &hypergraph)?;
+            let result = apply_rule(&mut hypergraph, rule, &pattern_match);
+            if !result.success
+                || result.new_atoms != event.atoms_created()
+                || result.new_relations != event.relations_created()
+            {
+                return Err(format!("Replaying step {} did not reproduce the recorded event", event.step_number()));
+            }
+        }
+
+        Ok(HypergraphState::new(
+            hypergraph.get_all_atoms(),
+            hypergraph.get_all_relations(),
+            step_number,
+            hypergraph.next_atom_id(),
+            hypergraph.next_relation_id(),
+        )
+        .with_next_timestamp(hypergraph.next_timestamp()))
+    }
+
+    /// Reads a checkpoint back.
+    fn load(&self, checkpoint: &Checkpoint) -> Result<HypergraphState, String> {
+        match (checkpoint, &self.persistence) {
+            (Checkpoint::Memory(state), _) => Ok(state.as_ref().clone()),
+            (Checkpoint::Stored(key), Some(persistence)) => persistence
+                .load_hypergraph_state(key)
+                .map_err(|e| format!("Failed to load checkpoint: {}", e)),
+            (Checkpoint::Stored(key), None) => Err(format!("No persistence configured for checkpoint {}", key)),
+        }
+    }
+
+    /// Deletes the stored snapshot behind a dropped checkpoint, if any.
+    fn discard(&mut self, checkpoint: Checkpoint) {
+        if let (Checkpoint::Stored(key), Some(persistence)) = (checkpoint, self.persistence.as_mut()) {
+            // A leftover snapshot is harmless; it is overwritten if the step is checkpointed again
+            let _ = persistence.delete_hypergraph(&key);
+        }
+    }
+}
+
+/// Recovers the match a recorded event applied `rule` to, from the relations it
+/// removed; these are the matched relations, in pattern order.
+pub(crate) fn recorded_match(rule: &Rule, event: &SimulationEvent, hypergraph: &Hypergraph) -> Result<PatternMatch, String> {
+    let mut binding = Binding::new();
+    for (pattern_relation, relation_id) in rule.pattern().relations().iter().zip(event.relations_removed()) {
+        let relation = hypergraph
+            .get_relation(*relation_id)
+            .ok_or_else(|| format!("Step {} removed missing relation {}", event.step_number(), relation_id.value()))?;
+        for (element, &atom_id) in pattern_relation.elements().iter().zip(relation.atoms()) {
+            if let PatternElement::Variable(variable) = element {
+                binding.bind(variable.clone(), atom_id);
+            }
+        }
+    }
+    Ok(PatternMatch::new(binding, event.relations_removed().to_vec()))
+}
+
+impl Default for Timeline {
+    /// A timeline keeping only its first checkpoint, in memory.
+    fn default() -> Self {
+        Timeline {
+            checkpoint_interval: None,
+            max_checkpoints: DEFAULT_MAX_CHECKPOINTS,
+            checkpoints: BTreeMap::new(),
+            events: Vec::new(),
+            persistence: None,
+            max_events: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::SimulationManager;
+    use crate::serialization::PredefinedExamples;
+    use tempfile::TempDir;
+
+    fn run(timeline: Timeline, steps: u64) -> (SimulationManager, Vec<HypergraphState>) {
+        run_with_retention(timeline, steps, None)
+    }
+
+    fn run_with_retention(timeline: Timeline, steps: u64, retention: Option<usize>) -> (SimulationManager, Vec<HypergraphState>) {
+        let initial = PredefinedExamples::single_edge();
+        let mut manager = SimulationManager::from_state(&initial, RuleSet::create_basic_ruleset()).unwrap();
+        manager.set_event_retention(retention);
+        manager.set_timeline(timeline);
+        let mut states = vec![manager.get_current_state()];
+        for _ in 0..steps {
+            assert!(manager.step().success);
+            states.push(manager.get_current_state());
+        }
+        (manager, states)
+    }
+
+    fn without_rng(state: &HypergraphState) -> HypergraphState {
+        HypergraphState { rng: None, ..state.clone() }
+    }
+
+    #[test]
+    fn test_state_at_replays_from_checkpoints() {
+        let (manager, states) = run(Timeline::new(4), 10);
+        let timeline = manager.timeline();
+        assert_eq!(timeline.checkpoint_steps().collect::<Vec<_>>(), vec![0, 4, 8]);
+
+        for (step, expected) in states.iter().enumerate() {
+            let state = manager.state_at_step(step as u64).unwrap();
+            assert_eq!(without_rng(&state), without_rng(expected), "step {}", step);
+        }
+        assert!(manager.state_at_step(11).is_err());
+    }
+
+    #[test]
+    fn test_truncate_after_undo() {
+        let (mut manager, states) = run(Timeline::new(4), 10);
+        manager.undo(5);
+
+        let timeline = manager.timeline();
+        assert_eq!(timeline.last_step(), Some(5));
+        assert_eq!(timeline.checkpoint_steps().collect::<Vec<_>>(), vec![0, 4]);
+        assert_eq!(without_rng(&manager.state_at_step(5).unwrap()), without_rng(&states[5]));
+
+        // Stepping again records the new branch
+        manager.step();
+        assert_eq!(manager.timeline().last_step(), Some(6));
+    }
+
+    #[test]
+    fn test_checkpoints_on_disk() {
+        let temp_dir = TempDir::new().unwrap();
+        let persistence = PersistenceManager::with_save_directory(temp_dir.path());
+        let (mut manager, states) = run(Timeline::with_persistence(3, persistence), 7);
+
+        let snapshot_files = || {
+            std::fs::read_dir(temp_dir.path())
+                .unwrap()
+                .filter(|entry| entry.as_ref().unwrap().path().extension().is_some_and(|e| e == "hgb"))
+                .count()
+        };
+        assert_eq!(snapshot_files(), 3);
+        assert_eq!(without_rng(&manager.state_at_step(5).unwrap()), without_rng(&states[5]));
+
+        manager.undo(2);
+        assert_eq!(snapshot_files(), 2);
+    }
+
+    #[test]
+    fn test_default_timeline_keeps_one_checkpoint() {
+        let (manager, states) = run_with_retention(Timeline::default(), 40, Some(16));
+        let timeline = manager.timeline();
+        assert_eq!(timeline.checkpoint_interval(), None);
+        assert_eq!(timeline.checkpoint_steps().count(), 1);
+        assert!(timeline.events().len() <= 16);
+
+        // The first checkpoint was rebuilt past the forgotten events
+        let first = timeline.first_step().unwrap();
+        assert!(first > 0);
+        for step in first..=40 {
+            let state = manager.state_at_step(step).unwrap();
+            assert_eq!(without_rng(&state), without_rng(&states[step as usize]), "step {}", step);
+        }
+    }
+
+    #[test]
+    fn test_checkpoints_thin_out_with_age() {
+        let (manager, states) = run(Timeline::new(1).with_max_checkpoints(8), 200);
+        let steps: Vec<u64> = manager.timeline().checkpoint_steps().collect();
+        assert_eq!(steps.len(), 8);
+        assert_eq!((steps[0], steps[7]), (0, 200));
+
+        // Older checkpoints are further apart than recent ones
+        let gaps: Vec<u64> = steps.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        assert!(gaps[0] > gaps[gaps.len() - 1], "{:?}", steps);
+        for step in (0..=200).step_by(7) {
+            let state = manager.state_at_step(step).unwrap();
+            assert_eq!(without_rng(&state), without_rng(&states[step as usize]), "step {}", step);
+        }
+    }
+
+    #[test]
+    fn test_thinned_checkpoints_are_deleted_from_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let persistence = PersistenceManager::with_save_directory(temp_dir.path());
+        let (manager, _) = run(Timeline::with_persistence(2, persistence).with_max_checkpoints(3), 20);
+        assert_eq!(manager.timeline().checkpoint_steps().count(), 3);
+        let snapshot_files = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension().is_some_and(|e| e == "hgb"))
+            .count();
+        assert_eq!(snapshot_files, 3);
+    }
+
+    #[test]
+    fn test_max_events_drops_oldest_checkpoints() {
+        let (manager, states) = run_with_retention(Timeline::new(4), 20, Some(6));
+        let timeline = manager.timeline();
+        assert_eq!(timeline.max_events(), Some(6));
+        assert!(timeline.events().len() <= 6);
+        assert_eq!(timeline.checkpoint_steps().next(), timeline.first_step());
+        assert_eq!(timeline.last_step(), Some(20));
+
+        let first = timeline.first_step().unwrap();
+        assert!(first > 0);
+        assert!(manager.state_at_step(first - 1).is_err());
+        for step in first..=20 {
+            let state = manager.state_at_step(step).unwrap();
+            assert_eq!(without_rng(&state), without_rng(&states[step as usize]), "step {}", step);
+        }
+    }
+}